@@ -0,0 +1,122 @@
+// Minimal desktop build: board vẽ bằng sprite, click 2 ô kề nhau để swap.
+// Run with: cargo run --example bevy_desktop --features bevy
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use core_rust::api::GameConfig;
+use core_rust::bevy_adapter::{CoreEvent, CorePlugin, MatchGame, SwapRejected, SwapRequest};
+
+const TILE: f32 = 64.0;
+
+#[derive(Component)]
+struct Tile(usize);
+
+#[derive(Resource, Default)]
+struct Selected(Option<(usize, usize)>);
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(CorePlugin {
+            config: GameConfig { seed: 42, ..GameConfig::default() },
+            ..CorePlugin::default()
+        })
+        .init_resource::<Selected>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, (handle_click, sync_tiles, log_events))
+        .run();
+}
+
+fn element_color(element: u8) -> Color {
+    match element {
+        1 => Color::srgb(0.85, 0.85, 0.85), // Kim
+        2 => Color::srgb(0.2, 0.7, 0.3),    // Mộc
+        3 => Color::srgb(0.2, 0.4, 0.9),    // Thủy
+        4 => Color::srgb(0.9, 0.25, 0.2),   // Hỏa
+        5 => Color::srgb(0.7, 0.5, 0.2),    // Thổ
+        10 => Color::srgb(0.35, 0.35, 0.35), // Đá
+        _ => Color::srgb(0.05, 0.05, 0.05),
+    }
+}
+
+// Toạ độ world của tâm ô (x, y); y của board hướng xuống
+fn tile_center(game: &MatchGame, x: usize, y: usize) -> Vec2 {
+    let w = game.0.width() as f32;
+    let h = game.0.height() as f32;
+    Vec2::new((x as f32 - (w - 1.0) / 2.0) * TILE, ((h - 1.0) / 2.0 - y as f32) * TILE)
+}
+
+fn setup(mut commands: Commands, game: Res<MatchGame>) {
+    commands.spawn(Camera2dBundle::default());
+    let width = game.0.width();
+    for (idx, cell) in game.0.cells().iter().enumerate() {
+        let pos = tile_center(&game, idx % width, idx / width);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: element_color(cell.element),
+                    custom_size: Some(Vec2::splat(TILE - 4.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(pos.x, pos.y, 0.0),
+                ..default()
+            },
+            Tile(idx),
+        ));
+    }
+}
+
+fn handle_click(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    game: Res<MatchGame>,
+    mut selected: ResMut<Selected>,
+    mut swaps: EventWriter<SwapRequest>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) { return; }
+    let (camera, cam_transform) = cameras.single();
+    let Some(world) = windows
+        .single()
+        .cursor_position()
+        .and_then(|p| camera.viewport_to_world_2d(cam_transform, p))
+    else { return; };
+
+    let origin = tile_center(&game, 0, 0);
+    let fx = ((world.x - origin.x) / TILE).round();
+    let fy = ((origin.y - world.y) / TILE).round();
+    if fx < 0.0 || fy < 0.0 || fx as usize >= game.0.width() || fy as usize >= game.0.height() {
+        selected.0 = None;
+        return;
+    }
+    let cell = (fx as usize, fy as usize);
+
+    match selected.0.take() {
+        Some(first) if first.0.abs_diff(cell.0) + first.1.abs_diff(cell.1) == 1 => {
+            swaps.send(SwapRequest { a: first, b: cell });
+        }
+        _ => selected.0 = Some(cell),
+    }
+}
+
+fn sync_tiles(game: Res<MatchGame>, selected: Res<Selected>, mut tiles: Query<(&Tile, &mut Sprite)>) {
+    let width = game.0.width();
+    for (tile, mut sprite) in &mut tiles {
+        let cell = game.0.cells()[tile.0];
+        let is_selected = selected.0 == Some((tile.0 % width, tile.0 / width));
+        sprite.color = if is_selected { Color::WHITE } else { element_color(cell.element) };
+    }
+}
+
+fn log_events(
+    game: Res<MatchGame>,
+    mut events: EventReader<CoreEvent>,
+    mut rejected: EventReader<SwapRejected>,
+) {
+    if events.read().count() > 0 {
+        info!("score: {}", game.0.score());
+    }
+    for r in rejected.read() {
+        info!("swap rejected: {:?}", r.reason);
+    }
+}
@@ -0,0 +1,46 @@
+// Replay 1 ván thành GIF để đính kèm bug report / balance review.
+// Run with: cargo run --example replay_gif --features gif-export -- <width> <height> <seed> <out.gif> [moves.bin]
+// moves.bin: [x1, y1, x2, y2] mỗi nước (cùng format validate_replay). Không có -> tự chơi demo 10 nước.
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use core_rust::api::{Game, GameConfig};
+use core_rust::gif_export::{export_replay_gif, GifOptions};
+
+// Demo: mỗi lượt lấy swap ngang hợp lệ đầu tiên
+fn demo_moves(config: &GameConfig, turns: usize) -> Vec<u8> {
+    let mut game = Game::new(config);
+    let mut moves = Vec::new();
+    for _ in 0..turns {
+        let found = (0..config.height)
+            .flat_map(|y| (0..config.width - 1).map(move |x| (x, y)))
+            .find(|&(x, y)| game.swap((x, y), (x + 1, y)).is_ok());
+        let Some((x, y)) = found else { break };
+        moves.extend([x as u8, y as u8, x as u8 + 1, y as u8]);
+        game.run_until_stable(1000);
+    }
+    moves
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 5 {
+        eprintln!("usage: replay_gif <width> <height> <seed> <out.gif> [moves.bin]");
+        std::process::exit(2);
+    }
+    let config = GameConfig {
+        width: args[1].parse().expect("width"),
+        height: args[2].parse().expect("height"),
+        seed: args[3].parse().expect("seed"),
+        ..GameConfig::default()
+    };
+    let moves = match args.get(5) {
+        Some(path) => std::fs::read(path).expect("read moves"),
+        None => demo_moves(&config, 10),
+    };
+
+    let out = BufWriter::new(File::create(&args[4]).expect("create output"));
+    let frames = export_replay_gif(&config, &moves, &GifOptions::default(), out).expect("encode gif");
+    println!("{} moves, {} frames -> {}", moves.len() / 4, frames, args[4]);
+}
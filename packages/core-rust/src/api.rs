@@ -0,0 +1,343 @@
+//! Native Rust API (server-side validation, tools, native engine integrations).
+//!
+//! Không có wasm_bindgen, không có raw pointer: dữ liệu trả về là slice / Vec có kiểu.
+//! Lớp wasm (`GridState`, `Simulation`) và API này dùng chung logic trong `sim::grid`;
+//! factory wasm `create_grid` dựng ván qua `GameConfig` / `Game::new` như client native.
+
+pub use crate::sim::cycle::{CycleMode, CycleState, CycleTracks};
+pub use crate::sim::events::{CycleMilestoneInfo, CycleMilestoneKind, EventInfo, EventType, GridEvent, SwapRejectReason};
+pub use crate::sim::grid::{Cell, InteractionRule, ResolutionPolicy};
+pub use crate::sim::photo::{PhotoEntity, PhotoState};
+pub use crate::sim::seal::{open as open_save, seal as seal_save, wrap_plaintext, SealError};
+pub use crate::sim::scoring::{ActiveMultipliers, ScoreBreakdown, ScoreDecay};
+pub use crate::sim::drought::DroughtPity;
+pub use crate::sim::noise::BoardNoise;
+pub use crate::sim::stats::{GameSummary, SessionStats};
+pub use crate::sim::xp::{XpCurve, XpProgress, XpRewards};
+pub use crate::sim::moves::{decode_moves, encode_moves};
+pub use crate::sim::rng::{daily_seed, is_legacy_seed, legacy_seed, seed_from_string, RngStream, SharedRng};
+pub use crate::sim::replay::{
+    check_replay_moves, first_divergence, verify_replay_file, verify_replay_summary, ReplayError, ReplayHeader, ReplayLimits, ReplayRules, ReplayRejection, ReplayStep,
+};
+pub use crate::sim::snapshot::{SnapshotError, SNAPSHOT_VERSION};
+pub use crate::sim::diff::{diagnose_replay, CellDiff, FieldDiff, ReplayDiagnosis, StateDiff};
+
+use crate::sim::grid::GridState;
+use crate::sim::replay::{replay_checked, replay_steps};
+
+/// Cấu hình 1 ván. `GameConfig::default()` = ruleset chính thức trên board 8x8.
+#[derive(Clone, Debug)]
+pub struct GameConfig {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64, // legacy_seed(..) cho ván tạo bằng RNG ChaCha8 cũ
+    pub cycle_mode: CycleMode,
+    pub dual_cycle: bool,
+    /// Số match lệch target được tha mỗi chuỗi (0 = đứt chuỗi ngay)
+    pub cycle_grace: u32,
+    /// Số lượt liền không tiến chuỗi trước khi multiplier giảm 1 bậc (0 = tắt)
+    pub momentum_decay_turns: u32,
+    /// Charge booster skip target người chơi mang vào ván
+    pub cycle_skip_charges: u32,
+    pub resolution_policy: ResolutionPolicy,
+    /// Booster / level modifier theo phần trăm (100 = x1)
+    pub score_modifier_pct: u32,
+    /// Anti-farming cho endless mode, mặc định `ScoreDecay::OFF`
+    pub score_decay: ScoreDecay,
+    /// Bù element bị hạn khi refill, mặc định `DroughtPity::OFF`
+    pub drought_pity: DroughtPity,
+    /// Board đầu theo cụm noise, mặc định `BoardNoise::OFF` (random đều)
+    pub board_noise: BoardNoise,
+    /// Curve level và XP thưởng, mặc định `XpCurve::DEFAULT` / `XpRewards::DEFAULT`
+    pub xp_curve: XpCurve,
+    pub xp_rewards: XpRewards,
+    /// A/B bucket (None = không tham gia), ghi đè mode / policy / modifier ở trên
+    pub experiment_bucket: Option<u32>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            width: 8,
+            height: 8,
+            seed: 0,
+            cycle_mode: CycleMode::Generation,
+            dual_cycle: false,
+            cycle_grace: 0,
+            momentum_decay_turns: 0,
+            cycle_skip_charges: 0,
+            resolution_policy: ResolutionPolicy::FoundOrder,
+            score_modifier_pct: 100,
+            score_decay: ScoreDecay::OFF,
+            drought_pity: DroughtPity::OFF,
+            board_noise: BoardNoise::OFF,
+            xp_curve: XpCurve::DEFAULT,
+            xp_rewards: XpRewards::DEFAULT,
+            experiment_bucket: None,
+        }
+    }
+}
+
+/// 1 ván chơi. Toạ độ là (x, y), gốc ở góc trên-trái.
+pub struct Game {
+    grid: GridState,
+}
+
+impl Game {
+    pub fn new(config: &GameConfig) -> Self {
+        let noise = config.board_noise;
+        let mut grid = GridState::new_with_noise(config.width, config.height, config.seed, noise.scale, noise.cluster_pct);
+        grid.set_cycle_mode(config.cycle_mode);
+        grid.set_dual_cycle(config.dual_cycle);
+        grid.set_cycle_grace(config.cycle_grace);
+        grid.set_momentum_decay(config.momentum_decay_turns);
+        grid.set_cycle_skip_charges(config.cycle_skip_charges);
+        grid.set_resolution_policy(config.resolution_policy);
+        grid.set_score_modifier(config.score_modifier_pct);
+        let decay = config.score_decay;
+        grid.set_score_decay(decay.element_step_pct, decay.region_step_pct, decay.floor_pct);
+        let pity = config.drought_pity;
+        grid.set_drought_pity(pity.threshold, pity.step_pct, pity.max_boost_pct);
+        let (curve, rewards) = (config.xp_curve, config.xp_rewards);
+        grid.set_xp_curve(curve.base, curve.growth_pct, curve.max_level);
+        grid.set_xp_rewards(rewards.per_match, rewards.per_extra_cell, rewards.per_interaction);
+        if let Some(bucket) = config.experiment_bucket {
+            grid.set_experiment_bucket(bucket);
+        }
+        Self { grid }
+    }
+
+    // Lớp wasm (create_grid) dựng ván qua GameConfig rồi lấy GridState ra export cho JS
+    pub(crate) fn into_grid(self) -> GridState {
+        self.grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.get_width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.get_height()
+    }
+
+    /// Ô tại (x, y), None nếu ngoài board
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        if x >= self.width() || y >= self.height() { return None; }
+        self.grid.cells().get(y * self.width() + x).copied()
+    }
+
+    /// Toàn bộ board theo hàng (index = y * width + x)
+    pub fn cells(&self) -> &[Cell] {
+        self.grid.cells()
+    }
+
+    /// Swap 2 ô. Err = bị từ chối hoặc không tạo match (board giữ nguyên).
+    /// Swap hợp lệ chỉ bắt đầu resolve; gọi `step` / `run_until_stable` để chạy cascade.
+    pub fn swap(&mut self, a: (usize, usize), b: (usize, usize)) -> Result<(), SwapRejectReason> {
+        self.grid.swap_at_checked(a, b)
+    }
+
+    /// Chạy đúng 1 tick (trọng lực, refill, resolve match)
+    pub fn step(&mut self) {
+        self.grid.tick();
+    }
+
+    /// Tick tới khi board ổn định hoặc hết `max_ticks`. Returns số tick đã chạy.
+    pub fn run_until_stable(&mut self, max_ticks: u32) -> u32 {
+        let mut ticks = 0;
+        while ticks < max_ticks {
+            self.grid.tick();
+            ticks += 1;
+            if self.grid.is_stable() { break; }
+        }
+        ticks
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.grid.is_stable()
+    }
+
+    pub fn score(&self) -> u64 {
+        self.grid.score_u64()
+    }
+
+    /// Đổi track chu trình chính giữa ván (xem `GridState::switch_cycle_track`)
+    pub fn switch_cycle_track(&mut self) -> CycleMode {
+        self.grid.switch_cycle_track()
+    }
+
+    /// Booster: tiêu 1 charge để bỏ qua target hiện tại (xem `GridState::skip_cycle_target`)
+    pub fn skip_cycle_target(&mut self) -> bool {
+        self.grid.skip_cycle_target()
+    }
+
+    /// Input đã chấp nhận từ đầu ván, phần moves của `replay_file`
+    pub fn move_journal(&self) -> Vec<u8> {
+        self.grid.get_move_journal()
+    }
+
+    /// `move_journal` nén bằng `encode_moves` (~1-2 byte / nước), giải nén bằng `decode_moves(width, ..)`
+    pub fn move_journal_compact(&self) -> Vec<u8> {
+        self.grid.get_move_journal_compact()
+    }
+
+    /// Snapshot nhị phân của cả ván (checkpoint server / suspend), đọc lại bằng `Game::from_bytes`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.grid.to_bytes()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Game, SnapshotError> {
+        GridState::from_snapshot(data).map(|grid| Self { grid })
+    }
+
+    /// Nâng save ghi ở `from_version` lên schema hiện tại (`SNAPSHOT_VERSION`)
+    pub fn migrate(data: &[u8], from_version: u16) -> Result<Vec<u8>, SnapshotError> {
+        GridState::migrate_snapshot(data, from_version)
+    }
+
+    /// Random [0, n) cho kỹ năng boss, stream riêng (`RngStream::Boss`) nên không làm lệch refill
+    pub fn boss_roll(&mut self, n: u32) -> u32 {
+        self.grid.boss_roll(n)
+    }
+
+    /// RNG hiển thị (particle...) theo seed ván + `key`, không ảnh hưởng gameplay
+    pub fn shared_rng(&self, key: u32) -> SharedRng {
+        self.grid.get_shared_rng(key)
+    }
+
+    /// Chỉ trạng thái RNG (đã nằm trong `to_bytes`), cho save lưu board riêng
+    pub fn rng_state(&self) -> Vec<u8> {
+        self.grid.get_rng_state()
+    }
+
+    /// Đặt lại RNG từ `rng_state`: refill tiếp theo ra đúng chuỗi của ván gốc
+    pub fn set_rng_state(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        self.grid.import_rng_state(data)
+    }
+
+    /// Header + journal cho validation service, kiểm tra bằng `verify_replay_file`
+    pub fn replay_file(&self) -> Vec<u8> {
+        self.grid.get_replay_file()
+    }
+
+    /// Bật / tắt ghi `move_journal` (mặc định bật)
+    pub fn record_moves(&mut self, enabled: bool) {
+        self.grid.record_moves(enabled);
+    }
+
+    /// Trạng thái chu trình (cả 2 track) để suspend / resume hoặc server dựng lại điểm giữa ván
+    pub fn cycle_state(&self) -> CycleTracks {
+        self.grid.cycle_tracks()
+    }
+
+    /// Returns false (không đổi gì) nếu state không hợp lệ với luật
+    pub fn restore_cycle_state(&mut self, tracks: CycleTracks) -> bool {
+        self.grid.restore_cycle_tracks(tracks)
+    }
+
+    /// Điểm theo nguồn (base / interaction / cascade / cycle / avatar), tổng = `score()`
+    pub fn score_breakdown(&self) -> ScoreBreakdown {
+        self.grid.score_breakdown()
+    }
+
+    /// Thống kê cả phiên (match theo element, cascade / chuỗi dài nhất, swap bị từ chối...)
+    pub fn session_stats(&self) -> SessionStats {
+        self.grid.session_stats()
+    }
+
+    /// XP tích lũy và level hiện tại theo `GameConfig::xp_curve`
+    pub fn xp_progress(&self) -> XpProgress {
+        self.grid.xp_progress()
+    }
+
+    pub fn active_multipliers(&self) -> ActiveMultipliers {
+        self.grid.active_multipliers()
+    }
+
+    /// Hash của ruleset đang chạy (xem `GridState::get_rules_hash`)
+    pub fn rules_hash(&self) -> u64 {
+        self.grid.get_rules_hash()
+    }
+
+    /// Kết quả ván (điểm, rules hash, checksum, thống kê), cùng giá trị với header của `replay_file`
+    pub fn summary(&self) -> GameSummary {
+        self.grid.game_summary()
+    }
+
+    /// Checksum trạng thái board, dùng để đối chiếu replay
+    pub fn checksum(&self) -> u32 {
+        self.grid.get_checksum()
+    }
+
+    /// Ô / field khác nhau giữa 2 ván (left = `self`), dùng khi checksum lệch
+    pub fn diff(&self, other: &Game) -> StateDiff {
+        crate::sim::diff::diff_states(&self.grid, &other.grid)
+    }
+
+    /// Hash 64 bit gồm cả điểm, chu trình và RNG (xem `GridState::get_state_hash`)
+    pub fn state_hash(&self) -> u64 {
+        self.grid.get_state_hash()
+    }
+
+    /// Ghi `state_hash` sau mỗi `every` tick cho lockstep (0 = tắt), xem `GridState::set_hash_trail`
+    pub fn set_hash_trail(&mut self, every: u32) {
+        self.grid.set_hash_trail(every);
+    }
+
+    /// (tick của phần tử đầu, hash), phần tử i ứng với tick đầu + i * every
+    pub fn hash_trail(&self) -> (u32, &[u64]) {
+        (self.grid.get_hash_trail_first_tick(), self.grid.hash_trail())
+    }
+
+    pub fn clear_hash_trail(&mut self) {
+        self.grid.clear_hash_trail();
+    }
+
+    /// Lấy ra mọi event từ lần drain trước (cũ -> mới)
+    pub fn drain_events(&mut self) -> Vec<GridEvent> {
+        self.grid.drain_events()
+    }
+}
+
+/// Replay `moves` (format `Game::move_journal`) trên ván dựng từ `config`, trả kết quả từng nước.
+/// Dùng với `first_divergence` để tìm nước đầu tiên client lệch.
+pub fn validate_replay_detailed(config: &GameConfig, moves: &[u8]) -> Vec<ReplayStep> {
+    replay_steps(&mut Game::new(config).grid, moves)
+}
+
+/// Replay `moves` trên ván dựng từ `config` sau khi qua `check_replay_moves`: replay giả mạo thô bị loại
+/// (`ReplayError::Rejected`) mà không phải simulate. `move_ticks` rỗng = không kiểm tra nhịp.
+pub fn validate_replay_checked(config: &GameConfig, moves: &[u8], move_ticks: &[u32], limits: &ReplayLimits) -> Result<u64, ReplayError> {
+    replay_checked(&mut Game::new(config).grid, moves, move_ticks, limits)
+}
+
+// --- PARALLEL (feature `parallel`, native) ---
+// Mỗi ván có grid / RNG / schedule riêng, không chia sẻ state: chạy song song
+// cho kết quả y hệt chạy tuần tự, chỉ nhanh hơn khi server giữ nhiều board.
+
+/// Chạy `ticks` tick cho mọi ván trên rayon pool.
+#[cfg(feature = "parallel")]
+pub fn step_all(games: &mut [Game], ticks: u32) {
+    use rayon::prelude::*;
+    games.par_iter_mut().for_each(|game| {
+        for _ in 0..ticks { game.step(); }
+    });
+}
+
+/// 1 replay cần validate, xem `validate_replay_checked`
+#[cfg(feature = "parallel")]
+#[derive(Clone, Copy, Debug)]
+pub struct ReplayJob<'a> {
+    pub config: &'a GameConfig,
+    pub moves: &'a [u8],
+    pub move_ticks: &'a [u32],
+}
+
+/// `validate_replay_checked` cho cả lô trên rayon pool, kết quả giữ thứ tự `jobs`.
+#[cfg(feature = "parallel")]
+pub fn validate_replays_checked(jobs: &[ReplayJob<'_>], limits: &ReplayLimits) -> Vec<Result<u64, ReplayError>> {
+    use rayon::prelude::*;
+    jobs.par_iter()
+        .map(|job| validate_replay_checked(job.config, job.moves, job.move_ticks, limits))
+        .collect()
+}
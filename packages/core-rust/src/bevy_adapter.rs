@@ -0,0 +1,80 @@
+//! Bevy adapter (feature `bevy`): chạy `api::Game` như 1 Resource trong app native.
+//!
+//! Luồng dữ liệu:
+//! - Input: gửi `SwapRequest` (toạ độ ô), plugin gọi `Game::swap` trong `Update`.
+//! - Tick: `FixedUpdate` gọi `Game::step` mỗi tick cố định (như `Simulation::update`), kể cả khi board ổn định.
+//! - Output: mọi `GridEvent` được forward thành bevy event `CoreEvent`; `SwapRejected` cho swap lỗi.
+
+use bevy::prelude::*;
+
+use crate::api::{Game, GameConfig, GridEvent, SwapRejectReason};
+
+/// Ván chơi hiện tại. Đọc board qua `cells()` / `cell(x, y)`.
+#[derive(Resource)]
+pub struct MatchGame(pub Game);
+
+/// Yêu cầu swap 2 ô (x, y)
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SwapRequest {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+}
+
+/// Swap bị core từ chối
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SwapRejected {
+    pub request: SwapRequest,
+    pub reason: SwapRejectReason,
+}
+
+/// 1 event của core (clear, conversion, cycle...), cùng thứ tự với buffer gốc
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CoreEvent(pub GridEvent);
+
+/// Tick rate mặc định của core (Hz), khớp nhịp tick phía web
+pub const DEFAULT_TICK_HZ: f64 = 30.0;
+
+pub struct CorePlugin {
+    pub config: GameConfig,
+    pub tick_hz: f64,
+}
+
+impl Default for CorePlugin {
+    fn default() -> Self {
+        Self { config: GameConfig::default(), tick_hz: DEFAULT_TICK_HZ }
+    }
+}
+
+impl Plugin for CorePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MatchGame(Game::new(&self.config)))
+            .insert_resource(Time::<Fixed>::from_hz(self.tick_hz))
+            .add_event::<SwapRequest>()
+            .add_event::<SwapRejected>()
+            .add_event::<CoreEvent>()
+            .add_systems(Update, apply_swap_requests)
+            .add_systems(FixedUpdate, (tick_game, forward_core_events).chain());
+    }
+}
+
+fn apply_swap_requests(
+    mut game: ResMut<MatchGame>,
+    mut requests: EventReader<SwapRequest>,
+    mut rejected: EventWriter<SwapRejected>,
+) {
+    // Nhận input cả lúc board đang resolve (giống Simulation::swap), không bỏ request nào
+    for request in requests.read() {
+        if let Err(reason) = game.0.swap(request.a, request.b) {
+            rejected.send(SwapRejected { request: *request, reason });
+        }
+    }
+}
+
+// Tick cả lúc ổn định: nhịp tick (event, hash trail, refill) khớp Simulation / client web
+fn tick_game(mut game: ResMut<MatchGame>) {
+    game.0.step();
+}
+
+fn forward_core_events(mut game: ResMut<MatchGame>, mut out: EventWriter<CoreEvent>) {
+    out.send_batch(game.0.drain_events().into_iter().map(CoreEvent));
+}
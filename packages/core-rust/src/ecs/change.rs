@@ -0,0 +1,33 @@
+// --- CHANGE DETECTION ---
+// Kiểu T bật World::track_changes::<T>() thì mỗi entity có T mang thêm ChangeTicks<T>:
+// tick lúc T được thêm và lần cuối bị ghi. Consumer nhớ tick đã xử lý rồi hỏi "đổi từ tick đó?"
+// (World::changed / added / any_changed_since) thay vì duyệt lại mọi entity.
+// Ghi qua add_component / insert / get_component_mut được đánh dấu tự động; ghi qua query_mut
+// thì system tự gọi mark (xem MovementSystem). Tick là u32 tăng dần, ~400 ngày ở 60Hz mới tràn.
+
+use std::marker::PhantomData;
+
+pub struct ChangeTicks<T> {
+    added: u32,
+    changed: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ChangeTicks<T> {
+    pub(crate) fn new(tick: u32) -> Self {
+        Self { added: tick, changed: tick, _marker: PhantomData }
+    }
+
+    pub fn mark(&mut self, tick: u32) {
+        self.changed = tick;
+    }
+
+    pub fn is_added_since(&self, since: u32) -> bool {
+        self.added > since
+    }
+
+    // Thêm mới cũng tính là đổi
+    pub fn is_changed_since(&self, since: u32) -> bool {
+        self.changed > since
+    }
+}
@@ -0,0 +1,59 @@
+// --- DEFERRED COMMANDS ---
+// Đang duyệt query_mut thì World bị mượn mut, không spawn / despawn / thêm component được.
+// System ghi lệnh vào Commands trong vòng lặp rồi apply sau khi duyệt xong,
+// lệnh chạy đúng thứ tự đã ghi.
+
+use hecs::{Component, Entity};
+use crate::ecs::world::World;
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self { queue: Vec::new() }
+    }
+
+    // Entity chỉ có lúc apply nên setup nhận luôn handle mới
+    pub fn spawn(&mut self, setup: impl FnOnce(&mut World, Entity) + 'static) {
+        self.add(move |world| {
+            let e = world.create_entity();
+            setup(world, e);
+        });
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |world| { world.destroy_entity(entity); });
+    }
+
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        self.add(move |world| world.add_component(entity, component));
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.add(move |world| { world.remove_component::<T>(entity); });
+    }
+
+    // Lệnh tuỳ ý (gửi event, đổi resource...)
+    pub fn add(&mut self, command: impl FnOnce(&mut World) + 'static) {
+        self.queue.push(Box::new(command));
+    }
+
+    pub fn apply(&mut self, world: &mut World) {
+        for command in self.queue.drain(..) {
+            command(world);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
@@ -1,8 +1,9 @@
 use crate::ecs::component::Component;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Position {
     pub x: f32,
@@ -10,7 +11,7 @@ pub struct Position {
 }
 // impl Component for Position {}
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Velocity {
     pub x: f32,
@@ -18,9 +19,60 @@ pub struct Velocity {
 }
 // impl Component for Velocity {}
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Player {
     pub id: u32,
 }
 // impl Component for Player {}
+
+// Marker: Position / Velocity của entity này tính theo cell space (xem sim/coords.rs)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub struct GridBound;
+
+// Hierarchy: entity con trỏ lên cha (boss nhiều phần, emitter -> particle).
+// World::despawn_recursive huỷ cả cây. Không lưu trong world snapshot (handle thô).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub hecs::Entity);
+
+// Nhãn cho script / debug ("boss_core", "player_1"), tra bằng World::find_by_name.
+// Nên duy nhất: trùng tên thì entity gắn sau thắng. Đổi tên bằng add_component để index cập nhật.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Name(pub String);
+
+// Marker: entity đứng yên dù có Velocity (tutorial, cutscene...), MovementSystem bỏ qua
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Frozen;
+
+// Entity gắn với 1 ô lưới: ô bị clear -> despawn, tile rơi -> đi theo (xem CellBindingSystem)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub struct CellAnchor {
+    pub cell: u32, // Index ô (y * width + x)
+}
+
+// Entity tự huỷ sau `ticks` sim tick (xem LifetimeSystem)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Lifetime {
+    pub ticks: u32,
+}
+
+// --- ECS EVENTS (xem ecs/events.rs) ---
+
+// Entity gắn ô bị huỷ vì ô bị clear (CellBindingSystem), Simulation chuyển sang EventType::EntityDied
+#[derive(Debug, Clone, Copy)]
+pub struct EntityDied {
+    pub entity: hecs::Entity,
+    pub cell: u32,
+}
+
+// Entity hiệu ứng spawn từ grid event (xem sim/spawner.rs)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Effect {
+    pub archetype: u8,  // EffectArchetype hoặc id tuỳ frontend
+    pub event_type: u8, // Event đã sinh ra entity
+}
@@ -0,0 +1,79 @@
+// --- ECS EVENT BUS ---
+// System giao tiếp qua event thay vì gọi thẳng nhau ("entity died", "projectile hit cell"...).
+// Double buffer theo frame: event sống đúng 2 lần update() rồi bị bỏ, nên reader chạy
+// trước hay sau writer trong cùng tick đều không lỡ event.
+// Mỗi event có id tăng dần; EventReader chỉ giữ id kế tiếp cần đọc (cursor), nhiều reader
+// đọc độc lập trên cùng Events<T>.
+
+use std::marker::PhantomData;
+
+pub struct Events<T> {
+    previous: Vec<T>, // Event của frame trước
+    current: Vec<T>,  // Event gửi trong frame này
+    previous_start: u64, // Id của previous[0]
+    current_start: u64,  // Id của current[0]
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self { previous: Vec::new(), current: Vec::new(), previous_start: 0, current_start: 0 }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    // Gọi 1 lần / frame: bỏ buffer cũ nhất, frame hiện tại thành frame trước
+    pub fn update(&mut self) {
+        self.previous_start = self.current_start;
+        self.current_start += self.current.len() as u64;
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    // Reader mới chỉ thấy event gửi sau thời điểm tạo
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader { next: self.current_start + self.current.len() as u64, _marker: PhantomData }
+    }
+
+    // Event reader chưa đọc (còn trong 2 buffer), theo thứ tự gửi. Event đã rơi khỏi buffer bị bỏ qua.
+    pub fn read<'a>(&'a self, reader: &mut EventReader<T>) -> impl Iterator<Item = &'a T> + 'a {
+        let skip_previous = reader.next.saturating_sub(self.previous_start) as usize;
+        let skip_current = reader.next.saturating_sub(self.current_start) as usize;
+        reader.next = self.current_start + self.current.len() as u64;
+        self.previous.iter().skip(skip_previous).chain(self.current.iter().skip(skip_current))
+    }
+
+    // Lấy hết event còn trong buffer (reader cũ sẽ không thấy lại)
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.previous_start = self.current_start + self.current.len() as u64;
+        self.current_start = self.previous_start;
+        self.previous.drain(..).chain(self.current.drain(..))
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+}
+
+// Cursor đọc Events<T>, giữ trong system / Simulation
+pub struct EventReader<T> {
+    next: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    // Đọc từ đầu: thấy mọi event còn trong buffer
+    fn default() -> Self {
+        Self { next: 0, _marker: PhantomData }
+    }
+}
@@ -1,3 +1,10 @@
 pub mod component;
 pub mod components;
 pub mod world;
+pub mod schedule;
+pub mod events;
+pub mod change;
+pub mod commands;
+pub mod save;
+pub mod prefab;
+pub mod stats;
@@ -0,0 +1,40 @@
+// --- PREFABS ---
+// Bộ component khai báo 1 lần, đăng ký theo tên vào World (World::register_prefab),
+// rồi world.spawn_prefab("fireball", Prefab::new().with(pos)) tạo entity đủ component.
+// Override cũng là Prefab: gắn sau bộ gốc nên component cùng kiểu sẽ ghi đè.
+
+use hecs::{Component, Entity};
+use crate::ecs::world::World;
+
+type Insert = Box<dyn Fn(&mut World, Entity) + Send + Sync>;
+
+#[derive(Default)]
+pub struct Prefab {
+    inserts: Vec<Insert>,
+}
+
+impl Prefab {
+    pub fn new() -> Self {
+        Self { inserts: Vec::new() }
+    }
+
+    // Mỗi lần spawn nhận 1 bản clone
+    pub fn with<T: Component + Clone>(mut self, component: T) -> Self {
+        self.inserts.push(Box::new(move |world, entity| world.add_component(entity, component.clone())));
+        self
+    }
+
+    pub(crate) fn apply(&self, world: &mut World, entity: Entity) {
+        for insert in &self.inserts {
+            insert(world, entity);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inserts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inserts.is_empty()
+    }
+}
@@ -0,0 +1,116 @@
+// --- WORLD SAVE ---
+// Snapshot toàn bộ entity + component đã đăng ký (World::register_persistent) dạng byte,
+// để resume / rollback cả simulation chứ không chỉ grid.
+//
+// Layout (little-endian): MAGIC(4) | version u16
+//   | số kiểu u32 | [tên kiểu: len u32 + utf8]       (registry lúc save, index dùng bên dưới)
+//   | số entity u32 | [Entity::to_bits u64 | số component u32 | [index kiểu u32 | len u32 + payload]]
+// Payload = postcard (serde) của component. Kiểu nhận diện bằng tên ổn định thay vì TypeId
+// (TypeId đổi giữa các build). Handle entity giữ nguyên qua load (spawn_at), nên entity id
+// đã gửi cho JS / lưu trong component khác vẫn trỏ đúng entity.
+// Không gồm resource, event, change tick (thuần runtime).
+
+use hecs::{Component, Entity, World as HecsWorld};
+use serde::{de::DeserializeOwned, Serialize};
+use std::any::TypeId;
+use crate::ecs::world::World;
+
+pub const WORLD_SAVE_MAGIC: &[u8; 4] = b"LIWD";
+pub const WORLD_SAVE_VERSION: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorldSaveError {
+    BadHeader,
+    UnsupportedVersion(u16),
+    Truncated,
+    UnknownComponent(String), // Save có kiểu chưa register_persistent ở build này
+    Invalid, // Handle entity / payload không giải mã được
+}
+
+impl std::fmt::Display for WorldSaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldSaveError::BadHeader => f.write_str("not a world save"),
+            WorldSaveError::UnsupportedVersion(v) => write!(f, "unsupported world save version {}", v),
+            WorldSaveError::Truncated => f.write_str("world save truncated"),
+            WorldSaveError::UnknownComponent(name) => write!(f, "unknown component `{}`", name),
+            WorldSaveError::Invalid => f.write_str("world save contains invalid values"),
+        }
+    }
+}
+
+// 1 kiểu trong registry, fn pointer monomorphize theo T lúc register
+pub(crate) struct PersistentType {
+    pub name: &'static str,
+    pub type_id: TypeId,
+    pub save: fn(&HecsWorld, Entity) -> Option<Vec<u8>>,
+    pub decode: fn(&[u8]) -> Result<ComponentInsert, WorldSaveError>,
+}
+
+// Component đã giải mã, chờ gắn vào entity (giải mã hết trước khi đụng World)
+pub(crate) type ComponentInsert = Box<dyn FnOnce(&mut World, Entity)>;
+
+impl PersistentType {
+    pub fn of<T: Component + Serialize + DeserializeOwned>(name: &'static str) -> Self {
+        Self { name, type_id: TypeId::of::<T>(), save: save_component::<T>, decode: decode_component::<T> }
+    }
+}
+
+fn save_component<T: Component + Serialize>(world: &HecsWorld, entity: Entity) -> Option<Vec<u8>> {
+    let component = world.get::<&T>(entity).ok()?;
+    postcard::to_allocvec(&*component).ok()
+}
+
+fn decode_component<T: Component + DeserializeOwned>(data: &[u8]) -> Result<ComponentInsert, WorldSaveError> {
+    let component: T = postcard::from_bytes(data).map_err(|_| WorldSaveError::Invalid)?;
+    Ok(Box::new(move |world: &mut World, entity| world.add_component(entity, component)))
+}
+
+// --- BYTE HELPERS ---
+
+pub(crate) fn write_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+pub(crate) fn write_bytes(out: &mut Vec<u8>, v: &[u8]) {
+    write_u32(out, v.len() as u32);
+    out.extend_from_slice(v);
+}
+
+pub(crate) struct SaveReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SaveReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, WorldSaveError> {
+        if data.len() < 6 || &data[..4] != WORLD_SAVE_MAGIC { return Err(WorldSaveError::BadHeader); }
+        let version = u16::from_le_bytes([data[4], data[5]]);
+        if version != WORLD_SAVE_VERSION { return Err(WorldSaveError::UnsupportedVersion(version)); }
+        Ok(Self { data, pos: 6 })
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], WorldSaveError> {
+        let end = self.pos.checked_add(n).ok_or(WorldSaveError::Truncated)?;
+        let slice = self.data.get(self.pos..end).ok_or(WorldSaveError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, WorldSaveError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, WorldSaveError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], WorldSaveError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub fn finish(self) -> Result<(), WorldSaveError> {
+        if self.pos == self.data.len() { Ok(()) } else { Err(WorldSaveError::Invalid) }
+    }
+}
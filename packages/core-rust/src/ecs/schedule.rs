@@ -0,0 +1,216 @@
+// --- SYSTEM SCHEDULE ---
+// System đăng ký theo stage, chạy lần lượt Input -> Logic -> Grid -> Post mỗi tick.
+// Trong 1 stage chạy theo thứ tự đăng ký (system song song khai Access, xem PARALLEL SYSTEMS).
+// Tắt stage lúc runtime (vd tắt Grid khi debug entity).
+// Generic theo context `C` (Simulation truyền chính nó) để ecs không phụ thuộc sim.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Input = 0,
+    Logic = 1,
+    Grid = 2,
+    Post = 3,
+}
+
+pub const STAGE_COUNT: usize = 4;
+
+impl Stage {
+    pub const ALL: [Stage; STAGE_COUNT] = [Stage::Input, Stage::Logic, Stage::Grid, Stage::Post];
+
+    pub fn from_id(id: u8) -> Option<Stage> {
+        Stage::ALL.get(id as usize).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Input => "input",
+            Stage::Logic => "logic",
+            Stage::Grid => "grid",
+            Stage::Post => "post",
+        }
+    }
+}
+
+// Send + Sync để Simulation chạy được trên thread pool (feature `parallel`)
+type System<C> = Box<dyn FnMut(&mut C, f64) + Send + Sync>;
+
+// --- PARALLEL SYSTEMS ---
+// System song song chia 2 pha: compute chỉ đọc context (&C), trả về commit ghi vào context.
+// Các system song song liền nhau trong 1 stage mà access không xung đột được gom thành 1 batch:
+// feature `parallel` chạy compute của cả batch trên rayon pool, rồi commit theo thứ tự đăng ký.
+// Không bật feature thì compute -> commit lần lượt (run_sequential). 2 cách chỉ ra cùng kết quả khi
+// Access khai đúng: core không kiểm tra được system thật sự đụng gì.
+pub type Commit<C> = Box<dyn FnOnce(&mut C) + Send>;
+type ParallelSystem<C> = Box<dyn Fn(&C, f64) -> Commit<C> + Send + Sync>;
+
+// Tập phần context (tên tự đặt, vd "grid", "world") system đọc / ghi, gồm cả phần commit đụng tới
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Access {
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(mut self, name: &'static str) -> Self {
+        self.reads.push(name);
+        self
+    }
+
+    pub fn write(mut self, name: &'static str) -> Self {
+        self.writes.push(name);
+        self
+    }
+
+    // Xung đột khi 1 bên ghi phần bên kia đọc hoặc ghi
+    pub fn conflicts(&self, other: &Access) -> bool {
+        let touches = |a: &Access, name: &&'static str| a.reads.contains(name) || a.writes.contains(name);
+        self.writes.iter().any(|name| touches(other, name)) || other.writes.iter().any(|name| touches(self, name))
+    }
+}
+
+enum SystemFn<C> {
+    Exclusive(System<C>),
+    Parallel(Access, ParallelSystem<C>),
+}
+
+pub struct Schedule<C> {
+    stages: [Vec<(&'static str, SystemFn<C>)>; STAGE_COUNT],
+    enabled: [bool; STAGE_COUNT],
+    batches: Vec<(Stage, Vec<usize>)>, // Tính lại lúc đăng ký, không tính mỗi tick
+    // Buffer dùng lại giữa các tick, chỉ Box commit là cấp phát mới. Mutex chỉ để Schedule vẫn Sync
+    // (commit không Sync), luôn truy cập qua get_mut nên không lock.
+    #[cfg(feature = "parallel")]
+    commits: std::sync::Mutex<Vec<Commit<C>>>,
+}
+
+impl<C> Default for Schedule<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> Schedule<C> {
+    pub fn new() -> Self {
+        Self {
+            stages: std::array::from_fn(|_| Vec::new()),
+            enabled: [true; STAGE_COUNT],
+            batches: Vec::new(),
+            #[cfg(feature = "parallel")]
+            commits: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    // fn pointer hoặc closure: |ctx, dt| .. (chạy 1 mình, không gom batch)
+    pub fn add_system(&mut self, stage: Stage, name: &'static str, system: impl FnMut(&mut C, f64) + Send + Sync + 'static) -> &mut Self {
+        self.stages[stage as usize].push((name, SystemFn::Exclusive(Box::new(system))));
+        self.batches = self.plan_batches();
+        self
+    }
+
+    // |ctx, dt| -> Box<commit>, `access` phải khai đủ phần compute + commit đọc / ghi
+    pub fn add_parallel_system(
+        &mut self,
+        stage: Stage,
+        name: &'static str,
+        access: Access,
+        system: impl Fn(&C, f64) -> Commit<C> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.stages[stage as usize].push((name, SystemFn::Parallel(access, Box::new(system))));
+        self.batches = self.plan_batches();
+        self
+    }
+
+    pub fn set_stage_enabled(&mut self, stage: Stage, enabled: bool) {
+        self.enabled[stage as usize] = enabled;
+    }
+
+    pub fn is_stage_enabled(&self, stage: Stage) -> bool {
+        self.enabled[stage as usize]
+    }
+
+    pub fn system_names(&self, stage: Stage) -> impl Iterator<Item = &'static str> + '_ {
+        self.stages[stage as usize].iter().map(|(name, _)| *name)
+    }
+
+    // Batch theo thứ tự đăng ký: mỗi batch là 1 system exclusive, hoặc các system song song
+    // liền nhau không xung đột với nhau. Phần tử = (stage, các index trong stage).
+    pub fn batches(&self) -> &[(Stage, Vec<usize>)] {
+        &self.batches
+    }
+
+    fn plan_batches(&self) -> Vec<(Stage, Vec<usize>)> {
+        let mut batches = Vec::new();
+        for stage in Stage::ALL {
+            let systems = &self.stages[stage as usize];
+            let mut current: Vec<usize> = Vec::new();
+            for (i, (_, system)) in systems.iter().enumerate() {
+                let joins = match system {
+                    SystemFn::Parallel(access, _) => current.iter().all(|&j| match &systems[j].1 {
+                        SystemFn::Parallel(other, _) => !access.conflicts(other),
+                        SystemFn::Exclusive(_) => false,
+                    }),
+                    SystemFn::Exclusive(_) => false,
+                };
+                if !joins && !current.is_empty() {
+                    batches.push((stage, std::mem::take(&mut current)));
+                }
+                current.push(i);
+            }
+            if !current.is_empty() {
+                batches.push((stage, current));
+            }
+        }
+        batches
+    }
+
+    // Từng system theo thứ tự đăng ký, không gom batch
+    pub fn run_sequential(&mut self, ctx: &mut C, dt: f64) {
+        for (stage, systems) in self.stages.iter_mut().enumerate() {
+            if !self.enabled[stage] { continue; }
+            for (_, system) in systems.iter_mut() {
+                match system {
+                    SystemFn::Exclusive(system) => system(ctx, dt),
+                    SystemFn::Parallel(_, system) => system(ctx, dt)(ctx),
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pub fn run(&mut self, ctx: &mut C, dt: f64) {
+        self.run_sequential(ctx, dt);
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn run(&mut self, ctx: &mut C, dt: f64)
+    where
+        C: Sync,
+    {
+        use rayon::prelude::*;
+        for (stage, batch) in &self.batches {
+            if !self.enabled[*stage as usize] { continue; }
+            let systems = &mut self.stages[*stage as usize];
+            if let [i] = batch[..] {
+                match &mut systems[i].1 {
+                    SystemFn::Exclusive(system) => system(ctx, dt),
+                    SystemFn::Parallel(_, system) => system(ctx, dt)(ctx),
+                }
+                continue;
+            }
+            // Batch nhiều phần tử chỉ gồm system song song
+            let shared: &C = ctx;
+            let commits = self.commits.get_mut().unwrap_or_else(|e| e.into_inner());
+            commits.par_extend(batch.par_iter().filter_map(|&i| match &systems[i].1 {
+                SystemFn::Parallel(_, system) => Some(system(shared, dt)),
+                SystemFn::Exclusive(_) => None,
+            }));
+            for commit in commits.drain(..) {
+                commit(ctx);
+            }
+        }
+    }
+}
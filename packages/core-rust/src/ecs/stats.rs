@@ -0,0 +1,24 @@
+// --- WORLD STATS ---
+// Số liệu cho debug overlay / soak test (bắt rò entity). Tên và size chỉ biết với kiểu đã
+// register_component (register_persistent / track_changes tự đăng ký); bytes là ước lượng
+// count * size_of, không gồm overhead archetype của hecs.
+
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ComponentStats {
+    pub name: &'static str, // std::any::type_name
+    pub count: u32,
+    pub bytes: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WorldStats {
+    pub entities: u32,
+    pub components: Vec<ComponentStats>, // Kiểu đã đăng ký, sắp theo tên
+    pub unregistered_components: u32,    // Số component thuộc kiểu chưa đăng ký
+    pub component_bytes: usize,
+    pub resources: usize,
+    pub prefabs: usize,
+    pub named_entities: usize,
+}
@@ -1,13 +1,291 @@
-use hecs::{World as HecsWorld, Entity, Component, Ref, RefMut};
+use hecs::{World as HecsWorld, Bundle, DynamicBundle, Entity, Component, Query, QueryBorrow, QueryMut, Ref, RefMut};
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use crate::ecs::change::ChangeTicks;
+use crate::ecs::commands::Commands;
+use crate::ecs::components::{Name, Parent};
+use crate::ecs::events::{EventReader, Events};
+use crate::ecs::prefab::Prefab;
+use crate::ecs::stats::{ComponentStats, WorldStats};
+use crate::ecs::save::{write_bytes, write_u32, PersistentType, SaveReader, WorldSaveError, WORLD_SAVE_MAGIC, WORLD_SAVE_VERSION};
+use serde::{de::DeserializeOwned, Serialize};
+
+// Gắn ChangeTicks<T> (tick) cho các entity có T mà chưa có ticks, đã có thì đánh dấu đổi (T bị thay)
+type TrackFn = fn(&mut HecsWorld, &[Entity], u32);
 
 pub struct World {
+    // Component nằm trong archetype của hecs: cột dense theo số entity thực có, grow khi cần,
+    // không preallocate sparse array theo số entity tối đa. register_component chỉ ghi tên / size cho stats.
     world: HecsWorld,
+    component_info: BTreeMap<TypeId, (&'static str, usize)>,
+    // Resource: 1 giá trị / kiểu, dùng chung giữa các system (config, input state...)
+    resources: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+    // Swap buffer của mọi Events<T> đã add_event, gọi trong update_events
+    event_updaters: Vec<fn(&mut World)>,
+    // Change detection (xem ecs/change.rs)
+    change_tick: u32,
+    removed_tick: u32, // Tick gần nhất có entity bị huỷ / component được track bị gỡ
+    // Kiểu được track -> gắn ChangeTicks cho entity tạo ngoài add_component (spawn_batch)
+    tracked: BTreeMap<TypeId, TrackFn>,
+    // Kiểu component được World::save ghi lại (xem ecs/save.rs)
+    persistent: Vec<PersistentType>,
+    // Bộ component theo tên (xem ecs/prefab.rs), Arc để spawn không phải mượn World 2 lần
+    prefabs: BTreeMap<String, Arc<Prefab>>,
+    // Name -> entity, cập nhật khi thêm / gỡ Name hoặc huỷ entity
+    names: BTreeMap<String, Entity>,
 }
 
 impl World {
     pub fn new() -> Self {
         Self {
             world: HecsWorld::new(),
+            component_info: BTreeMap::new(),
+            resources: BTreeMap::new(),
+            event_updaters: Vec::new(),
+            change_tick: 1,
+            removed_tick: 0,
+            tracked: BTreeMap::new(),
+            persistent: Vec::new(),
+            prefabs: BTreeMap::new(),
+            names: BTreeMap::new(),
+        }
+    }
+
+    // --- RESOURCES ---
+
+    // Returns giá trị cũ cùng kiểu nếu có
+    pub fn insert_resource<R: Any + Send + Sync>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(resource))
+            .and_then(|old| old.downcast::<R>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn get_resource<R: Any + Send + Sync>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>())?.downcast_ref::<R>()
+    }
+
+    pub fn get_resource_mut<R: Any + Send + Sync>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut::<R>()
+    }
+
+    pub fn remove_resource<R: Any + Send + Sync>(&mut self) -> Option<R> {
+        self.resources.remove(&TypeId::of::<R>())?.downcast::<R>().ok().map(|r| *r)
+    }
+
+    // --- EVENTS ---
+
+    // Đăng ký Events<T> làm resource, gọi lại không reset buffer
+    pub fn add_event<T: Send + Sync + 'static>(&mut self) {
+        if self.get_resource::<Events<T>>().is_some() { return; }
+        self.insert_resource(Events::<T>::new());
+        self.event_updaters.push(|world| {
+            if let Some(events) = world.get_resource_mut::<Events<T>>() { events.update(); }
+        });
+    }
+
+    // false = chưa add_event, event bị bỏ (không ai nghe)
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) -> bool {
+        match self.get_resource_mut::<Events<T>>() {
+            Some(events) => { events.send(event); true }
+            None => false,
+        }
+    }
+
+    // Truy cập thẳng buffer (reader(), drain() cho consumer ngoài schedule)
+    pub fn events_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut Events<T>> {
+        self.get_resource_mut::<Events<T>>()
+    }
+
+    // Đọc event mới theo cursor của reader, trả về bản copy để system còn mượn &mut World
+    pub fn read_events<T: Clone + Send + Sync + 'static>(&self, reader: &mut EventReader<T>) -> Vec<T> {
+        self.get_resource::<Events<T>>()
+            .map(|events| events.read(reader).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Gọi 1 lần / tick trước các system (Simulation: stage Input)
+    pub fn update_events(&mut self) {
+        // Theo index (fn pointer là Copy) để không clone Vec mỗi tick
+        for i in 0..self.event_updaters.len() {
+            let update = self.event_updaters[i];
+            update(self);
+        }
+    }
+
+    // --- CHANGE DETECTION ---
+
+    // Chỉ áp dụng cho component thêm sau lời gọi này
+    pub fn track_changes<T: Component>(&mut self) {
+        self.register_component::<T>();
+        self.register_component::<ChangeTicks<T>>();
+        self.tracked.insert(TypeId::of::<T>(), |world, entities, tick| {
+            for &e in entities {
+                if world.get::<&T>(e).is_err() { continue; }
+                if let Ok(mut ticks) = world.get::<&mut ChangeTicks<T>>(e) {
+                    ticks.mark(tick);
+                    continue;
+                }
+                let _ = world.insert_one(e, ChangeTicks::<T>::new(tick));
+            }
+        });
+    }
+
+    fn is_tracked<T: Component>(&self) -> bool {
+        self.tracked.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+
+    // Kết thúc tick hiện tại, returns tick vừa kết thúc (lưu lại làm mốc `since`)
+    pub fn advance_change_tick(&mut self) -> u32 {
+        self.change_tick += 1;
+        self.change_tick - 1
+    }
+
+    // Dùng khi ghi qua query_mut
+    pub fn mark_changed<T: Component>(&mut self, entity: Entity) {
+        let tick = self.change_tick;
+        if let Ok(mut ticks) = self.world.get::<&mut ChangeTicks<T>>(entity) {
+            ticks.mark(tick);
+        }
+    }
+
+    // Entity có T được thêm / ghi sau tick `since`
+    pub fn changed<T: Component>(&self, since: u32) -> Vec<Entity> {
+        self.world.query::<&ChangeTicks<T>>().iter()
+            .filter(|(_, ticks)| ticks.is_changed_since(since))
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    pub fn added<T: Component>(&self, since: u32) -> Vec<Entity> {
+        self.world.query::<&ChangeTicks<T>>().iter()
+            .filter(|(_, ticks)| ticks.is_added_since(since))
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    // Không cấp phát: có gì cần làm lại từ tick `since`? Tính cả entity bị huỷ / T bị gỡ.
+    pub fn any_changed_since<T: Component>(&self, since: u32) -> bool {
+        self.removed_tick > since
+            || self.world.query::<&ChangeTicks<T>>().iter().any(|(_, ticks)| ticks.is_changed_since(since))
+    }
+
+    // Chạy lệnh đã ghi trong lúc duyệt query (xem ecs/commands.rs)
+    pub fn apply_commands(&mut self, commands: &mut Commands) {
+        commands.apply(self);
+    }
+
+    // --- SAVE / LOAD ---
+
+    // `name` ghi vào save, phải ổn định giữa các build. Component chưa đăng ký không được lưu.
+    pub fn register_persistent<T: Component + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        self.register_component::<T>();
+        if self.persistent.iter().any(|p| p.type_id == TypeId::of::<T>() || p.name == name) { return; }
+        self.persistent.push(PersistentType::of::<T>(name));
+    }
+
+    pub fn save(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(256);
+        out.extend_from_slice(WORLD_SAVE_MAGIC);
+        out.extend_from_slice(&WORLD_SAVE_VERSION.to_le_bytes());
+        write_u32(&mut out, self.persistent.len() as u32);
+        for p in &self.persistent {
+            write_bytes(&mut out, p.name.as_bytes());
+        }
+        write_u32(&mut out, self.world.len());
+        let mut components = Vec::new();
+        for entity_ref in self.world.iter() {
+            let entity = entity_ref.entity();
+            components.clear();
+            for (index, p) in self.persistent.iter().enumerate() {
+                if let Some(data) = (p.save)(&self.world, entity) { components.push((index as u32, data)); }
+            }
+            out.extend_from_slice(&entity.to_bits().get().to_le_bytes());
+            write_u32(&mut out, components.len() as u32);
+            for (index, data) in &components {
+                write_u32(&mut out, *index);
+                write_bytes(&mut out, data);
+            }
+        }
+        out
+    }
+
+    // Thay toàn bộ entity bằng nội dung save, handle giữ nguyên. Lỗi thì World không đổi.
+    // Resource / event / registry giữ nguyên.
+    pub fn load(&mut self, data: &[u8]) -> Result<(), WorldSaveError> {
+        let mut reader = SaveReader::new(data)?;
+        let mut decoders = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = std::str::from_utf8(reader.bytes()?).map_err(|_| WorldSaveError::Invalid)?;
+            let p = self.persistent.iter().find(|p| p.name == name)
+                .ok_or_else(|| WorldSaveError::UnknownComponent(name.to_string()))?;
+            decoders.push(p.decode);
+        }
+        let mut seen = BTreeSet::new();
+        let mut entities = Vec::new();
+        for _ in 0..reader.u32()? {
+            let entity = Entity::from_bits(reader.u64()?).ok_or(WorldSaveError::Invalid)?;
+            if !seen.insert(entity.id()) { return Err(WorldSaveError::Invalid); }
+            let mut components = Vec::new();
+            for _ in 0..reader.u32()? {
+                let decode = *decoders.get(reader.u32()? as usize).ok_or(WorldSaveError::Invalid)?;
+                components.push(decode(reader.bytes()?)?);
+            }
+            entities.push((entity, components));
+        }
+        reader.finish()?;
+
+        self.world.clear();
+        self.names.clear();
+        self.removed_tick = self.change_tick;
+        for (entity, components) in entities {
+            self.world.spawn_at(entity, ());
+            for insert in components {
+                insert(self, entity);
+            }
+        }
+        Ok(())
+    }
+
+    // --- PREFABS ---
+
+    // Đăng ký lại cùng tên thì thay bộ cũ
+    pub fn register_prefab(&mut self, name: &str, prefab: Prefab) {
+        self.prefabs.insert(name.to_string(), Arc::new(prefab));
+    }
+
+    pub fn has_prefab(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+
+    // None nếu chưa đăng ký `name`. overrides gắn sau nên thắng component cùng kiểu của prefab.
+    pub fn spawn_prefab(&mut self, name: &str, overrides: Prefab) -> Option<Entity> {
+        let prefab = Arc::clone(self.prefabs.get(name)?);
+        let entity = self.create_entity();
+        prefab.apply(self, entity);
+        overrides.apply(self, entity);
+        Some(entity)
+    }
+
+    // --- NAMES ---
+
+    // Kiểm lại Name hiện tại: Name bị sửa tại chỗ (get_component_mut) thì tên cũ không còn khớp
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        let entity = *self.names.get(name)?;
+        let current = self.world.get::<&Name>(entity).ok()?;
+        (current.0 == name).then_some(entity)
+    }
+
+    // Gỡ index của entity trước khi mất Name (gỡ component / huỷ entity)
+    fn unindex_name(&mut self, entity: Entity) {
+        let Ok(name) = self.world.get::<&Name>(entity) else { return };
+        if self.names.get(&name.0) == Some(&entity) {
+            self.names.remove(&name.0);
         }
     }
 
@@ -15,26 +293,187 @@ impl World {
         self.world.spawn(())
     }
 
+    // Spawn n entity 1 lượt (particle burst, wave): hecs gom theo archetype thay vì
+    // thêm từng component. make(i) trả bundle (tuple component) cho entity thứ i.
+    pub fn spawn_batch<B: Bundle + 'static>(&mut self, n: usize, make: impl FnMut(usize) -> B) -> Vec<Entity> {
+        let entities: Vec<Entity> = self.world.spawn_batch((0..n).map(make)).collect();
+        self.index_bundle_insert(&entities, None);
+        entities
+    }
+
+    // --- BUNDLES ---
+    // Tuple component là bundle (hecs), không cần derive: world.spawn((Position { .. }, Velocity { .. }))
+    // Cả bundle vào 1 lượt = 1 lần đổi archetype thay vì mỗi add_component 1 lần.
+
+    pub fn spawn(&mut self, bundle: impl DynamicBundle) -> Entity {
+        let entity = self.world.spawn(bundle);
+        self.index_bundle_insert(&[entity], None);
+        entity
+    }
+
+    // Component cùng kiểu bị thay được đánh dấu changed như add_component
+    pub fn insert(&mut self, entity: Entity, bundle: impl DynamicBundle) -> bool {
+        // Chỉ kiểu có trong bundle: component khác của entity không bị đánh dấu oan
+        let types: Vec<TypeId> = bundle.with_ids(|ids| ids.to_vec());
+        if self.world.insert(entity, bundle).is_err() { return false; }
+        self.index_bundle_insert(&[entity], Some(&types));
+        true
+    }
+
+    // Bundle đi thẳng vào hecs, bỏ qua add_component: bù change ticks + index Name.
+    // types = kiểu trong bundle, None = mọi kiểu được track (entity mới, chưa có ticks nào)
+    fn index_bundle_insert(&mut self, entities: &[Entity], types: Option<&[TypeId]>) {
+        let tick = self.change_tick;
+        for (type_id, track) in &self.tracked {
+            if types.is_some_and(|types| !types.contains(type_id)) { continue; }
+            track(&mut self.world, entities, tick);
+        }
+        for &e in entities {
+            if let Ok(name) = self.world.get::<&Name>(e) { self.names.insert(name.0.clone(), e); }
+        }
+    }
+
+    // Xoá entity cùng mọi component. Index được tái dùng với generation mới:
+    // entity mới không thừa hưởng component cũ, handle cũ thành không hợp lệ.
+    // Generation của hecs là u32 (NonZero): 1 index phải được tái dùng ~4 tỉ lần mới quay vòng,
+    // ở 60 spawn/giây trên cùng 1 index là ~2 năm chạy liên tục.
     pub fn destroy_entity(&mut self, entity: Entity) -> bool {
-        self.world.despawn(entity).is_ok()
+        self.unindex_name(entity);
+        let destroyed = self.world.despawn(entity).is_ok();
+        if destroyed { self.removed_tick = self.change_tick; }
+        destroyed
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.world.contains(entity)
+    }
+
+    // --- HIERARCHY ---
+
+    pub fn children(&self, parent: Entity) -> Vec<Entity> {
+        self.world.query::<&Parent>().iter()
+            .filter(|(_, p)| p.0 == parent)
+            .map(|(e, _)| e)
+            .collect()
     }
 
+    // Huỷ entity và mọi hậu duệ (theo Parent). Returns số entity đã huỷ.
+    pub fn despawn_recursive(&mut self, entity: Entity) -> usize {
+        if !self.world.contains(entity) { return 0; }
+        // Dựng bảng cha -> con 1 lần (1 lượt query), rồi duyệt cây bằng stack: O(n log n)
+        let mut children: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+        for (e, p) in self.world.query::<&Parent>().iter() {
+            children.entry(p.0).or_default().push(e);
+        }
+        let mut stack = vec![entity];
+        let mut destroyed = 0;
+        while let Some(current) = stack.pop() {
+            // remove: mỗi cha chỉ được mở 1 lần, Parent vòng (a <-> b) không lặp vô hạn
+            if let Some(kids) = children.remove(&current) { stack.extend(kids); }
+            if self.destroy_entity(current) { destroyed += 1; }
+        }
+        destroyed
+    }
+
+    // hecs không cần đăng ký để lưu trữ; chỉ để World::stats biết tên / size
     pub fn register_component<T: Component>(&mut self) {
-        // hecs does not need explicit registration
+        self.component_info.insert(TypeId::of::<T>(), (std::any::type_name::<T>(), std::mem::size_of::<T>()));
+    }
+
+    // Duyệt mọi entity, dùng cho debug overlay / soak test chứ không phải mỗi tick
+    pub fn stats(&self) -> WorldStats {
+        let mut counts: BTreeMap<TypeId, u32> = BTreeMap::new();
+        for entity_ref in self.world.iter() {
+            for type_id in entity_ref.component_types() {
+                *counts.entry(type_id).or_default() += 1;
+            }
+        }
+        let mut components = Vec::new();
+        let mut unregistered_components = 0;
+        for (type_id, count) in counts {
+            match self.component_info.get(&type_id) {
+                Some(&(name, size)) => components.push(ComponentStats { name, count, bytes: count as usize * size }),
+                None => unregistered_components += count,
+            }
+        }
+        components.sort_by_key(|c| c.name);
+        WorldStats {
+            entities: self.world.len(),
+            component_bytes: components.iter().map(|c| c.bytes).sum(),
+            components,
+            unregistered_components,
+            resources: self.resources.len(),
+            prefabs: self.prefabs.len(),
+            named_entities: self.world.query::<&Name>().iter().count(),
+        }
     }
 
     pub fn add_component<T: Component>(&mut self, entity: Entity, component: T) {
-        let _ = self.world.insert_one(entity, component);
+        if let Some(name) = (&component as &dyn Any).downcast_ref::<Name>() {
+            if !self.world.contains(entity) { return; }
+            self.unindex_name(entity);
+            self.names.insert(name.0.clone(), entity);
+        }
+        let replaced = self.world.get::<&T>(entity).is_ok();
+        if self.world.insert_one(entity, component).is_err() || !self.is_tracked::<T>() { return; }
+        if replaced {
+            self.mark_changed::<T>(entity);
+        } else {
+            let _ = self.world.insert_one(entity, ChangeTicks::<T>::new(self.change_tick));
+        }
+    }
+
+    // Gỡ 1 component (vd status effect hết hạn), entity vẫn sống. None nếu không có / entity đã chết.
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        if TypeId::of::<T>() == TypeId::of::<Name>() { self.unindex_name(entity); }
+        let removed = self.world.remove_one::<T>(entity).ok()?;
+        if self.world.remove_one::<ChangeTicks<T>>(entity).is_ok() {
+            self.removed_tick = self.change_tick;
+        }
+        Some(removed)
     }
 
     pub fn get_component<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
         self.world.get::<&T>(entity).ok()
     }
 
+    // Component được track bị đánh dấu đổi ngay khi mượn mut
     pub fn get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<RefMut<'_, T>> {
+        self.mark_changed::<T>(entity);
         self.world.get::<&mut T>(entity).ok()
     }
-    
+
+    // Query nhiều component, chỉ duyệt entity khớp (hecs lọc theo archetype):
+    // for (e, (pos, vel)) in world.query_mut::<(&mut Position, &Velocity)>() { .. }
+    // Option<&T> = component không bắt buộc
+    // Lọc theo marker, không mượn marker, không cần get_component trong vòng lặp:
+    //   world.query::<&Position>().with::<&Player>().without::<&Frozen>()
+    // hoặc ở mức kiểu: world.query::<hecs::Without<hecs::With<&Position, &Player>, &Frozen>>()
+    pub fn query<Q: Query>(&self) -> QueryBorrow<'_, Q> {
+        self.world.query::<Q>()
+    }
+
+    pub fn query_mut<Q: Query>(&mut self) -> QueryMut<'_, Q> {
+        self.world.query_mut::<Q>()
+    }
+
+    // --- ITERATION ---
+    // 1 kiểu component, không cần dựng query: for (e, pos) in world.iter::<Position>() { .. }
+
+    // Mọi entity còn sống
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.world.iter().map(|entity_ref| entity_ref.entity())
+    }
+
+    pub fn iter<T: Component>(&self) -> impl Iterator<Item = (Entity, Ref<'_, T>)> + '_ {
+        self.world.iter().filter_map(|entity_ref| Some((entity_ref.entity(), entity_ref.get::<&T>()?)))
+    }
+
+    // Ghi qua đây không tự đánh dấu change detection (như query_mut), gọi mark_changed nếu cần
+    pub fn iter_mut<T: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> + '_ {
+        self.world.query_mut::<&mut T>().into_iter()
+    }
+
     // Expose inner world for advanced usage (iteration)
     pub fn inner(&self) -> &HecsWorld {
         &self.world
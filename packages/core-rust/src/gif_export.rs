@@ -0,0 +1,114 @@
+//! GIF exporter (feature `gif-export`): replay 1 ván trên sim gốc rồi ghi thành ảnh động,
+//! để bug report / balance review đính kèm đúng những gì core đã chạy.
+//!
+//! - Mỗi tick = 1 frame; mỗi ô = 1 ô vuông `cell_px` pixel, màu theo element.
+//! - Special tile có chấm trắng ở giữa, tile FROZEN / LOCKED có viền.
+//! - Moves cùng format với `validate_replay`: [x1, y1, x2, y2] mỗi nước, swap bị từ chối thì bỏ qua.
+
+use std::io::{self, Write};
+
+use gif::{Encoder, EncodingError, Frame, Repeat};
+
+use crate::api::{Cell, Game, GameConfig};
+use crate::sim::grid::{FLAG_FROZEN, FLAG_LOCKED, FLAG_SPECIAL_MASK};
+
+// Palette RGB, index = palette_index(element)
+const PALETTE: [u8; 30] = [
+    0x1E, 0x1E, 0x24, // 0 Empty
+    0xC8, 0xC8, 0xD0, // 1 Metal
+    0x3C, 0xB4, 0x4B, // 2 Wood
+    0x32, 0x78, 0xE6, // 3 Water
+    0xE6, 0x3C, 0x32, // 4 Fire
+    0xAA, 0x78, 0x32, // 5 Earth
+    0x5A, 0x5A, 0x5A, // 6 Stone
+    0x50, 0x28, 0x78, // 7 Dark
+    0xFF, 0xFF, 0xFF, // 8 Special marker
+    0x8C, 0xDC, 0xFF, // 9 Frozen / locked border
+];
+const SPECIAL_INDEX: u8 = 8;
+const BORDER_INDEX: u8 = 9;
+
+fn palette_index(element: u8) -> u8 {
+    match element {
+        1..=5 => element,
+        10 => 6,
+        11 => 7,
+        _ => 0,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GifOptions {
+    pub cell_px: u16,
+    pub frame_delay_cs: u16,     // Thời gian mỗi frame (1/100 giây, đơn vị của GIF)
+    pub max_ticks_per_move: u32, // Loop guard cho cascade
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self { cell_px: 16, frame_delay_cs: 5, max_ticks_per_move: 1000 }
+    }
+}
+
+/// Framebuffer indexed (1 byte / pixel, theo `PALETTE`) của board hiện tại
+pub fn render_board(cells: &[Cell], width: usize, cell_px: usize) -> Vec<u8> {
+    let height = cells.len() / width.max(1);
+    let (img_w, img_h) = (width * cell_px, height * cell_px);
+    let mut pixels = vec![0u8; img_w * img_h];
+    let dot = (cell_px / 3).max(1);
+    for (idx, cell) in cells.iter().enumerate() {
+        let (ox, oy) = ((idx % width) * cell_px, (idx / width) * cell_px);
+        let bordered = cell.flags & (FLAG_FROZEN | FLAG_LOCKED) != 0;
+        let special = cell.flags & FLAG_SPECIAL_MASK != 0;
+        for py in 0..cell_px {
+            for px in 0..cell_px {
+                let edge = px == 0 || py == 0 || px + 1 == cell_px || py + 1 == cell_px;
+                let center = (cell_px - dot) / 2;
+                let in_dot = (center..center + dot).contains(&px) && (center..center + dot).contains(&py);
+                pixels[(oy + py) * img_w + ox + px] = if bordered && edge {
+                    BORDER_INDEX
+                } else if special && in_dot {
+                    SPECIAL_INDEX
+                } else {
+                    palette_index(cell.element)
+                };
+            }
+        }
+    }
+    pixels
+}
+
+/// Replay `moves` trên ván mới theo `config` và ghi GIF vào `out`. Returns số frame đã ghi.
+pub fn export_replay_gif<W: Write>(config: &GameConfig, moves: &[u8], options: &GifOptions, out: W) -> Result<u32, EncodingError> {
+    let cell_px = options.cell_px.max(1) as usize;
+    let (img_w, img_h) = (config.width * cell_px, config.height * cell_px);
+    if img_w > u16::MAX as usize || img_h > u16::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "board too large for GIF").into());
+    }
+    let (img_w, img_h) = (img_w as u16, img_h as u16);
+
+    let mut game = Game::new(config);
+    let mut encoder = Encoder::new(out, img_w, img_h, &PALETTE)?;
+    encoder.set_repeat(Repeat::Infinite)?;
+    let mut frames = 0;
+    let mut write_frame = |game: &Game, encoder: &mut Encoder<W>| -> Result<(), EncodingError> {
+        let mut frame = Frame::from_indexed_pixels(img_w, img_h, render_board(game.cells(), game.width(), cell_px), None);
+        frame.delay = options.frame_delay_cs;
+        encoder.write_frame(&frame)?;
+        frames += 1;
+        Ok(())
+    };
+
+    write_frame(&game, &mut encoder)?;
+    for mv in moves.chunks_exact(4) {
+        let (a, b) = ((mv[0] as usize, mv[1] as usize), (mv[2] as usize, mv[3] as usize));
+        if game.swap(a, b).is_err() { continue; }
+        write_frame(&game, &mut encoder)?;
+        for _ in 0..options.max_ticks_per_move {
+            game.step();
+            write_frame(&game, &mut encoder)?;
+            if game.is_stable() { break; }
+        }
+    }
+    Ok(frames)
+}
@@ -1,13 +1,28 @@
 mod ecs;
 mod sim;
+pub mod api;
+#[cfg(feature = "bevy")]
+pub mod bevy_adapter;
+#[cfg(feature = "gif-export")]
+pub mod gif_export;
 
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+compile_error!("feature `parallel` chỉ dành cho build native (wasm không có thread pool)");
+
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
-use sim::grid::GridState; 
+// Không bật feature `wasm` (mặc định bật): thư viện Rust thuần cho validation service,
+// GridState / validate_replay* dùng trực tiếp, không cần glue JS.
+pub use sim::grid::GridState;
 pub use sim::simulation::Simulation;
+pub use sim::replay::ReplayPlayer;
+pub use sim::rng::SharedRng;
 pub use ecs::component::Component;
+pub use ecs::stats::{ComponentStats, WorldStats};
 
 mod tests;
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
@@ -15,13 +30,14 @@ extern "C" {
 }
 
 // Initializes the panic hook for better error messages in the browser console
+#[cfg(feature = "wasm")]
 #[wasm_bindgen(start)]
 pub fn start() {
     console_error_panic_hook::set_once();
 }
 
 // Factory function to create GridState from JS
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub fn create_grid(width: usize, height: usize, seed: u64) -> GridState {
-    GridState::new(width, height, seed)
+    api::Game::new(&api::GameConfig { width, height, seed, ..api::GameConfig::default() }).into_grid()
 }
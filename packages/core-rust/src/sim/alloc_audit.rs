@@ -0,0 +1,57 @@
+// --- HOT PATH ALLOCATION AUDIT ---
+// Chỉ build trong test: global allocator đếm số lần cấp phát heap theo từng thread.
+// Dùng count_allocations(|| grid.tick()) để chặn regression ở hot path.
+//
+// Đếm theo thread nên các test chạy song song không làm nhiễu nhau.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAlloc;
+
+thread_local! {
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+    static COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record() {
+    // try_with: thread đang huỷ thì bỏ qua
+    let _ = ACTIVE.try_with(|active| {
+        if active.get() {
+            let _ = COUNT.try_with(|c| c.set(c.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record();
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// Số lần alloc / realloc xảy ra trong f (trên thread hiện tại)
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    COUNT.with(|c| c.set(0));
+    ACTIVE.with(|a| a.set(true));
+    let result = f();
+    ACTIVE.with(|a| a.set(false));
+    (result, COUNT.with(|c| c.get()))
+}
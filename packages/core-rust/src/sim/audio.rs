@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use super::events::{EventType, MAX_CLEAR_TYPE};
+
+// --- AUDIO CUE CHANNEL ---
+// Buffer riêng cho sound engine, tách khỏi stream hình ảnh.
+// Mỗi tick tối đa 1 cue cho mỗi category: cascade nổ 40 ô chỉ ra 1 MatchPop với count = 40.
+//
+// Layout: mỗi cue là 1 u32 nén [Cue(8) | 0(8) | Count(8) | Intensity(8)]
+// - count: số event gốc đã gộp (bão hoà 255)
+// - intensity: intensity lớn nhất trong các event gốc
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioCue {
+    MatchPop = 0,
+    CycleAdvance = 1,
+    AvatarEnter = 2,
+    AvatarExit = 3,
+    Destruction = 4,
+    Generation = 5,
+    SpecialActivation = 6,
+    Resonance = 7,
+    InvalidMove = 8,
+}
+
+const CUE_CATEGORIES: usize = 9;
+pub const AUDIO_CUE_CAPACITY: usize = 256;
+
+impl AudioCue {
+    // Event hình ảnh -> cue âm thanh (None = event không có tiếng)
+    pub fn from_event(type_id: u8) -> Option<AudioCue> {
+        if type_id <= MAX_CLEAR_TYPE { return Some(AudioCue::MatchPop); }
+        match EventType::from_id(type_id)? {
+            EventType::Destruction => Some(AudioCue::Destruction),
+            EventType::GenerationWaterSpawn
+            | EventType::GenerationFireSpread
+            | EventType::GenerationGrowth => Some(AudioCue::Generation),
+            EventType::CycleProgressSinh | EventType::CycleProgressKhac => Some(AudioCue::CycleAdvance),
+            EventType::CycleCompleteSinh | EventType::CycleCompleteKhac => Some(AudioCue::AvatarEnter),
+            EventType::AvatarExit => Some(AudioCue::AvatarExit),
+            EventType::Resonance => Some(AudioCue::Resonance),
+            EventType::SpecialActivation => Some(AudioCue::SpecialActivation),
+            EventType::SwapRejected => Some(AudioCue::InvalidMove),
+            EventType::Clear
+            | EventType::Conversion
+            | EventType::Fall
+            | EventType::Spawn
+            | EventType::BoardStable
+            | EventType::BoardUnstable
+            | EventType::CycleGraceUsed
+            | EventType::MomentumDecay
+            | EventType::CycleMilestone
+            | EventType::LevelUp
+            | EventType::EntityDied => None,
+        }
+    }
+}
+
+pub struct AudioCueBuffer {
+    cues: VecDeque<u32>,
+    frame: u32,
+    // Vị trí (tính từ đầu buffer) của cue mỗi category trong frame hiện tại
+    slots: [Option<usize>; CUE_CATEGORIES],
+}
+
+impl AudioCueBuffer {
+    pub fn new() -> Self {
+        Self {
+            cues: VecDeque::with_capacity(AUDIO_CUE_CAPACITY),
+            frame: 0,
+            slots: [None; CUE_CATEGORIES],
+        }
+    }
+
+    pub fn record(&mut self, type_id: u8, intensity: u8, frame: u32) {
+        let Some(cue) = AudioCue::from_event(type_id) else { return; };
+        if frame != self.frame {
+            self.frame = frame;
+            self.slots = [None; CUE_CATEGORIES];
+        }
+
+        if let Some(i) = self.slots[cue as usize] {
+            let packed = self.cues[i];
+            let count = ((packed >> 8) & 0xFF).saturating_add(1).min(255);
+            let max_intensity = (packed & 0xFF).max(intensity as u32);
+            self.cues[i] = (packed & 0xFF00_0000) | (count << 8) | max_intensity;
+            return;
+        }
+
+        if self.cues.len() >= AUDIO_CUE_CAPACITY {
+            // Bỏ cue cũ nhất, dời index các slot của frame hiện tại
+            self.cues.pop_front();
+            for slot in self.slots.iter_mut() {
+                *slot = slot.and_then(|i| i.checked_sub(1));
+            }
+        }
+        self.slots[cue as usize] = Some(self.cues.len());
+        self.cues.push_back(((cue as u32) << 24) | (1 << 8) | intensity as u32);
+    }
+
+    pub fn clear(&mut self) {
+        self.cues.clear();
+        self.slots = [None; CUE_CATEGORIES];
+    }
+
+    pub fn len(&self) -> usize {
+        self.cues.len()
+    }
+
+    pub fn ptr(&mut self) -> *const u32 {
+        self.cues.make_contiguous().as_ptr()
+    }
+}
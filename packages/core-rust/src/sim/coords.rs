@@ -0,0 +1,72 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use crate::ecs::components::{Position, Velocity};
+
+// --- GRID <-> WORLD COORDINATES ---
+// Core sở hữu phép ánh xạ duy nhất giữa ô lưới và toạ độ world,
+// renderer và entity gắn lưới (boss segment, hazard bò) dùng chung.
+//
+// Quy ước cell space: (2.0, 3.0) = tâm ô cột 2, hàng 3. Phần lẻ = đang nằm giữa 2 ô.
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionSpace {
+    World = 0,
+    Cell = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellMapping {
+    pub origin_x: f32, // Góc trên-trái của ô (0,0) trong world
+    pub origin_y: f32,
+    pub cell_size: f32,
+}
+
+impl Default for CellMapping {
+    fn default() -> Self {
+        Self { origin_x: 0.0, origin_y: 0.0, cell_size: 1.0 }
+    }
+}
+
+impl CellMapping {
+    pub fn cell_to_world(&self, cell: Position) -> Position {
+        Position {
+            x: self.origin_x + (cell.x + 0.5) * self.cell_size,
+            y: self.origin_y + (cell.y + 0.5) * self.cell_size,
+        }
+    }
+
+    pub fn world_to_cell(&self, world: Position) -> Position {
+        Position {
+            x: (world.x - self.origin_x) / self.cell_size - 0.5,
+            y: (world.y - self.origin_y) / self.cell_size - 0.5,
+        }
+    }
+
+    // Ô chứa điểm world (có thể nằm ngoài board, JS tự kiểm tra biên)
+    pub fn world_to_cell_index(&self, world: Position) -> (i32, i32) {
+        (
+            ((world.x - self.origin_x) / self.cell_size).floor() as i32,
+            ((world.y - self.origin_y) / self.cell_size).floor() as i32,
+        )
+    }
+
+    // Vận tốc chỉ đổi đơn vị, không dịch gốc
+    pub fn velocity_to_world(&self, v: Velocity) -> Velocity {
+        Velocity { x: v.x * self.cell_size, y: v.y * self.cell_size }
+    }
+
+    pub fn velocity_to_cell(&self, v: Velocity) -> Velocity {
+        Velocity { x: v.x / self.cell_size, y: v.y / self.cell_size }
+    }
+
+    // Đổi (pos, vel) của 1 entity sang không gian export.
+    // grid_bound = Position của entity đang lưu theo cell space.
+    pub fn export(&self, pos: Position, vel: Velocity, grid_bound: bool, space: PositionSpace) -> (Position, Velocity) {
+        match (grid_bound, space) {
+            (true, PositionSpace::World) => (self.cell_to_world(pos), self.velocity_to_world(vel)),
+            (false, PositionSpace::Cell) => (self.world_to_cell(pos), self.velocity_to_cell(vel)),
+            _ => (pos, vel),
+        }
+    }
+}
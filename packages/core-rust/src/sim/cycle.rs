@@ -0,0 +1,345 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
+use super::events::EventType;
+
+// --- CYCLE SYSTEM LOGIC ---
+
+// Avatar phase kết thúc sau N match, hoặc ở đầu lượt thứ T kể từ lượt kích hoạt.
+// Đếm theo lượt (swap hợp lệ) chứ không theo tick: client tick cả lúc rảnh, replay thì không.
+pub const AVATAR_MAX_MATCHES: u32 = 5;
+pub const AVATAR_MAX_TURNS: u32 = 3;
+// Thoát Avatar: chuỗi về đầu nhưng giữ lại 1 phần multiplier (không rơi thẳng về x1)
+pub const AVATAR_EXIT_DECAY_PCT: u32 = 50;
+// Chu trình lặp lại sau 5 bước, forecast dài hơn chỉ là lặp lại
+pub const MAX_TARGET_FORECAST: usize = 20;
+
+// Thứ tự chu trình được chọn theo level
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CycleMode {
+    // Tương Sinh: Water(3) -> Wood(2) -> Fire(4) -> Earth(5) -> Metal(1) -> Water(3)
+    Generation = 0,
+    // Tương Khắc: Metal(1) -> Wood(2) -> Earth(5) -> Water(3) -> Fire(4) -> Metal(1)
+    Destruction = 1,
+}
+
+impl CycleMode {
+    pub fn from_id(id: u8) -> Option<CycleMode> {
+        match id {
+            0 => Some(CycleMode::Generation),
+            1 => Some(CycleMode::Destruction),
+            _ => None,
+        }
+    }
+
+    pub fn start_target(self) -> u8 {
+        match self {
+            CycleMode::Generation => 3,  // Water
+            CycleMode::Destruction => 1, // Metal
+        }
+    }
+
+    pub fn next_target(self, target: u8) -> u8 {
+        match self {
+            CycleMode::Generation => match target {
+                3 => 2, // Water -> Wood
+                2 => 4, // Wood -> Fire
+                4 => 5, // Fire -> Earth
+                5 => 1, // Earth -> Metal
+                1 => 3, // Metal -> Water
+                _ => 3, // Fallback
+            },
+            CycleMode::Destruction => match target {
+                1 => 2, // Metal cuts Wood
+                2 => 5, // Wood breaks Earth
+                5 => 3, // Earth absorbs Water
+                3 => 4, // Water quenches Fire
+                4 => 1, // Fire melts Metal
+                _ => 1, // Fallback
+            },
+        }
+    }
+
+    // Khắc rủi ro hơn (dễ đứt chuỗi hơn trên board thường) nên multiplier tăng nhanh hơn
+    pub fn multiplier_step(self) -> u32 {
+        match self {
+            CycleMode::Generation => 1,
+            CycleMode::Destruction => 2,
+        }
+    }
+
+    // Sinh hoàn thành -> Avatar State. Khắc hoàn thành -> phá board (Sunder), không vào Avatar
+    pub fn enters_avatar(self) -> bool {
+        self == CycleMode::Generation
+    }
+
+    pub fn other(self) -> CycleMode {
+        match self {
+            CycleMode::Generation => CycleMode::Destruction,
+            CycleMode::Destruction => CycleMode::Generation,
+        }
+    }
+
+    // Event Type: 50 = Sinh progress, 52 = Khắc progress
+    pub fn progress_event(self) -> u8 {
+        match self {
+            CycleMode::Generation => EventType::CycleProgressSinh as u8,
+            CycleMode::Destruction => EventType::CycleProgressKhac as u8,
+        }
+    }
+
+    // Event Type: 55 = Sinh full cycle (Avatar), 57 = Khắc full cycle
+    pub fn complete_event(self) -> u8 {
+        match self {
+            CycleMode::Generation => EventType::CycleCompleteSinh as u8,
+            CycleMode::Destruction => EventType::CycleCompleteKhac as u8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycleState {
+    pub mode: CycleMode,
+    pub target: u8,        // ElementType (1-5)
+    pub chain_length: u32,
+    pub multiplier: u32,
+    pub is_avatar_state: bool,
+    pub avatar_matches_left: u32,
+    #[serde(alias = "avatar_ticks_left")]
+    pub avatar_turns_left: u32,
+    // Grace: số lần match lệch target được tha mỗi chuỗi (tiêu token thay vì reset)
+    #[serde(default)]
+    pub grace_per_chain: u32,
+    #[serde(default)]
+    pub grace_left: u32,
+    // Momentum: N lượt (swap hợp lệ) liền không có match tiến chuỗi -> multiplier giảm 1 bậc (0 = tắt).
+    // Đếm theo lượt chứ không theo tick: client tick cả lúc rảnh, replay thì không.
+    #[serde(default, alias = "momentum_decay_ticks")]
+    pub momentum_decay_turns: u32,
+    #[serde(default, alias = "idle_ticks")]
+    pub idle_turns: u32,
+}
+
+impl Default for CycleState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Toàn bộ trạng thái chu trình của 1 ván (export / import cho suspend-resume, server validate)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CycleTracks {
+    pub primary: CycleState,
+    pub dual: Option<CycleState>,
+    pub resonance_count: u32,
+}
+
+// Shared board (co-op / versus): mỗi người chơi 1 track chu trình + điểm riêng
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerTrack {
+    pub id: u32,
+    pub cycle: CycleState,
+    pub score: u64,
+    pub role_mask: u32, // Bit (1 << element) người chơi được cầm lên để swap, 0 = mọi element
+}
+
+impl CycleTracks {
+    // Dual phải là mode còn lại của track chính
+    pub fn is_valid(&self) -> bool {
+        self.primary.is_valid()
+            && self.dual.is_none_or(|d| d.is_valid() && d.mode == self.primary.mode.other())
+    }
+
+    // Track theo index event (0 = chính, 1 = dual)
+    pub fn get_mut(&mut self, track: u8) -> Option<&mut CycleState> {
+        match track {
+            0 => Some(&mut self.primary),
+            1 => self.dual.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut CycleState> {
+        std::iter::once(&mut self.primary).chain(self.dual.as_mut())
+    }
+
+    // Đổi track chính. Dual: hoán đổi 2 track, giữ nguyên tiến độ cả hai.
+    // Không dual: đổi sang mode kia (giữ luật grace / momentum), chuỗi bắt đầu lại.
+    pub fn switch(&mut self) {
+        match self.dual.as_mut() {
+            Some(dual) => std::mem::swap(&mut self.primary, dual),
+            None => self.primary = CycleState::with_rules_of(self.primary.mode.other(), &self.primary),
+        }
+    }
+}
+
+impl CycleState {
+    pub fn new() -> Self {
+        Self::with_mode(CycleMode::Generation)
+    }
+
+    pub fn with_mode(mode: CycleMode) -> Self {
+        Self {
+            mode,
+            target: mode.start_target(),
+            chain_length: 0,
+            multiplier: 1,
+            is_avatar_state: false,
+            avatar_matches_left: 0,
+            avatar_turns_left: 0,
+            grace_per_chain: 0,
+            grace_left: 0,
+            momentum_decay_turns: 0,
+            idle_turns: 0,
+        }
+    }
+
+    // Track mới ở `mode`, mang theo cấu hình luật (grace, momentum) của `rules`
+    pub fn with_rules_of(mode: CycleMode, rules: &CycleState) -> Self {
+        let mut cycle = Self::with_mode(mode);
+        cycle.set_grace(rules.grace_per_chain);
+        cycle.momentum_decay_turns = rules.momentum_decay_turns;
+        cycle
+    }
+
+    pub fn set_grace(&mut self, misses: u32) {
+        self.grace_per_chain = misses;
+        self.grace_left = misses;
+    }
+
+    // Check if match continues cycle
+    // Returns: (is_success, multiplier_applied)
+    pub fn process_match(&mut self, element: u8) -> (bool, u32) {
+        if self.is_avatar_state {
+             // In Avatar State, everything is a match/bonus?
+             // Or just huge multiplier?
+             // Let's keep existing logic but with boosted stats
+             // Hệ số Avatar (x2) được áp dụng trong scoring pipeline, không phải ở đây
+             self.multiplier = self.multiplier.saturating_add(1);
+             self.idle_turns = 0;
+             let applied = self.multiplier;
+             self.avatar_matches_left = self.avatar_matches_left.saturating_sub(1);
+             if self.avatar_matches_left == 0 {
+                 self.exit_avatar();
+             }
+             return (true, applied);
+        }
+
+        if element == self.target {
+            // SUCCESS
+            self.chain_length += 1;
+            self.multiplier = self.multiplier.saturating_add(self.mode.multiplier_step());
+            self.idle_turns = 0;
+
+            self.target = self.mode.next_target(self.target);
+
+            // Full cycle (Chain >= 5)
+            if self.chain_length >= 5 {
+                if self.mode.enters_avatar() {
+                    self.is_avatar_state = true;
+                    self.avatar_matches_left = AVATAR_MAX_MATCHES;
+                    self.avatar_turns_left = AVATAR_MAX_TURNS;
+                } else {
+                    // Khắc: chuỗi về đầu, giữ multiplier làm phần thưởng
+                    let multiplier = self.multiplier;
+                    self.reset();
+                    self.multiplier = multiplier;
+                }
+            }
+
+            (true, self.multiplier)
+        } else if self.chain_length > 0 && self.grace_left > 0 {
+            // GRACE: tiêu 1 token, giữ nguyên chuỗi / target / multiplier
+            self.grace_left -= 1;
+            (false, 1)
+        } else {
+            // BROKEN
+            self.reset();
+            (false, 1)
+        }
+    }
+
+    // Booster: nhảy qua target hiện tại, chuỗi / multiplier giữ nguyên. Không dùng được trong Avatar.
+    pub fn skip_target(&mut self) -> bool {
+        if self.is_avatar_state { return false; }
+        self.target = self.mode.next_target(self.target);
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.chain_length = 0;
+        self.multiplier = 1;
+        self.target = self.mode.start_target();
+        self.is_avatar_state = false;
+        self.avatar_matches_left = 0;
+        self.avatar_turns_left = 0;
+        self.grace_left = self.grace_per_chain;
+        self.idle_turns = 0;
+    }
+
+    fn exit_avatar(&mut self) {
+        let kept = (self.multiplier * AVATAR_EXIT_DECAY_PCT / 100).max(1);
+        self.reset();
+        self.multiplier = kept;
+    }
+
+    // Đếm ngược Avatar khi 1 lượt mới bắt đầu. Returns true nếu Avatar vừa hết hạn.
+    pub fn turn_avatar(&mut self) -> bool {
+        if !self.is_avatar_state { return false; }
+        self.avatar_turns_left = self.avatar_turns_left.saturating_sub(1);
+        if self.avatar_turns_left == 0 {
+            self.exit_avatar();
+            return true;
+        }
+        false
+    }
+
+    // Trạng thái có thể đạt được qua process_match / turn_avatar (chặn state import bị sửa tay)
+    pub fn is_valid(&self) -> bool {
+        let chain_ok = if self.is_avatar_state {
+            self.mode.enters_avatar() && self.avatar_matches_left <= AVATAR_MAX_MATCHES && self.avatar_turns_left <= AVATAR_MAX_TURNS
+        } else {
+            self.chain_length < 5 && self.avatar_matches_left == 0 && self.avatar_turns_left == 0
+        };
+        let idle_ok = self.idle_turns <= self.momentum_decay_turns;
+        (1..=5).contains(&self.target) && self.multiplier >= 1 && self.grace_left <= self.grace_per_chain && chain_ok && idle_ok
+    }
+
+    // Sau process_match: true nếu `hit` vừa hoàn thành 1 chu trình không vào Avatar (Khắc)
+    pub fn completed_without_avatar(&self, hit: bool) -> bool {
+        hit && !self.mode.enters_avatar() && !self.is_avatar_state && self.chain_length == 0
+    }
+
+    // Gọi khi 1 lượt mới bắt đầu (swap hợp lệ). idle_turns = số lượt từ lần tiến chuỗi gần nhất;
+    // đủ N lượt trắng thì lượt kế tiếp mở đầu bằng giảm 1 bậc multiplier (không xuống dưới x1).
+    // Returns true nếu vừa giảm. Avatar có bộ đếm riêng nên không bị momentum decay.
+    pub fn turn_momentum(&mut self) -> bool {
+        if self.momentum_decay_turns == 0 || self.is_avatar_state || self.multiplier <= 1 {
+            self.idle_turns = 0;
+            return false;
+        }
+        let decayed = self.idle_turns >= self.momentum_decay_turns;
+        if decayed {
+            self.idle_turns = 0;
+            self.multiplier = self.multiplier.saturating_sub(self.mode.multiplier_step()).max(1);
+        }
+        self.idle_turns += 1;
+        decayed
+    }
+
+    // Target hiện tại + các target kế tiếp theo thứ tự của mode (forecast strip trên HUD)
+    pub fn upcoming_targets(&self, count: usize) -> Vec<u8> {
+        std::iter::successors(Some(self.target), |&t| Some(self.mode.next_target(t)))
+            .take(count.min(MAX_TARGET_FORECAST))
+            .collect()
+    }
+
+    pub fn get_chain_length(&self) -> u32 {
+        self.chain_length
+    }
+
+    pub fn is_avatar(&self) -> bool {
+        self.is_avatar_state
+    }
+}
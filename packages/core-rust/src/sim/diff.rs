@@ -0,0 +1,85 @@
+use serde::Serialize;
+use super::grid::GridState;
+use super::snapshot::SnapshotError;
+
+// --- STATE DIFF ---
+// Chẩn đoán replay lệch: ô nào khác, field ẩn nào khác (điểm, chu trình, RNG...),
+// serialize thẳng vào log server thay vì chỉ có 1 checksum.
+
+pub const MAX_DIFF_CELLS: usize = 32; // Chỉ liệt kê N ô lệch đầu tiên, cell_count vẫn đếm đủ
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct CellDiff {
+    pub index: u32,
+    pub x: u32,
+    pub y: u32,
+    pub element: (u8, u8), // (bên trái, bên phải)
+    pub flags: (u8, u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    pub left: u64,
+    pub right: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StateDiff {
+    pub cells: Vec<CellDiff>,
+    pub cell_count: u32,
+    pub fields: Vec<FieldDiff>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.cell_count == 0 && self.fields.is_empty()
+    }
+}
+
+pub fn diff_states(left: &GridState, right: &GridState) -> StateDiff {
+    let mut diff = StateDiff::default();
+    let (lf, rf) = (left.state_fields(), right.state_fields());
+    for (&(field, l), &(_, r)) in lf.iter().zip(&rf) {
+        if l != r { diff.fields.push(FieldDiff { field, left: l, right: r }); }
+    }
+    // Khác kích thước thì so cells không có nghĩa (field width / height đã báo)
+    if left.get_width() != right.get_width() || left.get_height() != right.get_height() { return diff; }
+
+    let width = left.get_width();
+    for (index, (a, b)) in left.cells().iter().zip(right.cells()).enumerate() {
+        if a == b { continue; }
+        diff.cell_count += 1;
+        if diff.cells.len() < MAX_DIFF_CELLS {
+            diff.cells.push(CellDiff {
+                index: index as u32,
+                x: (index % width) as u32,
+                y: (index / width) as u32,
+                element: (a.element, b.element),
+                flags: (a.flags, b.flags),
+            });
+        }
+    }
+    diff
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReplayDiagnosis {
+    pub score: u64,
+    pub checksum_match: bool,
+    pub diff: StateDiff, // left = server replay, right = client
+}
+
+// Replay moves trên server rồi diff với state cuối client gửi lên
+pub fn diagnose_replay(width: usize, height: usize, seed: u64, moves: &[u8], client_snapshot: &[u8]) -> Result<ReplayDiagnosis, SnapshotError> {
+    let client = GridState::from_snapshot(client_snapshot)?;
+    let mut server = GridState::new(width, height, seed);
+    for record in moves.chunks_exact(4) {
+        server.replay_record(record);
+    }
+    Ok(ReplayDiagnosis {
+        score: server.score_u64(),
+        checksum_match: server.get_checksum() == client.get_checksum(),
+        diff: diff_states(&server, &client),
+    })
+}
@@ -0,0 +1,70 @@
+// --- DROUGHT AUDIT / PITY ---
+// "Mãi không ra Water": đếm số tile refill liên tiếp không có mỗi element (drought),
+// luôn bật để audit. Pity (mặc định tắt) tăng nhẹ trọng số của element đang hạn:
+//   weight(e) = 100 + min(max_boost_pct, step_pct × (drought(e) - threshold + 1))  khi drought(e) >= threshold
+// Chạy trong core trên RNG của grid -> tất định, replay validate với cùng cấu hình ra cùng board.
+
+pub const DROUGHT_ELEMENTS: usize = 5;
+pub const BASE_WEIGHT: u32 = 100;
+// Trần của max_boost_pct (x100 trọng số gốc): tổng 5 trọng số luôn vừa u32 dù JS truyền gì
+pub const MAX_BOOST_PCT: u32 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DroughtPity {
+    pub threshold: u32, // Số refill không có element trước khi bắt đầu bù (0 = tắt)
+    pub step_pct: u32,
+    pub max_boost_pct: u32,
+}
+
+impl DroughtPity {
+    pub const OFF: DroughtPity = DroughtPity { threshold: 0, step_pct: 0, max_boost_pct: 0 };
+
+    pub fn is_off(&self) -> bool {
+        self.threshold == 0 || self.step_pct == 0 || self.max_boost_pct == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DroughtTracker {
+    pub current: [u32; DROUGHT_ELEMENTS], // Drought hiện tại của element 1..=5
+    pub longest: [u32; DROUGHT_ELEMENTS],
+    pub pity_spawns: u32, // Số tile refill được rút với trọng số pity
+}
+
+impl DroughtTracker {
+    pub fn record_spawn(&mut self, element: u8) {
+        for (i, drought) in self.current.iter_mut().enumerate() {
+            if i + 1 == element as usize {
+                *drought = 0;
+            } else {
+                *drought = drought.saturating_add(1);
+                self.longest[i] = self.longest[i].max(*drought);
+            }
+        }
+    }
+
+    // Trọng số rút element 1..=5, None = không element nào đang được bù (dùng công thức refill gốc)
+    pub fn pity_weights(&self, config: &DroughtPity) -> Option<[u32; DROUGHT_ELEMENTS]> {
+        if config.is_off() { return None; }
+        let mut boosted = false;
+        let mut weights = [BASE_WEIGHT; DROUGHT_ELEMENTS];
+        for (weight, &drought) in weights.iter_mut().zip(&self.current) {
+            if drought >= config.threshold {
+                let boost = config.step_pct.saturating_mul(drought - config.threshold + 1);
+                *weight += boost.min(config.max_boost_pct).min(MAX_BOOST_PCT);
+                boosted = true;
+            }
+        }
+        boosted.then_some(weights)
+    }
+}
+
+// Chọn element theo trọng số từ roll trong 0..tổng trọng số
+pub fn pick_weighted(weights: &[u32; DROUGHT_ELEMENTS], roll: u32) -> u8 {
+    let mut acc = 0;
+    for (i, &w) in weights.iter().enumerate() {
+        acc += w;
+        if roll < acc { return (i + 1) as u8; }
+    }
+    DROUGHT_ELEMENTS as u8
+}
@@ -0,0 +1,560 @@
+// --- EVENT BUFFER ---
+// Cầu nối sự kiện tới renderer / audio.
+//
+// v1 (legacy): mỗi event là 1 u32 nén [Type(8) | X(8) | Y(8) | Intensity(8)]
+// v2: struct-of-arrays, mỗi cột là 1 buffer zero-copy riêng:
+//     types: u8, xs: u16, ys: u16, intensities: u8, payloads: u32, frames: u32
+//     delays: u16, durations: u16 (timing metadata, ms)
+// Hai format luôn được ghi song song và có cùng độ dài / thứ tự.
+// frames = tick đã phát ra event, renderer gom theo frame để xếp lịch animation cascade.
+
+use std::collections::VecDeque;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use serde::Serialize;
+
+pub const EVENT_FORMAT_VERSION: u32 = 2;
+
+// --- EVENT TYPE IDS ---
+// Nguồn duy nhất cho Type ID, frontend dùng enum này thay vì magic number.
+// Type 0..=11 (Clear) = ô bị clear, Type chính là element vừa nổ (Client coi như "Pop").
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum EventType {
+    Clear = 0,                  // payload = clear_payload(cascade depth, combo size)
+    Destruction = 21,           // Tương Khắc phá ô
+    GenerationWaterSpawn = 31,  // Metal sinh Water
+    GenerationFireSpread = 32,  // Wood sinh Fire
+    GenerationGrowth = 33,      // Water nuôi Wood
+    Conversion = 34,            // payload = element cũ
+    CycleProgressSinh = 50,     // payload = multiplier
+    CycleProgressKhac = 52,     // payload = multiplier
+    CycleCompleteSinh = 55,     // Vào Avatar State
+    AvatarExit = 56,            // intensity = track (0 chính, 1 dual), payload = multiplier còn lại
+    CycleCompleteKhac = 57,
+    Resonance = 58,             // payload = multiplier tổng hai track
+    SpecialActivation = 60,     // intensity = chain depth, payload = special flags
+    SwapRejected = 61,          // (x, y) = ô thứ nhất, intensity = SwapRejectReason, payload = index ô thứ hai
+    Fall = 62,                  // (x, y) = ô đích, intensity = element, payload = index ô nguồn
+    Spawn = 63,                 // Refill: (x, y) = ô mới, intensity = element, payload = số ô rơi từ mép trên board
+    BoardStable = 64,           // Board vừa ổn định -> mở lại input, payload = số lượt (turn_count)
+    BoardUnstable = 65,         // Board bắt đầu chuyển động (swap, refill, boss...), payload = số lượt (turn_count)
+    CycleGraceUsed = 66,        // Match lệch target được tha: intensity = track (0 chính, 1 dual), payload = grace còn lại
+    MomentumDecay = 67,         // Lâu không tiến chuỗi: intensity = track (0 chính, 1 dual), payload = multiplier còn lại
+    CycleMilestone = 68,        // intensity = track (0 chính, 1 dual), payload = cycle_milestone_payload(...)
+    LevelUp = 69,               // XP lên level: intensity = số level vừa lên, payload = level mới
+    EntityDied = 70,            // Entity ECS gắn ô bị huỷ vì ô bị clear: (x, y) = ô, payload = entity id
+}
+
+// Lý do try_swap từ chối / rollback, UI dùng để chơi hiệu ứng "invalid move"
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapRejectReason {
+    OutOfBounds = 1,
+    SameCell = 2,
+    Stone = 3,   // Vật cản không swap được
+    Locked = 4,  // Tile mang FLAG_LOCKED
+    NoMatch = 5, // Swap hợp lệ nhưng không tạo match -> rollback
+    NotYourTurn = 6,    // Co-op: chưa tới lượt của input source này
+    RoleRestricted = 7, // Co-op: tile được chọn không thuộc element của vai trò
+}
+
+pub const MAX_CLEAR_TYPE: u8 = 11; // Dark
+
+// Payload v2 của event Clear: [0(16) | Cascade depth(8) | Combo size(8)]
+// - cascade depth: lần resolve thứ mấy kể từ swap cuối (1 = match trực tiếp, 0 = ngoài cascade)
+// - combo size: số ô của cụm match chứa ô này, 0 = bị clear ké (interaction / special / ultimate)
+// v1 giữ intensity cố định 50 để không đổi hành vi renderer cũ.
+pub fn clear_payload(cascade_depth: u32, combo_size: usize) -> u32 {
+    (cascade_depth.min(255) << 8) | (combo_size.min(255) as u32)
+}
+
+// --- CYCLE MILESTONE ---
+// Event 50 / 52 chỉ mang độ dài chuỗi; CycleMilestone mô tả đầy đủ 1 bước ngoặt của chu trình
+// để meta-progression / achievement hook vào mà không phải suy ngược từ các event khác.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum CycleMilestoneKind {
+    Advance = 1,     // Match đúng target, chuỗi tiến (kể cả Khắc hoàn thành vòng)
+    Break = 2,       // Match lệch target làm đứt chuỗi (grace đã hết)
+    AvatarEnter = 3,
+    AvatarExit = 4,  // Hết lượt match hoặc hết giờ (pattern = 0)
+    Skip = 5,        // Booster skip_cycle_target (pattern = 0)
+}
+
+impl CycleMilestoneKind {
+    pub fn from_id(id: u8) -> Option<CycleMilestoneKind> {
+        match id {
+            1 => Some(CycleMilestoneKind::Advance),
+            2 => Some(CycleMilestoneKind::Break),
+            3 => Some(CycleMilestoneKind::AvatarEnter),
+            4 => Some(CycleMilestoneKind::AvatarExit),
+            5 => Some(CycleMilestoneKind::Skip),
+            _ => None,
+        }
+    }
+}
+
+// Payload: [Kind(4) | Pattern(4) | Target trước(8) | Target mới(8) | Multiplier(8)]
+// pattern = MatchPattern::id() của match gây ra, 0 = không do match
+pub fn cycle_milestone_payload(kind: CycleMilestoneKind, pattern: u8, prev_target: u8, new_target: u8, multiplier: u32) -> u32 {
+    ((kind as u32) << 28) | (((pattern & 0xF) as u32) << 24) | ((prev_target as u32) << 16) | ((new_target as u32) << 8) | multiplier.min(255)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct CycleMilestoneInfo {
+    pub kind: Option<CycleMilestoneKind>,
+    pub pattern: u8,
+    pub prev_target: u8,
+    pub new_target: u8,
+    pub multiplier: u32, // Bão hòa ở 255
+}
+
+impl CycleMilestoneInfo {
+    pub fn decode(payload: u32) -> Self {
+        Self {
+            kind: CycleMilestoneKind::from_id((payload >> 28) as u8),
+            pattern: ((payload >> 24) & 0xF) as u8,
+            prev_target: (payload >> 16) as u8,
+            new_target: (payload >> 8) as u8,
+            multiplier: payload & 0xFF,
+        }
+    }
+}
+
+// Giải mã payload CycleMilestone thành { kind, pattern, prev_target, new_target, multiplier }
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn describe_cycle_milestone(payload: u32) -> JsValue {
+    serde_wasm_bindgen::to_value(&CycleMilestoneInfo::decode(payload)).unwrap_or(JsValue::UNDEFINED)
+}
+
+impl EventType {
+    pub fn from_id(type_id: u8) -> Option<EventType> {
+        match type_id {
+            0..=MAX_CLEAR_TYPE => Some(EventType::Clear),
+            21 => Some(EventType::Destruction),
+            31 => Some(EventType::GenerationWaterSpawn),
+            32 => Some(EventType::GenerationFireSpread),
+            33 => Some(EventType::GenerationGrowth),
+            34 => Some(EventType::Conversion),
+            50 => Some(EventType::CycleProgressSinh),
+            52 => Some(EventType::CycleProgressKhac),
+            55 => Some(EventType::CycleCompleteSinh),
+            56 => Some(EventType::AvatarExit),
+            57 => Some(EventType::CycleCompleteKhac),
+            58 => Some(EventType::Resonance),
+            60 => Some(EventType::SpecialActivation),
+            61 => Some(EventType::SwapRejected),
+            62 => Some(EventType::Fall),
+            63 => Some(EventType::Spawn),
+            64 => Some(EventType::BoardStable),
+            65 => Some(EventType::BoardUnstable),
+            66 => Some(EventType::CycleGraceUsed),
+            67 => Some(EventType::MomentumDecay),
+            68 => Some(EventType::CycleMilestone),
+            69 => Some(EventType::LevelUp),
+            70 => Some(EventType::EntityDied),
+            _ => None,
+        }
+    }
+}
+
+// --- EVENT MASK ---
+// Mỗi EventType thuộc 1 category; consumer tắt category ngay tại nguồn bằng set_event_mask.
+// Ví dụ máy yếu: EVENT_MASK_ALL & !EventCategory::Clear -> bỏ pop từng ô, giữ interaction / special.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventCategory {
+    Clear = 1,        // Pop từng ô
+    Interaction = 2,  // Destruction / Generation
+    Conversion = 4,
+    Cycle = 8,        // Progress, full cycle, avatar exit, grace, momentum, resonance
+    Special = 16,     // Special activation
+    Feedback = 32,    // Phản hồi input (swap bị từ chối, board stable / unstable)
+    Movement = 64,    // Tile rơi do trọng lực / refill
+    Progression = 128, // XP / level up
+    Entity = 256,     // Event chuyển từ ECS (entity died...)
+}
+
+pub const EVENT_MASK_ALL: u32 = 0xFFFF_FFFF;
+
+impl EventType {
+    pub fn category(self) -> EventCategory {
+        match self {
+            EventType::Clear => EventCategory::Clear,
+            EventType::Destruction
+            | EventType::GenerationWaterSpawn
+            | EventType::GenerationFireSpread
+            | EventType::GenerationGrowth => EventCategory::Interaction,
+            EventType::Conversion => EventCategory::Conversion,
+            EventType::CycleProgressSinh
+            | EventType::CycleProgressKhac
+            | EventType::CycleCompleteSinh
+            | EventType::AvatarExit
+            | EventType::CycleGraceUsed
+            | EventType::MomentumDecay
+            | EventType::CycleMilestone
+            | EventType::CycleCompleteKhac
+            | EventType::Resonance => EventCategory::Cycle,
+            EventType::SpecialActivation => EventCategory::Special,
+            EventType::SwapRejected | EventType::BoardStable | EventType::BoardUnstable => EventCategory::Feedback,
+            EventType::Fall | EventType::Spawn => EventCategory::Movement,
+            EventType::LevelUp => EventCategory::Progression,
+            EventType::EntityDied => EventCategory::Entity,
+        }
+    }
+}
+
+// Kết quả giải mã 1 event v1 cho JS
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct EventInfo {
+    pub type_id: u8,
+    pub kind: Option<EventType>, // None = Type ID lạ (build mới hơn frontend)
+    pub element: Option<u8>,     // Chỉ có với Clear
+    pub x: u8,
+    pub y: u8,
+    pub intensity: u8,
+}
+
+impl EventInfo {
+    pub fn decode(packed: u32) -> Self {
+        let type_id = (packed >> 24) as u8;
+        let kind = EventType::from_id(type_id);
+        Self {
+            type_id,
+            kind,
+            element: if kind == Some(EventType::Clear) { Some(type_id) } else { None },
+            x: (packed >> 16) as u8,
+            y: (packed >> 8) as u8,
+            intensity: packed as u8,
+        }
+    }
+}
+
+// Giải mã 1 u32 nén (v1) thành { type_id, kind, element, x, y, intensity }
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn describe_event(packed: u32) -> JsValue {
+    serde_wasm_bindgen::to_value(&EventInfo::decode(packed)).unwrap_or(JsValue::UNDEFINED)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct GridEvent {
+    pub event_type: u8,
+    pub x: u16,
+    pub y: u16,
+    pub intensity: u8,
+    pub payload: u32,
+    pub frame: u32, // Tick counter của GridState lúc phát event
+    pub delay_ms: u16,    // Gợi ý stagger so với đầu frame (0 = phát ngay)
+    pub duration_ms: u16, // Gợi ý thời lượng animation (0 = renderer tự chọn)
+}
+
+// --- ANIMATION TIMING ---
+// Event Destruction / Generation được stagger theo khoảng cách Chebyshev tới tâm match,
+// nên clear cả hàng / cột lan ra đều trên mọi kích thước board.
+pub const INTERACTION_STAGGER_MS: u16 = 40;
+pub const INTERACTION_DURATION_MS: u16 = 300;
+
+// Returns (delay_ms, duration_ms)
+pub fn interaction_timing(dx: usize, dy: usize) -> (u16, u16) {
+    let distance = dx.max(dy).min(u16::MAX as usize) as u16;
+    (distance.saturating_mul(INTERACTION_STAGGER_MS), INTERACTION_DURATION_MS)
+}
+
+// Fall: cả cột rơi cùng lúc (delay 0), thời gian tỉ lệ với số ô rơi
+pub const FALL_MS_PER_CELL: u16 = 60;
+
+// Returns (delay_ms, duration_ms)
+pub fn fall_timing(distance: usize) -> (u16, u16) {
+    let distance = distance.min(u16::MAX as usize) as u16;
+    (0, distance.saturating_mul(FALL_MS_PER_CELL))
+}
+
+// --- EVENT LOG ---
+// Ghi toàn bộ event stream (trước mask / capacity, không bị JS clear) để diff giữa client và server
+// khi replay lệch. Mỗi record 20 byte little-endian:
+//   frame u32 | payload u32 | x u16 | y u16 | delay u16 | duration u16 | type u8 | intensity u8 | 0 u16
+pub const EVENT_LOG_RECORD_SIZE: usize = 20;
+
+// Index record đầu tiên khác nhau giữa 2 log (log ngắn hơn = khác ở record cuối của nó), None = giống hệt.
+// Không so frame: client tick cả lúc rảnh nên frame tuyệt đối lệch server dù event giống hệt.
+pub fn first_log_divergence(a: &[u8], b: &[u8]) -> Option<usize> {
+    let mut records = a.chunks(EVENT_LOG_RECORD_SIZE).zip(b.chunks(EVENT_LOG_RECORD_SIZE));
+    if let Some(i) = records.position(|(ra, rb)| ra.get(4..) != rb.get(4..)) {
+        return Some(i);
+    }
+    if a.len() == b.len() { None } else { Some(a.len().min(b.len()) / EVENT_LOG_RECORD_SIZE) }
+}
+
+impl GridEvent {
+    fn write_log_record(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.frame.to_le_bytes());
+        out.extend_from_slice(&self.payload.to_le_bytes());
+        out.extend_from_slice(&self.x.to_le_bytes());
+        out.extend_from_slice(&self.y.to_le_bytes());
+        out.extend_from_slice(&self.delay_ms.to_le_bytes());
+        out.extend_from_slice(&self.duration_ms.to_le_bytes());
+        out.extend_from_slice(&[self.event_type, self.intensity, 0, 0]);
+    }
+}
+
+impl GridEvent {
+    // Layout v1: [Type (8) | X (8) | Y (8) | Intensity (8)]
+    // Ví dụ: Fire (4) tại (3,5) cường độ Max (255) -> 0x040305FF
+    pub fn packed(&self) -> u32 {
+        ((self.event_type as u32) << 24)
+            | (((self.x & 0xFF) as u32) << 16)
+            | (((self.y & 0xFF) as u32) << 8)
+            | (self.intensity as u32)
+    }
+}
+
+// --- OVERFLOW POLICY ---
+// Buffer có capacity cố định, JS quên clear_events cũng không làm phình WASM memory.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventOverflowPolicy {
+    DropOldest = 0,  // Ghi đè event cũ nhất (ring buffer)
+    DropNewest = 1,  // Bỏ event mới
+    Coalesce = 2,    // Gộp vào event cùng (frame, type, x, y) nếu có, không thì như DropOldest
+}
+
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+// Mỗi cột là 1 ring buffer; trước khi đưa pointer cho JS thì make_contiguous()
+// nên JS vẫn đọc 1 đoạn liền, cũ -> mới.
+pub struct EventBuffer {
+    packed: VecDeque<u32>,
+    types: VecDeque<u8>,
+    xs: VecDeque<u16>,
+    ys: VecDeque<u16>,
+    intensities: VecDeque<u8>,
+    payloads: VecDeque<u32>,
+    frames: VecDeque<u32>,
+    delays: VecDeque<u16>,
+    durations: VecDeque<u16>,
+    capacity: usize,
+    policy: EventOverflowPolicy,
+    mask: u32, // Bit EventCategory được ghi vào buffer
+    dropped: u32,
+    total_pushed: u64, // Mọi event đã phát, kể cả bị mask / drop / clear
+    stored: u64,       // Số event đã thực sự ghi vào buffer (= số thứ tự của event kế tiếp)
+    log: Option<Vec<u8>>, // Event log debug (opt-in), xem EVENT LOG
+    latest_frame: Vec<GridEvent>, // Mọi event của frame mới nhất, trước mask / capacity (photo mode)
+}
+
+impl EventBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            packed: VecDeque::with_capacity(capacity),
+            types: VecDeque::with_capacity(capacity),
+            xs: VecDeque::with_capacity(capacity),
+            ys: VecDeque::with_capacity(capacity),
+            intensities: VecDeque::with_capacity(capacity),
+            payloads: VecDeque::with_capacity(capacity),
+            frames: VecDeque::with_capacity(capacity),
+            delays: VecDeque::with_capacity(capacity),
+            durations: VecDeque::with_capacity(capacity),
+            capacity,
+            policy: EventOverflowPolicy::DropOldest,
+            mask: EVENT_MASK_ALL,
+            dropped: 0,
+            total_pushed: 0,
+            stored: 0,
+            log: None,
+            latest_frame: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: GridEvent) {
+        self.total_pushed += 1;
+        if let Some(log) = self.log.as_mut() {
+            event.write_log_record(log);
+        }
+        if self.latest_frame.last().is_some_and(|e| e.frame != event.frame) {
+            self.latest_frame.clear();
+        }
+        self.latest_frame.push(event);
+        // Type lạ (không có category) luôn được ghi
+        let category = EventType::from_id(event.event_type).map_or(EVENT_MASK_ALL, |t| t.category() as u32);
+        if self.mask & category == 0 { return; }
+        if self.len() >= self.capacity {
+            self.dropped = self.dropped.saturating_add(1);
+            match self.policy {
+                EventOverflowPolicy::DropNewest => return,
+                EventOverflowPolicy::Coalesce => {
+                    if self.coalesce(&event) { return; }
+                    self.pop_oldest();
+                }
+                EventOverflowPolicy::DropOldest => self.pop_oldest(),
+            }
+        }
+        self.packed.push_back(event.packed());
+        self.types.push_back(event.event_type);
+        self.xs.push_back(event.x);
+        self.ys.push_back(event.y);
+        self.intensities.push_back(event.intensity);
+        self.payloads.push_back(event.payload);
+        self.frames.push_back(event.frame);
+        self.delays.push_back(event.delay_ms);
+        self.durations.push_back(event.duration_ms);
+        self.stored += 1;
+    }
+
+    // Bật = bắt đầu log mới (rỗng), tắt = bỏ log
+    pub fn set_log_enabled(&mut self, enabled: bool) {
+        self.log = if enabled { Some(Vec::with_capacity(EVENT_LOG_RECORD_SIZE * 256)) } else { None };
+    }
+
+    pub fn log_bytes(&self) -> &[u8] {
+        self.log.as_deref().unwrap_or(&[])
+    }
+
+    fn event_at(&self, i: usize) -> GridEvent {
+        GridEvent {
+            event_type: self.types[i],
+            x: self.xs[i],
+            y: self.ys[i],
+            intensity: self.intensities[i],
+            payload: self.payloads[i],
+            frame: self.frames[i],
+            delay_ms: self.delays[i],
+            duration_ms: self.durations[i],
+        }
+    }
+
+    // Gộp vào event trùng (frame, type, x, y) gần nhất: giữ intensity lớn nhất, payload mới nhất
+    fn coalesce(&mut self, event: &GridEvent) -> bool {
+        let found = (0..self.len()).rev().find(|&i| {
+            self.frames[i] == event.frame
+                && self.types[i] == event.event_type && self.xs[i] == event.x && self.ys[i] == event.y
+        });
+        let Some(i) = found else { return false; };
+        self.intensities[i] = self.intensities[i].max(event.intensity);
+        self.payloads[i] = event.payload;
+        self.packed[i] = GridEvent { intensity: self.intensities[i], ..*event }.packed();
+        true
+    }
+
+    fn pop_oldest(&mut self) {
+        self.packed.pop_front();
+        self.types.pop_front();
+        self.xs.pop_front();
+        self.ys.pop_front();
+        self.intensities.pop_front();
+        self.payloads.pop_front();
+        self.frames.pop_front();
+        self.delays.pop_front();
+        self.durations.pop_front();
+    }
+
+    pub fn clear(&mut self) {
+        self.packed.clear();
+        self.types.clear();
+        self.xs.clear();
+        self.ys.clear();
+        self.intensities.clear();
+        self.payloads.clear();
+        self.frames.clear();
+        self.delays.clear();
+        self.durations.clear();
+    }
+
+    // Lấy ra toàn bộ event (cũ -> mới) dạng GridEvent, buffer rỗng sau khi gọi
+    pub fn drain(&mut self) -> Vec<GridEvent> {
+        let events = (0..self.len()).map(|i| self.event_at(i)).collect();
+        self.clear();
+        events
+    }
+
+    // Event của frame mới nhất đầy đủ chi tiết: không bị mask / tràn / JS clear
+    pub fn latest_frame_events(&self) -> &[GridEvent] {
+        &self.latest_frame
+    }
+
+    pub fn stored_count(&self) -> u64 {
+        self.stored
+    }
+
+    // Event có số thứ tự >= seq còn trong buffer (cũ -> mới).
+    // Event đã bị JS clear / drain hoặc bị đẩy ra do tràn thì không còn.
+    pub fn events_since(&self, seq: u64) -> impl Iterator<Item = GridEvent> + '_ {
+        let newer = self.stored.saturating_sub(seq).min(self.len() as u64) as usize;
+        (self.len() - newer..self.len()).map(|i| self.event_at(i))
+    }
+
+    // Frame cũ nhất còn trong buffer
+    pub fn oldest_frame(&self) -> Option<u32> {
+        self.frames.front().copied()
+    }
+
+    // Lấy ra (và xoá) toàn bộ event của frame cũ nhất, dạng v1 nén
+    pub fn drain_oldest_frame(&mut self) -> Vec<u32> {
+        let mut drained = Vec::new();
+        let Some(frame) = self.oldest_frame() else { return drained; };
+        while self.frames.front() == Some(&frame) {
+            if let Some(packed) = self.packed.front() { drained.push(*packed); }
+            self.pop_oldest();
+        }
+        drained
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Thu nhỏ capacity thì bỏ bớt event cũ nhất
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.len() > self.capacity {
+            self.pop_oldest();
+            self.dropped = self.dropped.saturating_add(1);
+        }
+    }
+
+    pub fn set_mask(&mut self, mask: u32) {
+        self.mask = mask;
+    }
+
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    pub fn set_policy(&mut self, policy: EventOverflowPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn policy(&self) -> EventOverflowPolicy {
+        self.policy
+    }
+
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    pub fn total_pushed(&self) -> u64 {
+        self.total_pushed
+    }
+
+    // Zero-copy pointers (cần &mut: ring buffer phải được xếp liền trước khi JS đọc)
+    pub fn packed_ptr(&mut self) -> *const u32 { self.packed.make_contiguous().as_ptr() }
+    pub fn types_ptr(&mut self) -> *const u8 { self.types.make_contiguous().as_ptr() }
+    pub fn xs_ptr(&mut self) -> *const u16 { self.xs.make_contiguous().as_ptr() }
+    pub fn ys_ptr(&mut self) -> *const u16 { self.ys.make_contiguous().as_ptr() }
+    pub fn intensities_ptr(&mut self) -> *const u8 { self.intensities.make_contiguous().as_ptr() }
+    pub fn payloads_ptr(&mut self) -> *const u32 { self.payloads.make_contiguous().as_ptr() }
+    pub fn frames_ptr(&mut self) -> *const u32 { self.frames.make_contiguous().as_ptr() }
+    pub fn delays_ptr(&mut self) -> *const u16 { self.delays.make_contiguous().as_ptr() }
+    pub fn durations_ptr(&mut self) -> *const u16 { self.durations.make_contiguous().as_ptr() }
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+use super::cycle::CycleMode;
+use super::grid::ResolutionPolicy;
+use super::scoring::DEFAULT_MODIFIER_PCT;
+
+// --- A/B EXPERIMENT VARIANTS ---
+// Backend gán bucket id cho người chơi, core chọn variant một cách tất định:
+//   variant = EXPERIMENT_VARIANTS[bucket % EXPERIMENT_VARIANTS.len()]
+// Bucket được ghi vào rules hash, header replay file và GameSummary: server dựng lại đúng variant.
+// Thêm variant thì append vào cuối bảng, KHÔNG chèn giữa (đổi mapping của bucket cũ).
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RuleVariant {
+    pub name: &'static str,
+    pub cycle_mode: CycleMode,
+    pub resolution_policy: ResolutionPolicy,
+    pub score_modifier_pct: u32,
+}
+
+pub const EXPERIMENT_VARIANTS: &[RuleVariant] = &[
+    // 0. Control: luật mặc định
+    RuleVariant { name: "control", cycle_mode: CycleMode::Generation, resolution_policy: ResolutionPolicy::FoundOrder, score_modifier_pct: DEFAULT_MODIFIER_PCT },
+    // 1. Sinh thắng khi match chồng nhau
+    RuleVariant { name: "generation_wins", cycle_mode: CycleMode::Generation, resolution_policy: ResolutionPolicy::GenerationWins, score_modifier_pct: DEFAULT_MODIFIER_PCT },
+    // 2. Bắt đầu bằng chu trình Khắc
+    RuleVariant { name: "destruction_cycle", cycle_mode: CycleMode::Destruction, resolution_policy: ResolutionPolicy::FoundOrder, score_modifier_pct: DEFAULT_MODIFIER_PCT },
+];
+
+pub fn variant_for_bucket(bucket: u32) -> &'static RuleVariant {
+    &EXPERIMENT_VARIANTS[bucket as usize % EXPERIMENT_VARIANTS.len()]
+}
@@ -1,7 +1,25 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
-use rand::{Rng, SeedableRng};
-use rand_chacha::ChaCha8Rng;
+use super::rng::{GameRng, RngStream, SharedRng};
+use super::cycle::{CycleState, CycleMode, CycleTracks, PlayerTrack, AVATAR_EXIT_DECAY_PCT, AVATAR_MAX_MATCHES, AVATAR_MAX_TURNS};
+use super::scoring::{saturate_u32, ActiveMultipliers, DecayTracker, ScoreBreakdown, ScoreDecay, AVATAR_SCORE_FACTOR, DEFAULT_MODIFIER_PCT, MAX_CASCADE_MULTIPLIER};
+use super::experiment::variant_for_bucket;
+use super::hazard::{HazardScheduler, ScheduledHazard};
+use super::drought::{pick_weighted, DroughtPity, DroughtTracker};
+use super::noise::{noise_element, BoardNoise};
+use super::stats::{GameSummary, SessionStats};
+#[cfg(feature = "wasm")]
+use super::diff::{diagnose_replay, diff_states};
+use super::moves::{decode_moves, encode_moves};
+#[cfg(feature = "wasm")]
+use super::replay::{replay_checked, verify_replay_summary, ReplayLimits, ReplayVerdict};
+use super::replay::{replay_steps, ReplayHeader, ReplayRules, REPLAY_FLAG_DESTRUCTION, REPLAY_FLAG_DUAL_CYCLE};
+use super::xp::{MAX_XP_LEVEL, XpCurve, XpProgress, XpRewards};
+use super::level::validate_level_layout;
+use super::snapshot::{snapshot_version, SnapshotError, SnapshotReader, SnapshotWriter, SNAPSHOT_VERSION};
+use super::audio::AudioCueBuffer;
+use super::events::{clear_payload, cycle_milestone_payload, interaction_timing, fall_timing, SwapRejectReason, CycleMilestoneKind, EventBuffer, EventOverflowPolicy, EventType, GridEvent, DEFAULT_EVENT_CAPACITY, EVENT_FORMAT_VERSION, first_log_divergence};
 
 // --- ĐỊNH NGHĨA VẬT CHẤT ---
 
@@ -25,9 +43,48 @@ pub const FLAG_FROZEN: u8 = 1;
 pub const FLAG_BURNING: u8 = 2; // Ash/Burning
 pub const FLAG_LOCKED: u8 = 4;
 pub const FLAG_WET: u8 = 8;
+// Special tiles: kích hoạt hiệu ứng khi bị clear
+pub const FLAG_STRIPED_H: u8 = 16; // Clear cả hàng
+pub const FLAG_STRIPED_V: u8 = 32; // Clear cả cột
+pub const FLAG_BOMB: u8 = 64;      // Clear 3x3
+pub const FLAG_SPECIAL_MASK: u8 = FLAG_STRIPED_H | FLAG_STRIPED_V | FLAG_BOMB;
+
+// Loop guard cho chuỗi kích hoạt special trong 1 lần resolve
+const MAX_SPECIAL_CHAIN_DEPTH: u8 = 16;
+
+// Điểm cho mỗi ô bị Avatar Ultimate quét (nhân với multiplier)
+const AVATAR_ULTIMATE_SCORE: u32 = 50;
+
+// Co-op mặc định: A cầm Metal / Water, B cầm Wood / Fire / Earth
+pub const COOP_PLAYER_A: u32 = 1;
+pub const COOP_PLAYER_B: u32 = 2;
+pub const COOP_ROLE_A: u32 = (1 << 1) | (1 << 3);
+pub const COOP_ROLE_B: u32 = (1 << 2) | (1 << 4) | (1 << 5);
+
+// Move journal: mỗi input được chấp nhận = 1 record 4 byte đúng format validate_replay.
+// Swap = [x1, y1, x2, y2]; booster = [JOURNAL_OP_MARKER, JOURNAL_OP_MARKER, op, 0]
+// (board rộng / cao tối đa 255 nên (255, 255) không phải ô hợp lệ).
+pub const JOURNAL_OP_MARKER: u8 = 0xFF;
+pub const JOURNAL_OP_SKIP_CYCLE: u8 = 1;
+pub const JOURNAL_OP_SWITCH_TRACK: u8 = 2;
+// [FF, FF, JOURNAL_OP_PLAYER, index người chơi]: record swap ngay sau là swap_as của người đó
+pub const JOURNAL_OP_PLAYER: u8 = 3;
+
+// Điểm cho mỗi ô bị Khắc Sunder phá (nhân với multiplier)
+const SUNDER_SCORE: u32 = 50;
+
+// Điểm cộng hưởng khi Sinh và Khắc cùng tiến (nhân với tổng multiplier)
+const RESONANCE_BONUS: u32 = 250;
+
+// Spawn: đều trên các element Ngũ Hành 1..=SPAWN_ELEMENT_COUNT
+const SPAWN_ELEMENT_COUNT: u8 = 5;
 
 // Cấu trúc Cell siêu gọn (2 bytes)
-#[derive(Clone, Copy, Debug)]
+// `flags` thuộc về tile, không thuộc về ô:
+// - Rơi / swap di chuyển cả Cell, flags / power đi theo tile.
+// - Tương tác đổi nguyên tố (Mộc->Hỏa, Kim->Thủy, Sinh) giữ nguyên flags cũ.
+// - Flags chỉ reset khi tile bị clear (hoặc refill tile mới).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(C)] // Đảm bảo layout bộ nhớ tương thích C để JS đọc an toàn
 pub struct Cell {
     pub element: u8,
@@ -45,6 +102,58 @@ pub enum MatchPattern {
     Area,  // 3x3 (Future use)
 }
 
+impl MatchPattern {
+    // Độ ưu tiên khi resolve chồng lấn (cao hơn = áp dụng sau = thắng)
+    pub fn priority(self) -> u8 {
+        match self {
+            MatchPattern::Line3 => 0,
+            MatchPattern::Line4 => 1,
+            MatchPattern::Line5 => 2,
+            MatchPattern::Cross => 3,
+            MatchPattern::Area => 4,
+        }
+    }
+
+    // ID ổn định trong payload event (0 dành cho "không do match")
+    pub fn id(self) -> u8 {
+        match self {
+            MatchPattern::Line3 => 1,
+            MatchPattern::Line4 => 2,
+            MatchPattern::Line5 => 3,
+            MatchPattern::Cross => 4,
+            MatchPattern::Area => 5,
+        }
+    }
+}
+
+// Chính sách khi nhiều cụm match có interaction chồng lấn trong cùng 1 lần resolve
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ResolutionPolicy {
+    DestructionWins = 0,  // Destruction áp dụng sau cùng: ô đã bị phá không được Generation cứu lại
+    GenerationWins = 1,   // Generation áp dụng sau cùng: ô được chuyển hóa sẽ không bị clear
+    OrderedByPattern = 2, // Pattern lớn hơn (Cross > Line5 > Line4 > Line3) thắng
+    FoundOrder = 3,       // Luật gốc (mặc định): từng cụm theo thứ tự tìm thấy, cụm sau thấy board cụm trước đã chuyển hóa
+}
+
+impl ResolutionPolicy {
+    pub fn from_id(id: u8) -> Option<ResolutionPolicy> {
+        match id {
+            0 => Some(ResolutionPolicy::DestructionWins),
+            1 => Some(ResolutionPolicy::GenerationWins),
+            2 => Some(ResolutionPolicy::OrderedByPattern),
+            3 => Some(ResolutionPolicy::FoundOrder),
+            _ => None,
+        }
+    }
+}
+
+// Xem GridState::save_frame_state
+pub(crate) struct FrameState {
+    snapshot: Vec<u8>,
+    journal_len: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct MatchResult {
     pub pattern: MatchPattern,
@@ -53,48 +162,101 @@ pub struct MatchResult {
     pub center_idx: usize,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct GridState {
     width: usize,
     height: usize,
     cells: Vec<Cell>,
     
     // BUFFER SỰ KIỆN: Cầu nối tới WebGPU
-    // Mỗi u32 là một gói tin nén: [Type(8) | X(8) | Y(8) | Intensity(8)]
-    events: Vec<u32>,
+    // v1: mỗi u32 là một gói tin nén [Type(8) | X(8) | Y(8) | Intensity(8)]
+    // v2: struct-of-arrays (xem sim/events.rs)
+    events: EventBuffer,
+    audio_cues: AudioCueBuffer, // Kênh riêng cho sound engine (dedup theo tick)
     
     // GAMEPLAY STATE
-    score: u32,
+    tick_count: u32, // Đóng dấu frame cho event
+    // Thay đổi ô trong tick gần nhất, để sim cập nhật entity gắn ô (không qua JS)
+    tick_moves: Vec<(usize, usize)>, // (from, to) do trọng lực
+    tick_cleared: Vec<usize>,
+    score: u64,
+    score_breakdown: ScoreBreakdown, // Điểm theo nguồn, tổng = score
+    stats: SessionStats,
+    // Hash trail cho lockstep: get_state_hash sau mỗi `hash_trail_every` tick (0 = tắt)
+    hash_trail_every: u32,
+    hash_trail: Vec<u64>,
+    hash_trail_first_tick: u32,
+    xp: u64,
+    xp_curve: XpCurve,
+    xp_rewards: XpRewards,
+    board_noise: BoardNoise, // Tham số board đầu (new_with_noise), thuộc ruleset
     match_queue: Vec<u8>, // Store matched element types for Cycle System
 
     // State tracking
     is_stable: bool, // True nếu không có gì đang rơi hoặc nổ
+    reported_stable: bool, // Trạng thái đã báo qua event BoardStable / BoardUnstable
     pub auto_refill: bool,
     
     // RNG Deterministic
-    rng: ChaCha8Rng,
+    rng: GameRng,
     
     // Cycle System
-    cycle: CycleState,
+    tracks: CycleTracks, // Track chính + dual (chu trình ngược mode chính) + số lần cộng hưởng
+
+    // Scoring pipeline inputs
+    cascade_depth: u32,       // Số lần resolve liên tiếp kể từ swap cuối
+    score_modifier_pct: u32,  // Booster / level modifier (100 = x1)
+    score_decay: ScoreDecay,  // Anti-farming (endless mode), mặc định tắt
+    decay: DecayTracker,
+    drought_pity: DroughtPity, // Bù element bị hạn khi refill, mặc định tắt
+    drought: DroughtTracker,
+
+    // Overlapping-match resolution
+    resolution_policy: ResolutionPolicy,
+
+    // A/B experiment (None = không tham gia, chạy luật mặc định)
+    experiment_bucket: Option<u32>,
+
+    // Boss / hazard: hiệu ứng lên lịch theo lượt (1 lượt = 1 swap được chấp nhận)
+    turn_count: u32,
+    hazards: HazardScheduler,
+    // Shared board: track của người chơi đang được "mượn" vào self.tracks.primary từ lúc swap_as
+    // tới swap kế tiếp, kèm điểm board lúc bắt đầu để tính phần của người đó
+    players: Vec<PlayerTrack>,
+    active_player: Option<(usize, u64)>,
+    replay_player: Option<usize>, // Replay: người chơi của record swap kế tiếp (JOURNAL_OP_PLAYER), không lưu snapshot
+    // Lượt luân phiên theo thứ tự add_player: mỗi người `turn_window` swap (0 = ai cũng đi được)
+    turn_window: u32,
+    turn_slot: usize,
+    turn_used: u32,
+
+    // Booster skip target (meta game bán theo charge) + journal input để replay
+    cycle_skip_charges: u32,
+    seed: u64, // Seed tạo ván, ghi vào header replay file
+    move_journal: Vec<u8>,
+    recording_moves: bool, // Tắt = không ghi journal (vd tutorial / demo tự chơi)
+
+    // Sandbox / Practice mode
+    sandbox: bool,
+    tainted: bool, // Sticky: phiên đã từng bật sandbox thì không được submit
+    frozen_rng: Option<GameRng>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl GridState {
     // 1. KHỞI TẠO
     pub fn new(width: usize, height: usize, seed: u64) -> Self {
-        let mut grid = Self {
-            width,
-            height,
-            cells: vec![Cell { element: 0, flags: 0 }; width * height],
-            events: Vec::with_capacity(128), // Pre-allocate để tránh realloc liên tục
-            score: 0,
-            match_queue: Vec::with_capacity(64),
-            is_stable: true,
-            auto_refill: true,
-            rng: ChaCha8Rng::seed_from_u64(seed),
-            cycle: CycleState::new(),
-        };
-        grid.randomize(); // Khởi tạo ngẫu nhiên ban đầu
+        let mut grid = Self::new_empty(width, height, seed);
+        grid.randomize(BoardNoise::OFF); // Khởi tạo ngẫu nhiên ban đầu
+        grid
+    }
+
+    // Board đầu theo cụm noise (xem sim/noise.rs): `cluster_pct`% ô theo noise cỡ `scale` ô,
+    // còn lại random đều. Vẫn không có match sẵn. scale / cluster_pct = 0 -> giống new().
+    pub fn new_with_noise(width: usize, height: usize, seed: u64, scale: u32, cluster_pct: u32) -> Self {
+        let mut grid = Self::new_empty(width, height, seed);
+        grid.board_noise = BoardNoise { scale, cluster_pct: cluster_pct.min(100) };
+        grid.randomize(grid.board_noise);
         grid
     }
 
@@ -103,14 +265,103 @@ impl GridState {
             width,
             height,
             cells: vec![Cell { element: 0, flags: 0 }; width * height],
-            events: Vec::with_capacity(128),
+            events: EventBuffer::with_capacity(DEFAULT_EVENT_CAPACITY),
+            audio_cues: AudioCueBuffer::new(),
+            tick_count: 0,
+            tick_moves: Vec::with_capacity(64),
+            tick_cleared: Vec::with_capacity(64),
             score: 0,
+            score_breakdown: ScoreBreakdown::default(),
+            stats: SessionStats::default(),
+            hash_trail_every: 0,
+            hash_trail: Vec::new(),
+            hash_trail_first_tick: 0,
+            xp: 0,
+            xp_curve: XpCurve::DEFAULT,
+            xp_rewards: XpRewards::DEFAULT,
+            board_noise: BoardNoise::OFF,
             match_queue: Vec::with_capacity(64),
             is_stable: true,
+            reported_stable: true,
             auto_refill: true,
-            rng: ChaCha8Rng::seed_from_u64(seed),
-            cycle: CycleState::new(),
+            rng: GameRng::from_seed(seed),
+            tracks: CycleTracks::default(),
+            cascade_depth: 0,
+            score_modifier_pct: DEFAULT_MODIFIER_PCT,
+            score_decay: ScoreDecay::OFF,
+            decay: DecayTracker::default(),
+            drought_pity: DroughtPity::OFF,
+            drought: DroughtTracker::default(),
+            resolution_policy: ResolutionPolicy::FoundOrder,
+            experiment_bucket: None,
+            turn_count: 0,
+            hazards: HazardScheduler::default(),
+            players: Vec::new(),
+            active_player: None,
+            replay_player: None,
+            turn_window: 0,
+            turn_slot: 0,
+            turn_used: 0,
+            cycle_skip_charges: 0,
+            seed,
+            move_journal: Vec::new(),
+            recording_moves: true,
+            sandbox: false,
+            tainted: false,
+            frozen_rng: None,
+        }
+    }
+
+    // Hash 64 bit của toàn bộ state ảnh hưởng tới diễn biến ván: cells + flags, điểm, mọi cycle track,
+    // RNG, bộ đếm ẩn (decay, drought, hazard, lượt, charge, XP, cascade, lượt co-op).
+    // get_checksum chỉ nhìn cells nên 2 state khác nhau ở phần ẩn có thể trùng checksum.
+    // Không gồm tick_count / event / thống kê: không đổi diễn biến về sau.
+    pub fn get_state_hash(&self) -> u64 {
+        // Dùng lại encoding của snapshot cho các field, rồi FNV-1a
+        let mut w = SnapshotWriter::with_version(SNAPSHOT_VERSION);
+        for cell in &self.cells {
+            w.u8(cell.element);
+            w.u8(cell.flags);
+        }
+        w.score(self.score);
+        w.cycle(&self.tracks.primary);
+        w.bool(self.tracks.dual.is_some());
+        if let Some(dual) = &self.tracks.dual {
+            w.cycle(dual);
+        }
+        w.u32(self.tracks.resonance_count);
+        for p in &self.players {
+            w.u32(p.id);
+            w.cycle(&p.cycle);
+            w.score(p.score);
         }
+        w.rng(&self.rng);
+        w.bool(self.is_stable);
+        w.bytes(&self.match_queue);
+        w.u32(self.cascade_depth);
+        w.u32(self.turn_count);
+        w.u32(self.cycle_skip_charges);
+        w.u64(self.xp);
+        w.opt_u32(self.decay.last_element.map(u32::from));
+        w.u32(self.decay.element_streak);
+        w.opt_u32(self.decay.last_region.map(u32::from));
+        w.u32(self.decay.region_streak);
+        for drought in self.drought.current {
+            w.u32(drought);
+        }
+        w.u32(self.hazards.pending().len() as u32);
+        for hazard in self.hazards.pending() {
+            w.u32(hazard.idx as u32);
+            w.u8(hazard.flag);
+            w.u32(hazard.turn);
+        }
+        w.opt_u32(self.active_player.map(|(i, _)| i as u32));
+        w.score(self.active_player.map_or(0, |(_, start)| start));
+        w.u32(self.turn_slot as u32);
+        w.u32(self.turn_used);
+        let mut h = RuleHasher::new();
+        h.write_bytes(&w.finish());
+        h.0
     }
 
     pub fn get_checksum(&self) -> u32 {
@@ -141,8 +392,8 @@ impl GridState {
         self.cells.len()
     }
 
-    pub fn get_events_ptr(&self) -> *const u32 {
-        self.events.as_ptr()
+    pub fn get_events_ptr(&mut self) -> *const u32 {
+        self.events.packed_ptr()
     }
 
     pub fn get_events_len(&self) -> usize {
@@ -153,8 +404,118 @@ impl GridState {
         self.events.clear();
     }
 
+    // Buffer sự kiện có capacity cố định, tràn thì xử lý theo policy
+    pub fn set_event_capacity(&mut self, capacity: usize) {
+        self.events.set_capacity(capacity);
+    }
+
+    pub fn get_event_capacity(&self) -> usize {
+        self.events.capacity()
+    }
+
+    pub fn set_event_overflow_policy(&mut self, policy: EventOverflowPolicy) {
+        self.events.set_policy(policy);
+    }
+
+    pub fn get_event_overflow_policy(&self) -> EventOverflowPolicy {
+        self.events.policy()
+    }
+
+    // Bitmask EventCategory được ghi vào buffer (mặc định tất cả).
+    // Không ảnh hưởng kênh audio cue.
+    pub fn set_event_mask(&mut self, mask: u32) {
+        self.events.set_mask(mask);
+    }
+
+    pub fn get_event_mask(&self) -> u32 {
+        self.events.mask()
+    }
+
+    // Tổng số event bị drop / gộp do tràn buffer kể từ đầu session
+    pub fn events_dropped(&self) -> u32 {
+        self.events.dropped()
+    }
+
+    // EVENT FORMAT v2 (struct-of-arrays, cùng độ dài với get_events_len)
+    pub fn get_event_format_version(&self) -> u32 { EVENT_FORMAT_VERSION }
+    pub fn get_event_types_ptr(&mut self) -> *const u8 { self.events.types_ptr() }
+    pub fn get_event_xs_ptr(&mut self) -> *const u16 { self.events.xs_ptr() }
+    pub fn get_event_ys_ptr(&mut self) -> *const u16 { self.events.ys_ptr() }
+    pub fn get_event_intensities_ptr(&mut self) -> *const u8 { self.events.intensities_ptr() }
+    pub fn get_event_payloads_ptr(&mut self) -> *const u32 { self.events.payloads_ptr() }
+    pub fn get_event_frames_ptr(&mut self) -> *const u32 { self.events.frames_ptr() }
+    // Timing metadata (ms): chỉ Destruction / Generation có giá trị, còn lại = 0
+    pub fn get_event_delays_ptr(&mut self) -> *const u16 { self.events.delays_ptr() }
+    pub fn get_event_durations_ptr(&mut self) -> *const u16 { self.events.durations_ptr() }
+
+    // --- AUDIO CUES ---
+    // Mỗi u32: [Cue(8) | 0(8) | Count(8) | Intensity(8)], tối đa 1 cue / category / tick
+    pub fn get_audio_cues_ptr(&mut self) -> *const u32 {
+        self.audio_cues.ptr()
+    }
+
+    pub fn get_audio_cues_len(&self) -> usize {
+        self.audio_cues.len()
+    }
+
+    pub fn clear_audio_cues(&mut self) {
+        self.audio_cues.clear();
+    }
+
+    // --- PER-TICK FRAMING ---
+    // Mỗi event mang tick đã phát ra nó. Renderer đọc từng frame theo thứ tự
+    // để xếp lịch animation cho cascade kéo dài nhiều tick.
+    pub fn get_tick_count(&self) -> u32 {
+        self.tick_count
+    }
+
+    // Tick của frame cũ nhất còn trong buffer (undefined nếu rỗng)
+    pub fn get_next_event_frame(&self) -> Option<u32> {
+        self.events.oldest_frame()
+    }
+
+    // Lấy ra và xoá các event (v1 nén) của frame cũ nhất
+    pub fn drain_events_for_frame(&mut self) -> Vec<u32> {
+        self.events.drain_oldest_frame()
+    }
+
+    // --- EVENT LOG (debug replay) ---
+    // Bật: ghi mọi event (kể cả bị mask / tràn / JS clear) vào log mới, xem EVENT_LOG_RECORD_SIZE
+    pub fn set_event_log(&mut self, enabled: bool) {
+        self.events.set_log_enabled(enabled);
+    }
+
+    pub fn get_event_log(&self) -> Vec<u8> {
+        self.events.log_bytes().to_vec()
+    }
+
+    // Điểm u32 (bão hòa ở u32::MAX), giữ cho client cũ
     pub fn get_score(&self) -> u32 {
-        self.score
+        saturate_u32(self.score)
+    }
+
+    // Điểm đầy đủ cho JS: chính xác tới 2^53
+    pub fn get_score_f64(&self) -> f64 {
+        self.score as f64
+    }
+
+    pub fn get_score_hi(&self) -> u32 {
+        (self.score >> 32) as u32
+    }
+
+    pub fn get_score_lo(&self) -> u32 {
+        self.score as u32
+    }
+
+    // True khi không còn gì đang rơi hoặc nổ
+    pub fn is_stable(&self) -> bool {
+        self.is_stable
+    }
+
+    // Hash chuẩn của toàn bộ luật đang chạy (xem RULE SNAPSHOT HASH).
+    // Đưa vào kết quả / replay header để server đối chiếu với ruleset chính thức.
+    pub fn get_rules_hash(&self) -> u64 {
+        self.rules_hash()
     }
 
     pub fn get_match_queue_ptr(&self) -> *const u8 {
@@ -179,6 +540,12 @@ impl GridState {
         }
     }
 
+    // Random cho kỹ năng boss do JS điều khiển: [0, n), stream riêng nên không làm lệch refill
+    pub fn boss_roll(&mut self, n: u32) -> u32 {
+        if n == 0 { return 0; }
+        self.rng.below(RngStream::Boss, n)
+    }
+
     pub fn get_cell_element(&self, idx: usize) -> u8 {
          if idx < self.cells.len() {
              self.cells[idx].element
@@ -203,12 +570,50 @@ impl GridState {
         if idx < self.cells.len() { self.cells[idx].flags } else { 0 }
     }
 
+    // Tile đóng gói: [Flags (8) | Element (8)]
+    // Cùng layout với đọc cell buffer bằng Uint16Array (little-endian).
+    pub fn get_cell_full(&self, idx: usize) -> u16 {
+        if idx < self.cells.len() {
+            ((self.cells[idx].flags as u16) << 8) | self.cells[idx].element as u16
+        } else {
+            0
+        }
+    }
+
+    // --- CELL QUERY ---
+    // Lọc ô trong Rust thay cho vòng lặp JS trên cả cell buffer (highlight ô FROZEN, đếm Stone...).
+    // - element_mask: bit (1 << element), 0 = mọi element (kể cả ô trống)
+    // - flag_mask: ô phải mang ít nhất 1 flag trong mask, 0 = không lọc flag
+    // - region: hình chữ nhật (x, y, w, h), tự cắt theo board; w = 0 hoặc h = 0 -> cả board
+    // Returns mỗi ô 1 u32: [Index (16) | get_cell_full (16)], thứ tự index tăng dần.
+    pub fn query_cells(&self, element_mask: u32, flag_mask: u8, x: usize, y: usize, w: usize, h: usize) -> Vec<u32> {
+        let ((x0, y0), (x1, y1)) = if w == 0 || h == 0 {
+            ((0, 0), (self.width, self.height))
+        } else {
+            ((x, y), (x.saturating_add(w).min(self.width), y.saturating_add(h).min(self.height)))
+        };
+        let mut out = Vec::new();
+        for cy in y0..y1 {
+            for cx in x0..x1 {
+                let idx = cy * self.width + cx;
+                let cell = self.cells[idx];
+                let element_ok = element_mask == 0 || (cell.element < 32 && element_mask & (1 << cell.element) != 0);
+                let flag_ok = flag_mask == 0 || cell.flags & flag_mask != 0;
+                if element_ok && flag_ok {
+                    out.push(((idx as u32) << 16) | self.get_cell_full(idx) as u32);
+                }
+            }
+        }
+        out
+    }
+
     // Spawn special tiles (Ash, Stone) on random valid cells
     // count: number to spawn
     // element: target element type
     // flags: target flags to set
     // exclude_element: avoid replacing this element (e.g. don't replace Stone with Ash)
     pub fn spawn_special(&mut self, count: usize, element: u8, flags: u8, exclude_element: u8) -> Vec<usize> {
+        self.restore_frozen_rng();
         let mut affected = Vec::new();
         let mut attempts = 0;
         let max_attempts = count * 5;
@@ -216,7 +621,7 @@ impl GridState {
 
         while spawned < count && attempts < max_attempts {
             attempts += 1;
-            let idx = self.rng.gen_range(0..self.cells.len());
+            let idx = self.rng.index(RngStream::Special, self.cells.len());
             
             if idx >= self.cells.len() { continue; }
             
@@ -237,38 +642,316 @@ impl GridState {
         affected
     }
 
+    // Color Splash: chuyển mọi ô trong bán kính (vuông) sang element mới
+    // Stone/Dark miễn nhiễm, ô trống bỏ qua. Flags giữ nguyên theo persistence contract.
+    // Returns affected cell indices.
+    pub fn convert_area(&mut self, center_idx: usize, radius: usize, element: u8) -> Vec<usize> {
+        let mut affected = Vec::new();
+        if center_idx >= self.cells.len() || element == 0 || element > 5 { return affected; }
+
+        let cx = center_idx % self.width;
+        let cy = center_idx / self.width;
+
+        for y in cy.saturating_sub(radius)..=cy.saturating_add(radius).min(self.height - 1) {
+            for x in cx.saturating_sub(radius)..=cx.saturating_add(radius).min(self.width - 1) {
+                let idx = y * self.width + x;
+                let current = self.cells[idx].element;
+                if current == 0 || current == 10 || current == 11 || current == element { continue; }
+
+                self.cells[idx].element = element;
+                // Event Type 34 = Conversion, intensity = new element
+                self.push_event_payload(EventType::Conversion as u8, x, y, element, current as u32); // payload = element cũ
+                affected.push(idx);
+            }
+        }
+
+        if !affected.is_empty() { self.is_stable = false; }
+        affected
+    }
+
+    pub fn set_resolution_policy(&mut self, policy: ResolutionPolicy) {
+        self.resolution_policy = policy;
+    }
+
+    pub fn get_resolution_policy(&self) -> ResolutionPolicy {
+        self.resolution_policy
+    }
+
+    // --- SANDBOX / PRACTICE MODE ---
+    // Chỉnh sửa tự do cho streamer & team tuning. Bật sandbox 1 lần là phiên bị đánh dấu
+    // non-submittable vĩnh viễn (kể cả khi tắt lại).
+
+    pub fn set_sandbox(&mut self, enabled: bool) {
+        self.sandbox = enabled;
+        if enabled {
+            self.tainted = true;
+        } else {
+            self.frozen_rng = None;
+        }
+    }
+
+    pub fn is_sandbox(&self) -> bool {
+        self.sandbox
+    }
+
+    pub fn is_submittable(&self) -> bool {
+        !self.tainted
+    }
+
+    // Đặt element + flags bất kỳ (ghi đè flags, không OR)
+    pub fn sandbox_place(&mut self, idx: usize, element: u8, flags: u8) -> bool {
+        if !self.sandbox || idx >= self.cells.len() { return false; }
+        self.cells[idx] = Cell { element, flags };
+        self.is_stable = false;
+        true
+    }
+
+    // Kích hoạt luật tương tác của `element` như thể có match tại center_idx
+    // Returns true nếu có luật được kích hoạt
+    pub fn sandbox_trigger(&mut self, center_idx: usize, element: u8) -> bool {
+        if !self.sandbox || center_idx >= self.cells.len() { return false; }
+
+        let m = MatchResult {
+            pattern: MatchPattern::Line3,
+            element,
+            cells: vec![center_idx],
+            center_idx,
+        };
+        let mut cells_to_clear = std::collections::BTreeSet::new();
+        let events_before = self.events.total_pushed();
+        let (interaction, bonus) = self.analyze_match_interaction(&m);
+        let mut staged = StagedInteractions::default();
+        self.apply_interaction(&m, interaction, &mut cells_to_clear, &mut staged);
+        self.flush_interaction_events(staged);
+        self.add_score(bonus as u64);
+        self.score_breakdown.interaction = self.score_breakdown.interaction.saturating_add(bonus as u64);
+        self.execute_clears(cells_to_clear, &std::collections::BTreeMap::new());
+
+        let fired = self.events.total_pushed() > events_before;
+        if fired { self.is_stable = false; }
+        fired
+    }
+
+    // RNG hiển thị cho frontend, cùng seed + key thì client / replay / spectator ra cùng chuỗi
+    pub fn get_shared_rng(&self, key: u32) -> SharedRng {
+        SharedRng::new(self.seed, key)
+    }
+
+    // Ván chạy ChaCha8 cũ (seed có cờ legacy_seed), xem sim/rng.rs
+    pub fn is_legacy_rng(&self) -> bool {
+        self.rng.is_legacy()
+    }
+
+    // Đóng băng RNG: mỗi lần tick/spawn bắt đầu lại từ cùng 1 trạng thái RNG
+    pub fn set_rng_frozen(&mut self, frozen: bool) {
+        if frozen && self.sandbox {
+            self.frozen_rng = Some(self.rng.clone());
+        } else {
+            self.frozen_rng = None;
+        }
+    }
+
+    // Trạng thái RNG riêng (header snapshot + RNG): lưu kèm save ngoài snapshot, vd checkpoint phía server.
+    // Set lại thì chuỗi refill tiếp tục y hệt chỗ đã lấy.
+    pub fn get_rng_state(&self) -> Vec<u8> {
+        let mut w = SnapshotWriter::with_version(SNAPSHOT_VERSION);
+        w.rng(&self.rng);
+        w.finish()
+    }
+
+    // Returns false nếu state hỏng / sai version, RNG giữ nguyên
+    pub fn set_rng_state(&mut self, data: &[u8]) -> bool {
+        self.import_rng_state(data).is_ok()
+    }
+
+    pub(crate) fn import_rng_state(&mut self, data: &[u8]) -> Result<(), SnapshotError> {
+        let mut r = SnapshotReader::new(data)?;
+        let rng = r.rng()?;
+        r.finish()?;
+        // RNG đang đóng băng (sandbox) thì điểm đóng băng cũng chuyển theo
+        if self.frozen_rng.is_some() {
+            self.frozen_rng = Some(rng.clone());
+        }
+        self.rng = rng;
+        Ok(())
+    }
+
+    fn restore_frozen_rng(&mut self) {
+        if let Some(snapshot) = &self.frozen_rng {
+            self.rng = snapshot.clone();
+        }
+    }
+
     // 3. LOGIC TƯƠNG TÁC (PLAYER SWAP)
+    // Swap bị từ chối / rollback phát event SwapRejected kèm lý do (xem SwapRejectReason)
     pub fn try_swap(&mut self, idx1: usize, idx2: usize) -> bool {
-        // Validation
-        if idx1 >= self.cells.len() || idx2 >= self.cells.len() { return false; }
-        if idx1 == idx2 { return false; }
+        self.plain_swap_checked(idx1, idx2).is_ok()
+    }
 
-        let c1 = self.cells[idx1];
-        let c2 = self.cells[idx2];
+    // Swap không gắn người chơi. Đang luân phiên co-op thì mọi swap phải qua swap_as (NotYourTurn)
+    fn plain_swap_checked(&mut self, idx1: usize, idx2: usize) -> Result<(), SwapRejectReason> {
+        if self.get_turn_player().is_some() {
+            return self.reject_swap(idx1, idx2, SwapRejectReason::NotYourTurn);
+        }
+        self.release_player();
+        self.swap_checked(idx1, idx2)
+    }
 
-        // Không cho phép swap vật thể bị khóa (Stone)
-        if c1.element == 10 || c2.element == 10 { return false; }
+    // --- SHARED BOARD (co-op / versus) ---
+    // Mỗi người chơi có CycleState + điểm riêng. swap_as cho track của người đó chạy thay track chính
+    // (get_cycle_* trả về track đang chạy) tới lần swap kế tiếp: match và cascade trong khoảng đó
+    // được tính cho người vừa đi. Avatar của track đang không chạy thì tạm dừng đếm lượt.
+    pub fn add_player(&mut self, player_id: u32) -> bool {
+        if self.player_index(player_id).is_some() { return false; }
+        let home = self.active_player.map_or(self.tracks.primary, |(i, _)| self.players[i].cycle);
+        let cycle = CycleState::with_rules_of(home.mode, &home);
+        self.players.push(PlayerTrack { id: player_id, cycle, score: 0, role_mask: 0 });
+        true
+    }
 
-        // Thực hiện Swap
-        self.cells.swap(idx1, idx2);
+    pub fn clear_players(&mut self) {
+        self.release_player();
+        self.players.clear();
+        self.turn_slot = 0;
+        self.turn_used = 0;
+    }
 
-        // Kiểm tra Match ngay lập tức
-        let has_match = self.check_matches_at(idx1) || self.check_matches_at(idx2);
+    pub fn try_swap_as(&mut self, player_id: u32, idx1: usize, idx2: usize) -> bool {
+        self.swap_as_checked(player_id, idx1, idx2).is_ok()
+    }
 
-        if has_match {
-            // Nếu có match, trạng thái trở nên bất ổn để tick() xử lý tiếp
-            self.is_stable = false;
-            return true;
-        } else {
-            // Nếu không match, swap lại (Rollback)
-            self.cells.swap(idx1, idx2);
-            return false;
+    // Chỉ cho cầm tile thuộc `element_mask` (bit 1 << element, 0 = không giới hạn)
+    pub fn set_player_role(&mut self, player_id: u32, element_mask: u32) -> bool {
+        let Some(i) = self.player_index(player_id) else { return false; };
+        self.players[i].role_mask = element_mask;
+        true
+    }
+
+    // Mỗi người được `swaps` swap hợp lệ rồi tới lượt người kế tiếp (0 = tắt luân phiên)
+    pub fn set_turn_window(&mut self, swaps: u32) {
+        self.turn_window = swaps;
+        self.turn_used = 0;
+    }
+
+    // Người đang tới lượt (undefined nếu không bật luân phiên)
+    pub fn get_turn_player(&self) -> Option<u32> {
+        if self.turn_window == 0 { return None; }
+        self.players.get(self.turn_slot).map(|p| p.id)
+    }
+
+    // CO-OP: 2 người chơi chung board, luân phiên `window` swap, vai trò COOP_ROLE_A / COOP_ROLE_B
+    pub fn setup_coop(&mut self, window: u32) {
+        self.clear_players();
+        self.add_player(COOP_PLAYER_A);
+        self.add_player(COOP_PLAYER_B);
+        self.set_player_role(COOP_PLAYER_A, COOP_ROLE_A);
+        self.set_player_role(COOP_PLAYER_B, COOP_ROLE_B);
+        self.set_turn_window(window.max(1));
+    }
+
+    // Người chơi đang được tính điểm (undefined nếu không có)
+    pub fn get_active_player(&self) -> Option<u32> {
+        self.active_player.map(|(i, _)| self.players[i].id)
+    }
+
+    pub fn get_player_score(&self, player_id: u32) -> u32 {
+        self.player_track(player_id).map_or(0, |t| saturate_u32(t.score))
+    }
+
+    pub fn get_player_cycle_target(&self, player_id: u32) -> u8 {
+        self.player_track(player_id).map_or(0, |t| t.cycle.target)
+    }
+
+    pub fn get_player_cycle_chain(&self, player_id: u32) -> u32 {
+        self.player_track(player_id).map_or(0, |t| t.cycle.chain_length)
+    }
+
+    pub fn get_player_cycle_multiplier(&self, player_id: u32) -> u32 {
+        self.player_track(player_id).map_or(0, |t| t.cycle.multiplier)
+    }
+
+    fn player_index(&self, player_id: u32) -> Option<usize> {
+        self.players.iter().position(|p| p.id == player_id)
+    }
+
+    // Track hiện tại của người chơi (tính cả phần đang chạy nếu là người đang đi)
+    fn player_track(&self, player_id: u32) -> Option<PlayerTrack> {
+        let i = self.player_index(player_id)?;
+        match self.active_player {
+            Some((active, start)) if active == i => Some(PlayerTrack {
+                id: player_id,
+                cycle: self.tracks.primary,
+                score: self.players[i].score.saturating_add(self.score.saturating_sub(start)),
+                role_mask: self.players[i].role_mask,
+            }),
+            _ => Some(self.players[i]),
+        }
+    }
+
+    pub(crate) fn swap_as_checked(&mut self, player_id: u32, idx1: usize, idx2: usize) -> Result<(), SwapRejectReason> {
+        let Some(i) = self.player_index(player_id) else { return Err(SwapRejectReason::NotYourTurn); };
+        if self.turn_window > 0 && self.turn_slot != i {
+            return self.reject_swap(idx1, idx2, SwapRejectReason::NotYourTurn);
+        }
+        // Vai trò xét cả 2 ô (element ngoài 0..32 không thuộc vai trò nào)
+        let role = self.players[i].role_mask;
+        let in_role = |c: &Cell| 1u32.checked_shl(c.element as u32).is_some_and(|bit| role & bit != 0);
+        if role != 0 && [idx1, idx2].iter().any(|&idx| self.cells.get(idx).is_some_and(|c| !in_role(c))) {
+            return self.reject_swap(idx1, idx2, SwapRejectReason::RoleRestricted);
+        }
+
+        self.release_player();
+        std::mem::swap(&mut self.tracks.primary, &mut self.players[i].cycle);
+        self.active_player = Some((i, self.score));
+        // Journal: người đi ngay trước record swap của họ (bỏ đi nếu swap bị từ chối)
+        let mark = self.move_journal.len();
+        self.journal_record([JOURNAL_OP_MARKER, JOURNAL_OP_MARKER, JOURNAL_OP_PLAYER, i as u8]);
+        let result = self.swap_checked(idx1, idx2);
+        if result.is_err() {
+            self.move_journal.truncate(mark);
+            self.release_player();
+        } else if self.turn_window > 0 {
+            self.turn_used += 1;
+            if self.turn_used >= self.turn_window {
+                self.turn_used = 0;
+                self.turn_slot = (self.turn_slot + 1) % self.players.len();
+            }
+        }
+        result
+    }
+
+    // Trả track đang mượn về cho người chơi, cộng phần điểm từ lúc họ swap
+    fn release_player(&mut self) {
+        if let Some((i, start)) = self.active_player.take() {
+            std::mem::swap(&mut self.tracks.primary, &mut self.players[i].cycle);
+            self.players[i].score = self.players[i].score.saturating_add(self.score.saturating_sub(start));
+        }
+    }
+
+    // ASSIST MODE (Accessibility): chạm 1 ô -> tự chọn swap kề tốt nhất
+    // Returns move [x1, y1, x2, y2] (cùng layout với validate_replay), rỗng nếu không có swap hợp lệ.
+    // Swap đi qua try_swap nên được ghi / replay như nước đi thường.
+    pub fn assist_tap(&mut self, x: usize, y: usize) -> Vec<u8> {
+        if x >= self.width || y >= self.height { return Vec::new(); }
+        let idx = y * self.width + x;
+
+        match self.find_best_swap_at(idx) {
+            Some(target) if self.try_swap(idx, target) => {
+                let (tx, ty) = (target % self.width, target / self.width);
+                vec![x as u8, y as u8, tx as u8, ty as u8]
+            }
+            _ => Vec::new(),
         }
     }
 
     // 4. LOGIC VÒNG LẶP (TICK)
     // Được gọi mỗi frame (16ms) từ JS
     pub fn tick(&mut self) {
+        self.restore_frozen_rng();
+        self.tick_count = self.tick_count.wrapping_add(1);
+        self.tick_moves.clear();
+        self.tick_cleared.clear();
+        self.report_stability(); // Bị làm bất ổn từ ngoài tick (boss, set_cell...)
         let mut movement = false;
 
         // BƯỚC 1: TRỌNG LỰC (Gravity)
@@ -288,6 +971,8 @@ impl GridState {
                         let write_idx = write_y * self.width + x;
                         self.cells[write_idx] = cell;
                         self.cells[read_idx] = Cell { element: 0, flags: 0 };
+                        self.tick_moves.push((read_idx, write_idx));
+                        self.push_fall_event(read_idx, write_idx, cell.element);
                         movement = true;
                     }
                     if write_y > 0 { write_y -= 1; }
@@ -297,12 +982,10 @@ impl GridState {
                  for y in 0..=write_y {
                      let idx = y * self.width + x;
                      if self.cells[idx].element == 0 {
-                         // RNG
-                         let seed = (self.events.len() + idx + self.rng.gen_range(0..100)) % 5;
-                         self.cells[idx] = Cell {
-                             element: (seed + 1) as u8,
-                             flags: 0 
-                         };
+                         let element = self.refill_element(idx);
+                         self.cells[idx] = Cell { element, flags: 0 };
+                         // Tile mới xuất hiện phía trên board, rơi (write_y + 1) ô vào vị trí
+                         self.push_spawn_event(x, y, element, write_y + 1);
                          movement = true;
                          self.is_stable = false; 
                      }
@@ -314,79 +997,120 @@ impl GridState {
         if !movement {
             let matches = self.find_all_matches();
             if !matches.is_empty() {
-                let mut cells_to_clear = std::collections::HashSet::new();
+                self.cascade_depth += 1;
+                self.stats.longest_cascade = self.stats.longest_cascade.max(self.cascade_depth);
+                let mut cells_to_clear = std::collections::BTreeSet::new();
+                let mut combo_sizes = std::collections::BTreeMap::new();
                 let mut bonus_score = 0;
 
-                for m in matches {
+                for m in &matches {
+                    self.stats.record_match(m.element, m.cells.len());
+                    self.gain_xp(self.xp_rewards.for_match(m.cells.len()));
                     // 1. Basic Match Clearing - Mark match cells for clearing first
                     for &idx in &m.cells {
                         cells_to_clear.insert(idx);
+                        // Ô thuộc 2 cụm (hiếm) lấy cụm lớn hơn
+                        let size = combo_sizes.entry(idx).or_insert(0usize);
+                        *size = (*size).max(m.cells.len());
                         
                         // Basic match event (low intensity)
-                        let (mx, my) = (idx % self.width, idx / self.width);
                         // Only emit basic event if no special interaction overrides it later?
                         // For now, let's rely on the special events to be the "Big Sound", 
                         // and basic match can just be popping.
-                        // self.push_event(m.element, mx as u8, my as u8, 100); 
                     }
+                }
 
-                    // 2. Advanced Interactions
-                    let interaction = self.analyze_match_interaction(&m);
-                    
-                    match interaction {
-                        InteractionType::Destruction(target_idxs) => {
-                             // Effect: Destruction
-                             // Visuals handled by events in tick, but here we just apply logic
-                             for &t_idx in &target_idxs {
-                                    cells_to_clear.insert(t_idx);
-                                    let (tx, ty) = (t_idx % self.width, t_idx / self.width);
-                                    // Different events based on element?
-                                    // For MVP, generic "Destruction" event or specific if we passed it back
-                                    self.push_event(21, tx as u8, ty as u8, 200); 
-                             }
-                             bonus_score += 300;
-                        },
-                        InteractionType::Generation(target_idxs) => {
-                             // Effect: Generation
-                             for &t_idx in &target_idxs {
-                                 // Simple logic: Convert to next element or Special?
-                                 // Implementation from before:
-                                 // Wood feeds Fire -> Fire Spread (Convert to Fire)
-                                 // Metal gens Water -> Water Spawn (Convert to Water)
-                                 // Water nourishes Wood -> Growth (Power up)
-                                 
-                                 if m.element == 2 { // Wood -> Fire
-                                     cells_to_clear.remove(&t_idx); // Don't clear!
-                                     self.cells[t_idx].element = 4; // Fire
-                                     let (tx, ty) = (t_idx % self.width, t_idx / self.width);
-                                     self.push_event(32, tx as u8, ty as u8, 200);
-                                 } else if m.element == 1 { // Metal -> Water
-                                     // Convert neighbor
-                                     self.cells[t_idx].element = 3;
-                                     let (tx, ty) = (t_idx % self.width, t_idx / self.width);
-                                     self.push_event(31, tx as u8, ty as u8, 200);
-                                 } else if m.element == 3 { // Water -> Wood
-                                      cells_to_clear.remove(&t_idx);
-                                      self.cells[t_idx].element = 2;
-                                      self.cells[t_idx].flags |= 1; // Power
-                                      let (tx, ty) = (t_idx % self.width, t_idx / self.width);
-                                      self.push_event(33, tx as u8, ty as u8, 200);
-                                 }
-                             }
-                             bonus_score += 200;
-                        },
-                        InteractionType::None => {
-                            // Normal match, already added to cells_to_clear
+                // 2. Advanced Interactions
+                let mut staged = StagedInteractions::default();
+                if self.resolution_policy == ResolutionPolicy::FoundOrder {
+                    // Luật gốc: phân tích + áp dụng lần lượt, ô match của cụm sau được đánh dấu lại
+                    // (kể cả khi Generation của cụm trước đã bỏ ra)
+                    for m in &matches {
+                        cells_to_clear.extend(m.cells.iter().copied());
+                        let (interaction, bonus) = self.analyze_match_interaction(m);
+                        self.apply_interaction(m, interaction, &mut cells_to_clear, &mut staged);
+                        bonus_score += bonus;
+                    }
+                } else {
+                    // Phân tích trên board trước khi resolve, rồi áp dụng theo ResolutionPolicy
+                    // để các cụm chồng lấn không ghi đè nhau tùy thứ tự tìm thấy.
+                    let mut analyzed: Vec<Option<(InteractionType, u32)>> =
+                        matches.iter().map(|m| Some(self.analyze_match_interaction(m))).collect();
+                    for i in self.resolution_order(&matches, &analyzed) {
+                        if let Some((interaction, bonus)) = analyzed[i].take() {
+                            self.apply_interaction(&matches[i], interaction, &mut cells_to_clear, &mut staged);
+                            bonus_score += bonus;
                         }
                     }
-                    
+                }
+                self.flush_interaction_events(staged);
+
+                let mut primary_advanced = false;
+                let mut dual_advanced = false;
+                let mut sundered = Vec::new(); // Element kết thúc các chu trình Khắc hoàn thành
+
+                for m in matches {
                     self.match_queue.push(m.element);
                     
                     // --- PROCESS CYCLE ---
-                    let (cycle_hit, mult) = self.cycle.process_match(m.element);
+                    let was_avatar = self.tracks.primary.is_avatar();
+                    let fits_primary = was_avatar || m.element == self.tracks.primary.target;
+                    let fits_dual = self.tracks.dual.is_some_and(|c| c.is_avatar() || m.element == c.target);
+
+                    // Dual mode: match chỉ thuộc track kia thì track này giữ nguyên (không đứt chuỗi)
+                    let cycle_before = self.tracks.primary;
+                    let grace_before = self.tracks.primary.grace_left;
+                    let (cycle_hit, mut mult) = if self.tracks.dual.is_some() && !fits_primary && fits_dual {
+                        (false, 1)
+                    } else {
+                        self.tracks.primary.process_match(m.element)
+                    };
+                    primary_advanced |= cycle_hit;
+                    if self.tracks.primary.grace_left < grace_before {
+                        let (cx, cy) = (m.center_idx % self.width, m.center_idx / self.width);
+                        self.push_event_payload(EventType::CycleGraceUsed as u8, cx, cy, 0, self.tracks.primary.grace_left);
+                    }
+
+                    if let Some(mut dual) = self.tracks.dual {
+                        if fits_dual || !fits_primary {
+                            let was_dual_avatar = dual.is_avatar();
+                            let dual_before = dual;
+                            let dual_grace_before = dual.grace_left;
+                            let (dual_hit, dual_mult) = dual.process_match(m.element);
+                            if dual.grace_left < dual_grace_before {
+                                let (cx, cy) = (m.center_idx % self.width, m.center_idx / self.width);
+                                self.push_event_payload(EventType::CycleGraceUsed as u8, cx, cy, 1, dual.grace_left);
+                            }
+                            if dual_hit {
+                                dual_advanced = true;
+                                mult = mult.max(dual_mult);
+                                let (cx, cy) = (m.center_idx % self.width, m.center_idx / self.width);
+                                self.push_event_payload(dual.mode.progress_event(), cx, cy, dual.chain_length as u8, dual.multiplier);
+                                if !was_dual_avatar && dual.is_avatar() {
+                                    self.push_event_payload(dual.mode.complete_event(), cx, cy, 255, dual.multiplier);
+                                }
+                                if dual.completed_without_avatar(dual_hit) {
+                                    self.push_event_payload(dual.mode.complete_event(), cx, cy, 255, dual.multiplier);
+                                    sundered.push(m.element);
+                                }
+                                if was_dual_avatar && !dual.is_avatar() {
+                                    self.push_event_payload(EventType::AvatarExit as u8, 0, 0, 1, dual.multiplier);
+                                }
+                            }
+                            let at = (m.center_idx % self.width, m.center_idx / self.width);
+                            self.push_cycle_milestones(1, &dual_before, &dual, dual_hit, m.pattern.id(), at);
+                        }
+                        self.tracks.dual = Some(dual);
+                    }
                     
-                    // Base score = 100 * Multiplier
-                    self.score += 100 * mult;
+                    // Base score = 100 × scoring pipeline (xem sim/scoring.rs)
+                    if self.cascade_depth == 1 {
+                        self.decay.record_element(m.element);
+                    }
+                    let decay_pct = self.decay.decay_pct(&self.score_decay);
+                    let multipliers = ActiveMultipliers::compute(mult, was_avatar, self.cascade_depth, self.score_modifier_pct, decay_pct);
+                    let points = self.score_breakdown.add_match(&multipliers, 100);
+                    self.add_score(points);
                     
                     // If Cycle Hit (Visual Feedback)
                     // We can emit a special event for cycle hit?
@@ -395,49 +1119,100 @@ impl GridState {
                         // Element 255 = System Event? Or just reuse element type but with high intensity?
                         // Let's use Type 50 for "Cycle Progress"
                         let (cx, cy) = (m.center_idx % self.width, m.center_idx / self.width);
-                        self.push_event(50, cx as u8, cy as u8, self.cycle.chain_length as u8);
+                        self.push_event_payload(self.tracks.primary.mode.progress_event(), cx, cy, self.tracks.primary.chain_length as u8, self.tracks.primary.multiplier);
                         
-                        if self.cycle.chain_length == 5 {
+                        if !was_avatar && self.tracks.primary.is_avatar() {
                             // AVATAR STATE / FULL CYCLE
-                            // Huge bonus or screen clear?
-                            // For now, just a massive event
-                            self.push_event(55, cx as u8, cy as u8, 255);
+                            // Ultimate: quét sạch toàn bộ element vừa hoàn thành chu trình
+                            self.push_event_payload(self.tracks.primary.mode.complete_event(), cx, cy, 255, self.tracks.primary.multiplier);
+                            let cleared = self.avatar_ultimate(m.element, &mut cells_to_clear);
+                            let points = multipliers.apply(AVATAR_ULTIMATE_SCORE * cleared);
+                            self.add_score(points);
+                            self.score_breakdown.avatar = self.score_breakdown.avatar.saturating_add(points);
+                        }
+                        if self.tracks.primary.completed_without_avatar(cycle_hit) {
+                            self.push_event_payload(self.tracks.primary.mode.complete_event(), cx, cy, 255, self.tracks.primary.multiplier);
+                            sundered.push(m.element);
                         }
                     }
+
+                    if was_avatar && !self.tracks.primary.is_avatar() {
+                        // Event Type 56 = Avatar Exit (hết lượt match)
+                        self.push_event_payload(EventType::AvatarExit as u8, 0, 0, 0, self.tracks.primary.multiplier);
+                    }
+                    let longest = self.tracks.primary.chain_length.max(self.tracks.dual.map_or(0, |c| c.chain_length));
+                    self.stats.longest_chain = self.stats.longest_chain.max(longest);
+                    let at = (m.center_idx % self.width, m.center_idx / self.width);
+                    let cycle_after = self.tracks.primary;
+                    self.push_cycle_milestones(0, &cycle_before, &cycle_after, cycle_hit, m.pattern.id(), at);
                 }
                 
-                self.score += bonus_score;
+                self.add_score(bonus_score as u64);
+                self.score_breakdown.interaction = self.score_breakdown.interaction.saturating_add(bonus_score as u64);
+
+                // KHẮC SUNDER: phá vật cản + element bị khắc, điểm theo multiplier hiện tại
+                for element in sundered {
+                    let cleared = self.khac_sunder(element, &mut cells_to_clear);
+                    let mult = self.tracks.primary.multiplier.max(self.tracks.dual.map_or(1, |c| c.multiplier));
+                    let decay_pct = self.decay.decay_pct(&self.score_decay);
+                    let multipliers = ActiveMultipliers::compute(mult, false, self.cascade_depth, self.score_modifier_pct, decay_pct);
+                    let points = multipliers.apply(SUNDER_SCORE * cleared);
+                    self.add_score(points);
+                    self.score_breakdown.cycle = self.score_breakdown.cycle.saturating_add(points);
+                }
 
-                // Execute Clears
-                for idx in cells_to_clear {
-                    if self.cells[idx].element != 0 { // Check if already cleared
-                        let (x, y) = (idx % self.width, idx / self.width);
-                        // Emit default clear event if no special interaction covered it?
-                        // We can just emit a "Pop" event (Type 0 or special 99?)
-                        // Client can handle "Element 0 event" as "Pop".
-                        // Use Type=0 for "Clear Poof"
-                        self.push_event(self.cells[idx].element, x as u8, y as u8, 50); 
-                        self.cells[idx] = Cell { element: 0, flags: 0 };
-                    }
+                // RESONANCE: cả 2 chu trình cùng tiến trong 1 lần resolve
+                if primary_advanced && dual_advanced {
+                    self.tracks.resonance_count += 1;
+                    let combined = self.tracks.primary.multiplier + self.tracks.dual.map_or(0, |c| c.multiplier);
+                    let points = (RESONANCE_BONUS as u64).saturating_mul(combined as u64);
+                    self.add_score(points);
+                    self.score_breakdown.cycle = self.score_breakdown.cycle.saturating_add(points);
+                    // Event Type 58 = Resonance, intensity = tổng multiplier
+                    self.push_event_payload(EventType::Resonance as u8, 0, 0, combined.min(255) as u8, combined);
                 }
 
+                // Execute Clears
+                self.execute_clears(cells_to_clear, &combo_sizes);
+
                 self.is_stable = false;
             } else {
                 self.is_stable = true;
+                self.cascade_depth = 0;
             }
         } else {
             self.is_stable = false;
         }
+        self.report_stability();
+        self.record_hash_trail();
     }
 
     // --- INTERNAL HELPERS ---
 
     // --- INTERNAL HELPERS ---
 
-    fn randomize(&mut self) {
+    // RNG: chỉ phụ thuộc seed + vị trí (+ drought khi bật pity), KHÔNG phụ thuộc buffer event
+    // (JS clear / mask / capacity khác nhau giữa client và validate_replay)
+    fn refill_element(&mut self, idx: usize) -> u8 {
+        let element = match self.drought.pity_weights(&self.drought_pity) {
+            Some(weights) => {
+                self.drought.pity_spawns += 1;
+                pick_weighted(&weights, self.rng.below(RngStream::Refill, weights.iter().sum::<u32>()))
+            }
+            None => ((idx + self.rng.index(RngStream::Refill, 100)) % SPAWN_ELEMENT_COUNT as usize + 1) as u8,
+        };
+        self.drought.record_spawn(element);
+        element
+    }
+
+    fn randomize(&mut self, noise: BoardNoise) {
+        let noise_seed = if noise.is_off() { 0 } else { self.rng.next_u64(RngStream::Board) };
         for i in 0..self.cells.len() {
-            // rng.gen_range takes Range<usize>. 1..=5 is inclusive, so 1..6
-            let val = self.rng.gen_range(1..6) as u8;
+            let mut val = self.rng.element(RngStream::Board, SPAWN_ELEMENT_COUNT);
+            if !noise.is_off() && self.rng.below(RngStream::Board, 100) < noise.cluster_pct {
+                let (x, y) = ((i % self.width) as u32, (i / self.width) as u32);
+                val = noise_element(noise_seed, x, y, noise.scale, SPAWN_ELEMENT_COUNT);
+            }
             self.cells[i] = Cell { element: val, flags: 0 };
         }
         // Remove matches
@@ -445,10 +1220,13 @@ impl GridState {
             let matches = self.find_all_matches();
             if matches.is_empty() { break; }
             for m in matches {
-                for idx in m.cells {
-                   // Deterministic shift
-                   self.cells[idx].element = (self.cells[idx].element % 5) + 1;
-                }
+                // Deterministic shift, chỉ đổi 1 ô gần tâm nhất.
+                // Đổi cả match thì hàng vẫn cùng màu -> lặp vô hạn.
+                let (cx, cy) = (m.center_idx % self.width, m.center_idx / self.width);
+                let idx = *m.cells.iter()
+                    .min_by_key(|&&i| (i % self.width).abs_diff(cx) + (i / self.width).abs_diff(cy))
+                    .unwrap_or(&m.center_idx);
+                self.cells[idx].element = (self.cells[idx].element % 5) + 1;
             }
         }
     }
@@ -495,7 +1273,6 @@ impl GridState {
 
     // Tìm tất cả các cụm match (Connected Components)
     pub(crate) fn find_all_matches(&self) -> Vec<MatchResult> {
-        let mut checked = vec![false; self.width * self.height];
         let mut results = Vec::new();
 
         // 1. Quét tìm tất cả các cặp match cơ bản (Horizontal & Vertical)
@@ -556,8 +1333,8 @@ impl GridState {
         }
 
         // Convert matches to a Map of Cell -> ClusterID
-        let mut parent: Vec<usize> = (0..self.cells.len()).collect();
-        let mut active_nodes = std::collections::HashSet::new();
+        // BTreeSet: thứ tự duyệt cụm (= thứ tự xử lý cycle / điểm) phải tất định trên mọi nền tảng
+        let mut active_nodes = std::collections::BTreeSet::new();
 
         // Helper find root
         // Note: Rust ownership makes recursive closure tricky, using iterative
@@ -656,68 +1433,589 @@ impl GridState {
         results
     }
 
-    // --- BIT PACKING MAGIC ---
-    // Đóng gói data sự kiện vào 1 số u32 duy nhất
-    fn push_event(&mut self, type_id: u8, x: u8, y: u8, intensity: u8) {
-        // Layout: [Type (8) | X (8) | Y (8) | Intensity (8)]
-        // Ví dụ: Fire (4) tại (3,5) cường độ Max (255)
-        // 0x040305FF
-        let data: u32 = ((type_id as u32) << 24) 
-                      | ((x as u32) << 16) 
-                      | ((y as u32) << 8) 
-                      | (intensity as u32);
-        self.events.push(data);
+    // --- EVENT EMISSION ---
+    // Ghi vào cả v1 (u32 nén, tọa độ cắt còn 8 bit) và v2 (SoA, tọa độ 16 bit)
+    fn push_event_payload(&mut self, type_id: u8, x: usize, y: usize, intensity: u8, payload: u32) {
+        self.audio_cues.record(type_id, intensity, self.tick_count);
+        self.events.push(GridEvent {
+            event_type: type_id,
+            x: x as u16,
+            y: y as u16,
+            intensity,
+            payload,
+            frame: self.tick_count,
+            delay_ms: 0,
+            duration_ms: 0,
+        });
+    }
+
+    // CycleMilestone cho 1 track, suy từ trạng thái trước / sau 1 lần process_match hoặc turn_avatar
+    fn push_cycle_milestones(&mut self, track: u8, before: &CycleState, after: &CycleState, hit: bool, pattern: u8, (x, y): (usize, usize)) {
+        let milestones = [
+            (hit && !before.is_avatar(), CycleMilestoneKind::Advance),
+            (!hit && before.chain_length > 0 && after.chain_length == 0 && !before.is_avatar(), CycleMilestoneKind::Break),
+            (!before.is_avatar() && after.is_avatar(), CycleMilestoneKind::AvatarEnter),
+            (before.is_avatar() && !after.is_avatar(), CycleMilestoneKind::AvatarExit),
+        ];
+        for (_, kind) in milestones.into_iter().filter(|&(fired, _)| fired) {
+            let payload = cycle_milestone_payload(kind, pattern, before.target, after.target, after.multiplier);
+            self.push_event_payload(EventType::CycleMilestone as u8, x, y, track, payload);
+        }
+    }
+
+    // Event Fall: renderer tween thẳng từ ô nguồn tới ô đích, không cần diff cell buffer
+    fn push_fall_event(&mut self, from_idx: usize, to_idx: usize, element: u8) {
+        let (delay_ms, duration_ms) = fall_timing((to_idx - from_idx) / self.width);
+        self.events.push(GridEvent {
+            event_type: EventType::Fall as u8,
+            x: (to_idx % self.width) as u16,
+            y: (to_idx / self.width) as u16,
+            intensity: element,
+            payload: from_idx as u32,
+            frame: self.tick_count,
+            delay_ms,
+            duration_ms,
+        });
+    }
+
+    fn push_spawn_event(&mut self, x: usize, y: usize, element: u8, drop_cells: usize) {
+        let (delay_ms, duration_ms) = fall_timing(drop_cells);
+        self.events.push(GridEvent {
+            event_type: EventType::Spawn as u8,
+            x: x as u16,
+            y: y as u16,
+            intensity: element,
+            payload: drop_cells as u32,
+            frame: self.tick_count,
+            delay_ms,
+            duration_ms,
+        });
+    }
+
+    fn record_hash_trail(&mut self) {
+        if self.hash_trail_every == 0 || !self.tick_count.is_multiple_of(self.hash_trail_every) { return; }
+        if self.hash_trail.is_empty() { self.hash_trail_first_tick = self.tick_count; }
+        let hash = self.get_state_hash();
+        self.hash_trail.push(hash);
+    }
+
+    fn gain_xp(&mut self, amount: u64) {
+        if amount == 0 { return; }
+        let before = self.xp_curve.level_for_xp(self.xp);
+        self.xp = self.xp.saturating_add(amount);
+        let after = self.xp_curve.level_for_xp(self.xp);
+        if after > before {
+            self.push_event_payload(EventType::LevelUp as u8, 0, 0, (after - before).min(255) as u8, after);
+        }
+    }
+
+    fn add_score(&mut self, points: u64) {
+        self.score = self.score.saturating_add(points);
+    }
+
+    // Event Destruction / Generation kèm timing theo khoảng cách tới tâm match.
+    // Phát event interaction đã gom của 1 lần resolve (mỗi ô tối đa 1 event)
+    fn flush_interaction_events(&mut self, staged: StagedInteractions) {
+        for (t_idx, type_id, center_idx) in staged.events {
+            let (tx, ty) = (t_idx % self.width, t_idx / self.width);
+            let (cx, cy) = (center_idx % self.width, center_idx / self.width);
+            let (delay_ms, duration_ms) = interaction_timing(tx.abs_diff(cx), ty.abs_diff(cy));
+            self.audio_cues.record(type_id, 200, self.tick_count);
+            self.events.push(GridEvent {
+                event_type: type_id,
+                x: tx as u16,
+                y: ty as u16,
+                intensity: 200,
+                payload: 0,
+                frame: self.tick_count,
+                delay_ms,
+                duration_ms,
+            });
+        }
     }
     // --- ANTI-CHEAT: DETERMINISTIC REPLAY ---
     
     // Static validation method
-    // moves: [x1, y1, x2, y2, ...]
+    // moves: [x1, y1, x2, y2, ...]. Qua check_replay_moves (ReplayLimits::DEFAULT) trước, bị loại -> 0.
     pub fn validate_replay(width: usize, height: usize, seed: u64, moves: &[u8]) -> u32 {
         let mut grid = GridState::new(width, height, seed);
-        
-        let mut i = 0;
-        while i < moves.len() {
-             if i + 4 > moves.len() { break; }
-             let x1 = moves[i] as usize;
-             let y1 = moves[i+1] as usize;
-             let x2 = moves[i+2] as usize;
-             let y2 = moves[i+3] as usize;
-             i += 4;
-             
-             let idx1 = y1 * width + x1;
-             let idx2 = y2 * width + x2;
-             
-             // Try Swap
-             if grid.try_swap(idx1, idx2) {
-                 // If swap success (match made), Run simulation until stable
-                 let mut ticks = 0;
-                 let max_ticks = 1000; // prevents infinite loop
-                 while !grid.is_stable && ticks < max_ticks {
-                     grid.tick();
-                     ticks += 1;
-                 }
-             }
-         }
-        
-        grid.score
+        replay_checked(&mut grid, moves, &[], &ReplayLimits::DEFAULT).map_or(0, |_| grid.get_score())
     }
 
-    // --- CYCLE SYSTEM API ---
-
-    pub fn get_cycle_target(&self) -> u8 {
-        self.cycle.target
+    // Như validate_replay với moves nén (get_move_journal_compact). Dữ liệu hỏng -> 0.
+    pub fn validate_replay_compact(width: usize, height: usize, seed: u64, data: &[u8]) -> u32 {
+        decode_moves(width, data).map_or(0, |moves| GridState::validate_replay(width, height, seed, &moves))
     }
 
-    pub fn get_cycle_chain(&self) -> u32 {
-        self.cycle.chain_length
+    // Từng nước: [checksum sau khi ổn định, điểm cộng thêm (bão hòa u32), 1 = chấp nhận / 0 = từ chối], nối liền.
+    // Server so với checksum client gửi để biết chính xác nước nào lệch.
+    pub fn validate_replay_detailed(width: usize, height: usize, seed: u64, moves: &[u8]) -> Vec<u32> {
+        replay_steps(&mut GridState::new(width, height, seed), moves)
+            .iter()
+            .flat_map(|s| [s.checksum, saturate_u32(s.score_delta), s.accepted as u32])
+            .collect()
     }
 
-    pub fn get_cycle_multiplier(&self) -> u32 {
-        self.cycle.multiplier
+    // Như validate_replay nhưng trả điểm u64 đầy đủ (validate_replay bão hòa ở u32::MAX)
+    pub fn validate_replay_f64(width: usize, height: usize, seed: u64, moves: &[u8]) -> f64 {
+        let mut grid = GridState::new(width, height, seed);
+        GridState::replay_moves(&mut grid, moves);
+        grid.score as f64
+    }
+
+    // Event log của lần replay phía server, để diff với log client gửi lên
+    pub fn replay_event_log(width: usize, height: usize, seed: u64, moves: &[u8]) -> Vec<u8> {
+        let mut grid = GridState::new(width, height, seed);
+        grid.set_event_log(true);
+        GridState::replay_moves(&mut grid, moves);
+        grid.get_event_log()
+    }
+
+    // --- HASH TRAIL (LOCKSTEP) ---
+    // Bật: cuối mỗi tick chia hết cho `every` ghi get_state_hash vào trail (u64, đọc bằng BigUint64Array).
+    // 2 client lockstep trao đổi trail định kỳ -> phát hiện desync ngay tick đầu tiên lệch.
+    // every = 0 -> tắt. Đổi cấu hình thì xoá trail cũ.
+    pub fn set_hash_trail(&mut self, every: u32) {
+        self.hash_trail_every = every;
+        self.clear_hash_trail();
+    }
+
+    pub fn get_hash_trail_ptr(&self) -> *const u64 {
+        self.hash_trail.as_ptr()
+    }
+
+    pub fn get_hash_trail_len(&self) -> usize {
+        self.hash_trail.len()
+    }
+
+    // Tick của phần tử đầu trail, phần tử i ứng với tick first + i * every
+    pub fn get_hash_trail_first_tick(&self) -> u32 {
+        self.hash_trail_first_tick
+    }
+
+    // Xoá phần đã gửi cho peer
+    pub fn clear_hash_trail(&mut self) {
+        self.hash_trail.clear();
+    }
+
+    pub(crate) fn hash_trail(&self) -> &[u64] {
+        &self.hash_trail
+    }
+
+    // Index đầu tiên khác nhau trong phần chung của 2 trail (cùng first tick), -1 = khớp
+    pub fn diff_hash_trails(a: &[u64], b: &[u64]) -> i32 {
+        a.iter().zip(b).position(|(x, y)| x != y).map_or(-1, |i| i as i32)
+    }
+
+    // --- DIVERGENCE DIAGNOSTICS ---
+    // { cells: [{ index, x, y, element, flags }], cell_count, fields: [{ field, left, right }] },
+    // left = state này. Rỗng = giống nhau (trừ tick count / buffer event).
+    #[cfg(feature = "wasm")]
+    pub fn diff_states(&self, other: &GridState) -> JsValue {
+        serde_wasm_bindgen::to_value(&diff_states(self, other)).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    // Replay rồi so với snapshot client gửi kèm: { score, checksum_match, diff } (diff: left = server),
+    // undefined nếu snapshot hỏng
+    #[cfg(feature = "wasm")]
+    pub fn validate_replay_diagnosed(width: usize, height: usize, seed: u64, moves: &[u8], client_snapshot: &[u8]) -> JsValue {
+        match diagnose_replay(width, height, seed, moves, client_snapshot) {
+            Ok(d) => serde_wasm_bindgen::to_value(&d).unwrap_or(JsValue::UNDEFINED),
+            Err(_) => JsValue::UNDEFINED,
+        }
+    }
+
+    // Index record đầu tiên khác nhau giữa 2 event log, -1 = giống hệt
+    pub fn diff_event_logs(a: &[u8], b: &[u8]) -> i32 {
+        first_log_divergence(a, b).map_or(-1, |i| i as i32)
+    }
+
+    // Ruleset chính thức mà validate_replay chạy (config mặc định).
+    // Server so với rules hash client gửi kèm kết quả.
+    pub fn official_rules_hash(width: usize, height: usize) -> u64 {
+        GridState::new_empty(width, height, 0).rules_hash()
+    }
+
+    // --- CYCLE SYSTEM API ---
+
+    pub fn get_cycle_target(&self) -> u8 {
+        self.tracks.primary.target
+    }
+
+    // N target sắp tới, bắt đầu từ target hiện tại (tối đa MAX_TARGET_FORECAST)
+    pub fn get_cycle_targets(&self, count: usize) -> Vec<u8> {
+        self.tracks.primary.upcoming_targets(count)
+    }
+
+    pub fn get_cycle_chain(&self) -> u32 {
+        self.tracks.primary.chain_length
+    }
+
+    pub fn get_cycle_multiplier(&self) -> u32 {
+        self.tracks.primary.multiplier
     }
 
     pub fn is_avatar_state(&self) -> bool {
-        self.cycle.is_avatar()
+        self.tracks.primary.is_avatar()
+    }
+
+    // Chọn chu trình theo level (reset chuỗi hiện tại)
+    pub fn set_cycle_mode(&mut self, mode: CycleMode) {
+        self.tracks.primary = CycleState::with_rules_of(mode, &self.tracks.primary);
+    }
+
+    // Số lần match lệch target được tha mỗi chuỗi (0 = đứt ngay như cũ), áp cho cả dual track
+    pub fn set_cycle_grace(&mut self, misses: u32) {
+        for cycle in self.tracks.iter_mut() {
+            cycle.set_grace(misses);
+        }
+        for player in self.players.iter_mut() {
+            player.cycle.set_grace(misses);
+        }
+    }
+
+    // Momentum decay: `turns` lượt liền không có match tiến chuỗi -> multiplier giảm 1 bậc (0 = tắt).
+    // Áp cho mọi track; đếm theo lượt (swap hợp lệ) nên replay ra cùng kết quả dù client rảnh bao lâu.
+    pub fn set_momentum_decay(&mut self, turns: u32) {
+        for cycle in self.tracks.iter_mut().chain(self.players.iter_mut().map(|p| &mut p.cycle)) {
+            cycle.momentum_decay_turns = turns;
+            cycle.idle_turns = 0;
+        }
+    }
+
+    pub fn get_momentum_decay(&self) -> u32 {
+        self.tracks.primary.momentum_decay_turns
+    }
+
+    // --- CYCLE SKIP BOOSTER ---
+    pub fn set_cycle_skip_charges(&mut self, charges: u32) {
+        self.cycle_skip_charges = charges;
+    }
+
+    pub fn get_cycle_skip_charges(&self) -> u32 {
+        self.cycle_skip_charges
+    }
+
+    // Tiêu 1 charge để target chính nhảy sang target kế tiếp mà không cần match.
+    // Chỉ khi board ổn định (giữa 2 nước) và ngoài Avatar. Ghi vào move journal.
+    pub fn skip_cycle_target(&mut self) -> bool {
+        if self.cycle_skip_charges == 0 || !self.is_stable { return false; }
+        let before = self.tracks.primary;
+        if !self.tracks.primary.skip_target() { return false; }
+        self.cycle_skip_charges -= 1;
+        self.journal_record([JOURNAL_OP_MARKER, JOURNAL_OP_MARKER, JOURNAL_OP_SKIP_CYCLE, 0]);
+        let payload = cycle_milestone_payload(CycleMilestoneKind::Skip, 0, before.target, self.tracks.primary.target, self.tracks.primary.multiplier);
+        self.push_event_payload(EventType::CycleMilestone as u8, 0, 0, 0, payload);
+        true
+    }
+
+    // --- SUSPEND / RESUME ---
+    // Toàn bộ trạng thái gameplay (cells, điểm, chu trình, RNG, config luật...) dạng byte có header version,
+    // xem sim/snapshot.rs. Không gồm buffer event / audio.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_snapshot()
+    }
+
+    // undefined nếu bytes hỏng hoặc version không hỗ trợ
+    pub fn from_bytes(data: &[u8]) -> Option<GridState> {
+        GridState::from_snapshot(data).ok()
+    }
+
+    // Version schema của 1 save (undefined nếu không phải snapshot)
+    pub fn get_snapshot_version(data: &[u8]) -> Option<u16> {
+        snapshot_version(data).ok()
+    }
+
+    // Nâng save ghi ở `from_version` lên schema hiện tại. Rỗng nếu hỏng / sai version / version quá cũ.
+    pub fn migrate(data: &[u8], from_version: u16) -> Vec<u8> {
+        GridState::migrate_snapshot(data, from_version).unwrap_or_default()
+    }
+
+    // --- MOVE RECORDING ---
+    // Mỗi swap được chấp nhận = [x1, y1, x2, y2], booster = [FF, FF, op, 0].
+    // Bật mặc định; tắt thì journal giữ nguyên phần đã ghi.
+    pub fn record_moves(&mut self, enabled: bool) {
+        self.recording_moves = enabled;
+    }
+
+    pub fn is_recording_moves(&self) -> bool {
+        self.recording_moves
+    }
+
+    // Input đã chấp nhận từ đầu ván (phần moves của get_replay_file)
+    pub fn get_move_journal(&self) -> Vec<u8> {
+        self.move_journal.clone()
+    }
+
+    // Journal nén (xem sim/moves.rs), ~1-2 byte / nước thay vì 4. Đọc lại bằng validate_replay_compact.
+    pub fn get_move_journal_compact(&self) -> Vec<u8> {
+        encode_moves(self.width, &self.move_journal)
+    }
+
+    pub fn clear_move_journal(&mut self) {
+        self.move_journal.clear();
+    }
+
+    // Replay file (header + journal, xem sim/replay.rs) để gửi lên validation service.
+    // Journal phải được ghi từ đầu ván.
+    pub fn get_replay_file(&self) -> Vec<u8> {
+        self.replay_header().encode(&self.move_journal)
+    }
+
+    // Validator cho mọi cấu hình ván (endless, pity, noise, co-op, bucket...): luật lấy từ header.
+    // { score, summary } hoặc { error } (BadHeader, CoreVersionMismatch, RulesMismatch, Rejected...)
+    #[cfg(feature = "wasm")]
+    pub fn validate_replay_file(data: &[u8]) -> JsValue {
+        ReplayVerdict::to_js(verify_replay_summary(data))
+    }
+
+    // validate_replay kèm kiểm tra rẻ trước khi simulate (số nước, toạ độ, swap kề nhau, nhịp input).
+    // move_ticks rỗng = không kiểm tra nhịp. { score, summary } hoặc { error: { Rejected: { TooFast: { index } } } }...
+    #[cfg(feature = "wasm")]
+    pub fn validate_replay_checked(width: usize, height: usize, seed: u64, moves: &[u8], max_moves: u32, min_tick_gap: u32, move_ticks: &[u32]) -> JsValue {
+        let limits = ReplayLimits { max_moves, require_adjacent: true, min_tick_gap };
+        let mut grid = GridState::new(width, height, seed);
+        ReplayVerdict::to_js(replay_checked(&mut grid, moves, move_ticks, &limits).map(|_| grid.game_summary()))
+    }
+
+    fn journal_record(&mut self, record: [u8; 4]) {
+        if self.recording_moves {
+            self.move_journal.extend_from_slice(&record);
+        }
+    }
+
+    pub fn get_cycle_grace(&self) -> u32 {
+        self.tracks.primary.grace_per_chain
+    }
+
+    pub fn get_cycle_grace_left(&self) -> u32 {
+        self.tracks.primary.grace_left
+    }
+
+    pub fn get_dual_cycle_grace_left(&self) -> u32 {
+        self.tracks.dual.map_or(0, |c| c.grace_left)
+    }
+
+    pub fn get_cycle_mode(&self) -> CycleMode {
+        self.tracks.primary.mode
+    }
+
+    // --- SCORING PIPELINE API ---
+    // Trả về { cycle, avatar, cascade, modifier_pct, decay_pct, total_pct } đang áp dụng
+    #[cfg(feature = "wasm")]
+    pub fn get_active_multipliers(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.active_multipliers()).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    // Thống kê phiên: { matches_by_element, largest_cluster, longest_cascade, longest_chain,
+    //   destructions, generations, swaps_attempted, swaps_rejected }
+    #[cfg(feature = "wasm")]
+    pub fn get_session_stats(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.stats).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    pub fn reset_session_stats(&mut self) {
+        self.stats = SessionStats::default();
+    }
+
+    pub(crate) fn session_stats(&self) -> SessionStats {
+        self.stats
+    }
+
+    // --- XP / LEVELING ---
+    // Curve: XP cho level 2 = base, mỗi level sau x growth_pct%. Level hiện tại tính lại theo curve mới.
+    pub fn set_xp_curve(&mut self, base: u32, growth_pct: u32, max_level: u32) {
+        self.xp_curve = XpCurve { base: base.max(1), growth_pct, max_level: max_level.clamp(1, MAX_XP_LEVEL) };
+    }
+
+    pub fn set_xp_rewards(&mut self, per_match: u32, per_extra_cell: u32, per_interaction: u32) {
+        self.xp_rewards = XpRewards { per_match, per_extra_cell, per_interaction };
+    }
+
+    // Chính xác tới 2^53
+    pub fn get_xp(&self) -> f64 {
+        self.xp as f64
+    }
+
+    pub fn get_xp_level(&self) -> u32 {
+        self.xp_curve.level_for_xp(self.xp)
+    }
+
+    // { xp, level, level_start, next_level } cho thanh tiến trình
+    #[cfg(feature = "wasm")]
+    pub fn get_xp_progress(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.xp_progress()).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    // XP mang sang từ phiên trước (không phát LevelUp)
+    pub fn set_xp(&mut self, xp: f64) {
+        self.xp = xp.max(0.0) as u64;
+    }
+
+    pub(crate) fn xp_progress(&self) -> XpProgress {
+        XpProgress::new(&self.xp_curve, self.xp)
+    }
+
+    // Kết quả ván cho màn kết quả / gửi server: { seed, rules_hash, score, checksum, turns, move_count,
+    //   bucket, player_scores, breakdown, stats, xp }. Số 64 bit (seed, rules_hash, điểm) ra BigInt.
+    #[cfg(feature = "wasm")]
+    pub fn get_game_summary(&self) -> JsValue {
+        let serializer = serde_wasm_bindgen::Serializer::new().serialize_large_number_types_as_bigints(true);
+        self.game_summary().serialize(&serializer).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    // Cùng seed / rules hash / checksum / điểm với header của replay file
+    pub(crate) fn game_summary(&self) -> GameSummary {
+        let header = self.replay_header();
+        GameSummary {
+            seed: header.seed,
+            rules_hash: header.rules_hash,
+            score: header.final_score,
+            checksum: header.final_checksum,
+            turns: self.turn_count,
+            move_count: header.move_count,
+            bucket: header.bucket,
+            player_scores: self.players.iter().filter_map(|p| self.player_track(p.id)).map(|t| t.score).collect(),
+            breakdown: self.score_breakdown,
+            stats: self.stats,
+            xp: self.xp_progress(),
+        }
+    }
+
+    // Điểm theo nguồn cho màn kết quả: { base, interaction, cascade, cycle, avatar }
+    #[cfg(feature = "wasm")]
+    pub fn get_score_breakdown(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.score_breakdown).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    pub(crate) fn score_breakdown(&self) -> ScoreBreakdown {
+        self.score_breakdown
+    }
+
+    pub(crate) fn active_multipliers(&self) -> ActiveMultipliers {
+        let cycle = self.tracks.primary.multiplier.max(self.tracks.dual.map_or(1, |c| c.multiplier));
+        let decay_pct = self.decay.decay_pct(&self.score_decay);
+        ActiveMultipliers::compute(cycle, self.tracks.primary.is_avatar(), self.cascade_depth, self.score_modifier_pct, decay_pct)
+    }
+
+    // Booster / level modifier theo phần trăm (100 = x1)
+    pub fn set_score_modifier(&mut self, modifier_pct: u32) {
+        self.score_modifier_pct = modifier_pct;
+    }
+
+    // Anti-farming cho endless mode (xem ScoreDecay). Step = % bị trừ mỗi lần lặp, floor = % tối thiểu.
+    // set_score_decay(0, 0, 100) = tắt. Reset streak hiện tại.
+    pub fn set_score_decay(&mut self, element_step_pct: u32, region_step_pct: u32, floor_pct: u32) {
+        self.score_decay = ScoreDecay { element_step_pct, region_step_pct, floor_pct };
+        self.decay = DecayTracker::default();
+    }
+
+    // --- DROUGHT PITY ---
+    // Sau `threshold` tile refill liên tiếp không có element E, trọng số của E tăng step_pct mỗi refill,
+    // tối đa max_boost_pct (trên nền 100, trần MAX_BOOST_PCT). threshold = 0 -> tắt. Xem sim/drought.rs
+    pub fn set_drought_pity(&mut self, threshold: u32, step_pct: u32, max_boost_pct: u32) {
+        self.drought_pity = DroughtPity { threshold, step_pct, max_boost_pct };
+    }
+
+    // Returns [drought hiện tại e1..e5, drought dài nhất e1..e5, số tile rút theo pity]
+    pub fn get_drought_stats(&self) -> Vec<u32> {
+        let mut stats = Vec::with_capacity(11);
+        stats.extend_from_slice(&self.drought.current);
+        stats.extend_from_slice(&self.drought.longest);
+        stats.push(self.drought.pity_spawns);
+        stats
+    }
+
+    // --- A/B EXPERIMENT ---
+    // Áp variant của bucket (xem sim/experiment.rs). Reset chuỗi chu trình hiện tại.
+    pub fn set_experiment_bucket(&mut self, bucket: u32) {
+        let variant = variant_for_bucket(bucket);
+        self.experiment_bucket = Some(bucket);
+        self.set_cycle_mode(variant.cycle_mode);
+        self.resolution_policy = variant.resolution_policy;
+        self.score_modifier_pct = variant.score_modifier_pct;
+    }
+
+    pub fn get_experiment_bucket(&self) -> Option<u32> {
+        self.experiment_bucket
+    }
+
+    pub fn get_experiment_variant(&self) -> String {
+        self.experiment_bucket.map_or("control", |b| variant_for_bucket(b).name).to_string()
+    }
+
+    // --- DUAL CYCLE API ---
+    // Theo dõi đồng thời chu trình còn lại (Sinh <-> Khắc) với chuỗi độc lập
+    pub fn set_dual_cycle(&mut self, enabled: bool) {
+        self.tracks.dual = if enabled {
+            Some(CycleState::with_rules_of(self.tracks.primary.mode.other(), &self.tracks.primary))
+        } else {
+            None
+        };
+    }
+
+    // Đổi track chính giữa ván (xem CycleTracks::switch). Returns mode chính mới.
+    // Như booster: chỉ khi board ổn định (giữa 2 nước), ghi vào move journal để replay đổi đúng chỗ.
+    pub fn switch_cycle_track(&mut self) -> CycleMode {
+        if self.is_stable {
+            self.tracks.switch();
+            self.journal_record([JOURNAL_OP_MARKER, JOURNAL_OP_MARKER, JOURNAL_OP_SWITCH_TRACK, 0]);
+        }
+        self.tracks.primary.mode
+    }
+
+    // --- CYCLE STATE EXPORT ---
+    // { primary, dual, resonance_count } đủ để dựng lại đúng trạng thái tính điểm giữa ván
+    #[cfg(feature = "wasm")]
+    pub fn export_cycle_state(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.cycle_tracks()).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    // Returns false (state giữ nguyên) nếu object sai format hoặc không thể đạt được trong luật
+    #[cfg(feature = "wasm")]
+    pub fn import_cycle_state(&mut self, state: JsValue) -> bool {
+        serde_wasm_bindgen::from_value::<CycleTracks>(state).is_ok_and(|tracks| self.restore_cycle_tracks(tracks))
+    }
+
+    pub(crate) fn cycle_tracks(&self) -> CycleTracks {
+        self.tracks
+    }
+
+    pub(crate) fn restore_cycle_tracks(&mut self, tracks: CycleTracks) -> bool {
+        if !tracks.is_valid() { return false; }
+        self.tracks = tracks;
+        true
+    }
+
+    pub fn is_dual_cycle(&self) -> bool {
+        self.tracks.dual.is_some()
+    }
+
+    pub fn get_dual_cycle_target(&self) -> u8 {
+        self.tracks.dual.map_or(0, |c| c.target)
+    }
+
+    pub fn get_dual_cycle_chain(&self) -> u32 {
+        self.tracks.dual.map_or(0, |c| c.chain_length)
+    }
+
+    pub fn get_dual_cycle_multiplier(&self) -> u32 {
+        self.tracks.dual.map_or(1, |c| c.multiplier)
+    }
+
+    pub fn get_resonance_count(&self) -> u32 {
+        self.tracks.resonance_count
+    }
+
+    // --- INTERACTION CODEX ---
+    // Luật sẽ kích hoạt khi element_a match cạnh element_b, đọc từ bảng luật đang chạy.
+    // Returns { source, target, kind, shape, bonus, event_type } hoặc undefined.
+    #[cfg(feature = "wasm")]
+    pub fn explain_interaction(element_a: u8, element_b: u8) -> JsValue {
+        match GridState::interaction_rule(element_a, element_b) {
+            Some(rule) => serde_wasm_bindgen::to_value(rule).unwrap_or(JsValue::UNDEFINED),
+            None => JsValue::UNDEFINED,
+        }
     }
 
     // --- PREVIEW IMPLEMENTATION ---
@@ -733,18 +2031,19 @@ impl GridState {
 
         // Check matches
         let matches = self.find_all_matches();
+        let has_match = !matches.is_empty();
         let mut result = Vec::with_capacity(matches.len() * 5); // Heuristic
 
         for m in matches {
-            let interaction = self.analyze_match_interaction(&m);
+            let (interaction, _) = self.analyze_match_interaction(&m);
             match interaction {
-                InteractionType::Destruction(affected) => {
+                InteractionType::Destruction(affected, _) => {
                     for idx in affected {
                         result.push(idx as u32);
                         result.push(1); // 1 = Destruction (Red)
                     }
                 },
-                InteractionType::Generation(affected) => {
+                InteractionType::Generation(affected, _) => {
                     for idx in affected {
                         result.push(idx as u32);
                         result.push(2); // 2 = Generation (Blue/Green)
@@ -762,9 +2061,126 @@ impl GridState {
         // Revert Swap
         self.cells.swap(idx1, idx2);
 
+        // Hiệu ứng boss sẽ áp ngay khi nước đi này được chấp nhận
+        if has_match {
+            for hazard in self.hazards.upcoming(self.turn_count, 1) {
+                result.push(hazard.idx as u32);
+                result.push(3); // 3 = Hazard lượt tới
+            }
+        }
+
         result
     }
 
+    // --- LEVEL LAYOUT ---
+    // Đặt vật cản của level lên board (1 byte / ô, Stone = vật cản, xem sim/level.rs).
+    // Layout bị từ chối thì board giữ nguyên; returns [idx, LayoutIssue, ...], rỗng = đã áp.
+    pub fn apply_level_layout(&mut self, layout: &[u8]) -> Vec<u32> {
+        let issues = validate_level_layout(self.width, self.height, layout);
+        if !issues.is_empty() { return issues; }
+        for (cell, &element) in self.cells.iter_mut().zip(layout) {
+            if element == ElementType::Stone as u8 {
+                *cell = Cell { element, flags: 0 };
+            }
+        }
+        self.is_stable = false;
+        Vec::new()
+    }
+
+    // --- HAZARD API ---
+    // Lên lịch hiệu ứng `flag` lên ô `idx` sau `turns` lượt (1 = ngay sau nước đi tiếp theo)
+    pub fn schedule_hazard(&mut self, idx: usize, flag: u8, turns: u32) {
+        if idx >= self.cells.len() { return; }
+        self.hazards.schedule(ScheduledHazard { idx, flag, turn: self.turn_count.saturating_add(turns.max(1)) });
+    }
+
+    // Các hiệu ứng trong `window` lượt tới, bộ ba [index, flag, số lượt còn lại]
+    pub fn preview_hazards(&self, window: u32) -> Vec<u32> {
+        self.hazards
+            .upcoming(self.turn_count, window)
+            .flat_map(|h| [h.idx as u32, h.flag as u32, h.turn - self.turn_count])
+            .collect()
+    }
+
+    pub fn clear_hazards(&mut self) {
+        self.hazards.clear();
+    }
+
+    pub fn get_turn_count(&self) -> u32 {
+        self.turn_count
+    }
+
+    // MOVE FINDER: swap kề có nhiều ô bị ảnh hưởng nhất (theo preview)
+    // Tie-break theo thứ tự cố định Up/Down/Left/Right để deterministic.
+    fn find_best_swap_at(&mut self, idx: usize) -> Option<usize> {
+        if idx >= self.cells.len() { return None; }
+        let x = idx % self.width;
+        let y = idx / self.width;
+
+        let neighbors = [
+            if y > 0 { Some(idx - self.width) } else { None },
+            if y < self.height - 1 { Some(idx + self.width) } else { None },
+            if x > 0 { Some(idx - 1) } else { None },
+            if x < self.width - 1 { Some(idx + 1) } else { None },
+        ];
+
+        let mut best: Option<(usize, usize)> = None; // (target, affected)
+        for &n_idx in neighbors.iter().flatten() {
+            // Chỉ tính swap hợp lệ: không dính Stone / Locked và tạo match tại 1 trong 2 ô
+            if self.swap_blocked(idx, n_idx).is_some() { continue; }
+            self.cells.swap(idx, n_idx);
+            let valid = self.check_matches_at(idx) || self.check_matches_at(n_idx);
+            self.cells.swap(idx, n_idx);
+            if !valid { continue; }
+
+            let affected = self.preview_swap(idx, n_idx).len() / 2;
+            let better = match best {
+                Some((_, a)) => affected > a,
+                None => true,
+            };
+            if better { best = Some((n_idx, affected)); }
+        }
+        best.map(|(target, _)| target)
+    }
+
+    // --- HEATMAP (UI hot zones / balancing) ---
+    // Per-cell xác suất (0..1) ô đó nằm trong 1 match sau đúng 1 nước đi,
+    // = số swap hợp lệ tạo match chứa ô / tổng số swap hợp lệ.
+    pub fn compute_match_heatmap(&mut self) -> Vec<f32> {
+        let mut hits = vec![0u32; self.cells.len()];
+        let mut valid_swaps = 0u32;
+
+        for idx in 0..self.cells.len() {
+            let x = idx % self.width;
+            let y = idx / self.width;
+            // Chỉ xét Right/Down để mỗi cặp được tính 1 lần
+            let targets = [
+                if x < self.width - 1 { Some(idx + 1) } else { None },
+                if y < self.height - 1 { Some(idx + self.width) } else { None },
+            ];
+
+            for &n_idx in targets.iter().flatten() {
+                let (a, b) = (self.cells[idx].element, self.cells[n_idx].element);
+                if a == 10 || b == 10 || a == b { continue; }
+
+                self.cells.swap(idx, n_idx);
+                if self.check_matches_at(idx) || self.check_matches_at(n_idx) {
+                    valid_swaps += 1;
+                    for m in self.find_all_matches() {
+                        if !m.cells.contains(&idx) && !m.cells.contains(&n_idx) { continue; }
+                        for c in m.cells { hits[c] += 1; }
+                    }
+                }
+                self.cells.swap(idx, n_idx);
+            }
+        }
+
+        if valid_swaps == 0 {
+            return vec![0.0; self.cells.len()];
+        }
+        hits.iter().map(|&h| h as f32 / valid_swaps as f32).collect()
+    }
+
     pub fn preview_neighbors(&mut self, x: usize, y: usize) -> Vec<u32> {
         let mut results = Vec::new();
         let width = self.width;
@@ -788,16 +2204,773 @@ impl GridState {
     }
 }
 
+// Event interaction của 1 lần resolve, gom lại để mỗi ô chỉ còn 1 event.
+// Nhiều interaction chạm cùng 1 ô (Cross + 3x3, tâm của Cross...) -> giữ event của interaction
+// áp dụng sau cùng (= thắng theo ResolutionPolicy, khớp kết quả thật trên board),
+// ở vị trí của event đầu tiên để thứ tự phát vẫn tất định.
+#[derive(Default)]
+struct StagedInteractions {
+    events: Vec<(usize, u8, usize)>, // (ô, event type, tâm match)
+    slot: std::collections::BTreeMap<usize, usize>,
+}
+
+impl StagedInteractions {
+    fn stage(&mut self, type_id: u8, t_idx: usize, center_idx: usize) {
+        match self.slot.get(&t_idx) {
+            Some(&i) => self.events[i] = (t_idx, type_id, center_idx),
+            None => {
+                self.slot.insert(t_idx, self.events.len());
+                self.events.push((t_idx, type_id, center_idx));
+            }
+        }
+    }
+}
+
 // --- PREVIEW SYSTEM ---
 #[derive(Debug)]
 enum InteractionType {
     None,
-    Destruction(Vec<usize>), // Affected cells
-    Generation(Vec<usize>),  // Affected cells
+    Destruction(Vec<usize>, &'static InteractionRule), // Affected cells + luật đã khớp
+    Generation(Vec<usize>, &'static InteractionRule),
+}
+
+// --- INTERACTION RULE TABLE ---
+// Nguồn duy nhất cho luật Tương Sinh / Tương Khắc. Thứ tự = độ ưu tiên khi nhiều luật cùng khớp.
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum InteractionKind {
+    Destruction,
+    Generation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum AreaShape {
+    Cross,           // Hàng + cột qua tâm match
+    Row,             // Hàng qua tâm match
+    Area3x3,         // 3x3 quanh tâm
+    TargetNeighbors, // Các neighbor mang element target
+    OtherNeighbors,  // Các neighbor Ngũ Hành khác target
+    MatchCells,      // Chính các ô trong match
+    Center,          // Ô tâm match
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct InteractionRule {
+    pub source: u8, // Element của match
+    pub target: u8, // Element neighbor kích hoạt luật
+    pub kind: InteractionKind,
+    pub shape: AreaShape,
+    pub bonus: u32,
+    pub event_type: u8,
+    pub convert_to: u8,   // Element mà ô bị ảnh hưởng chuyển thành (0 = bị clear)
+    pub keep_cells: bool, // Ô được chuyển hóa không bị clear dù thuộc match
+    pub add_flags: u8,    // Flag gắn thêm cho ô được chuyển hóa (vd Power)
+}
+
+pub const INTERACTION_RULES: &[InteractionRule] = &[
+    // DESTRUCTION
+    // 1. Metal (1) cuts Wood (2) -> Cross Clear
+    InteractionRule { source: 1, target: 2, kind: InteractionKind::Destruction, shape: AreaShape::Cross, bonus: 300, event_type: EventType::Destruction as u8, convert_to: 0, keep_cells: false, add_flags: 0 },
+    // 2. Wood (2) breaks Earth (5) -> Line Clear (Row)
+    InteractionRule { source: 2, target: 5, kind: InteractionKind::Destruction, shape: AreaShape::Row, bonus: 300, event_type: EventType::Destruction as u8, convert_to: 0, keep_cells: false, add_flags: 0 },
+    // 3. Water (3) quenches Fire (4) -> Area Clear (3x3)
+    InteractionRule { source: 3, target: 4, kind: InteractionKind::Destruction, shape: AreaShape::Area3x3, bonus: 300, event_type: EventType::Destruction as u8, convert_to: 0, keep_cells: false, add_flags: 0 },
+    // 4. Fire (4) melts Metal (1)
+    InteractionRule { source: 4, target: 1, kind: InteractionKind::Destruction, shape: AreaShape::TargetNeighbors, bonus: 300, event_type: EventType::Destruction as u8, convert_to: 0, keep_cells: false, add_flags: 0 },
+    // 5. Earth (5) absorbs Water (3)
+    InteractionRule { source: 5, target: 3, kind: InteractionKind::Destruction, shape: AreaShape::TargetNeighbors, bonus: 300, event_type: EventType::Destruction as u8, convert_to: 0, keep_cells: false, add_flags: 0 },
+    // GENERATION
+    // 6. Wood (2) -> Fire (4)
+    InteractionRule { source: 2, target: 4, kind: InteractionKind::Generation, shape: AreaShape::MatchCells, bonus: 200, event_type: EventType::GenerationFireSpread as u8, convert_to: 4, keep_cells: true, add_flags: 0 },
+    // 7. Metal (1) -> Water (3)
+    InteractionRule { source: 1, target: 3, kind: InteractionKind::Generation, shape: AreaShape::OtherNeighbors, bonus: 200, event_type: EventType::GenerationWaterSpawn as u8, convert_to: 3, keep_cells: false, add_flags: 0 },
+    // 8. Water (3) -> Wood (2)
+    InteractionRule { source: 3, target: 2, kind: InteractionKind::Generation, shape: AreaShape::Center, bonus: 200, event_type: EventType::GenerationGrowth as u8, convert_to: 2, keep_cells: true, add_flags: 1 },
+];
+
+// --- RULE SNAPSHOT HASH ---
+// FNV-1a 64 trên 1 chuỗi byte chuẩn: thứ tự field cố định, số little-endian.
+// Đổi luật / thứ tự ghi thì tăng RULES_HASH_VERSION.
+const RULES_HASH_VERSION: u8 = 11;
+
+// FNV-1a 64 bit (rules hash, state hash)
+struct RuleHasher(u64);
+
+impl RuleHasher {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write_u8(&mut self, v: u8) {
+        self.0 ^= v as u64;
+        self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+
+    fn write_u32(&mut self, v: u32) {
+        for b in v.to_le_bytes() { self.write_u8(b); }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes { self.write_u8(b); }
+    }
 }
 
 impl GridState {
-    fn analyze_match_interaction(&self, m: &MatchResult) -> InteractionType {
+    // Logic swap dùng chung cho wasm (try_swap) và native API.
+    // Err = bị từ chối / rollback, đã phát event SwapRejected.
+    pub(crate) fn swap_checked(&mut self, idx1: usize, idx2: usize) -> Result<(), SwapRejectReason> {
+        // Validation
+        if idx1 >= self.cells.len() || idx2 >= self.cells.len() {
+            return self.reject_swap(idx1, idx2, SwapRejectReason::OutOfBounds);
+        }
+        if idx1 == idx2 { return self.reject_swap(idx1, idx2, SwapRejectReason::SameCell); }
+
+        if let Some(reason) = self.swap_blocked(idx1, idx2) {
+            return self.reject_swap(idx1, idx2, reason);
+        }
+
+        // Thực hiện Swap
+        self.cells.swap(idx1, idx2);
+
+        // Kiểm tra Match ngay lập tức
+        let has_match = self.check_matches_at(idx1) || self.check_matches_at(idx2);
+
+        if has_match {
+            // Nếu có match, trạng thái trở nên bất ổn để tick() xử lý tiếp
+            self.is_stable = false;
+            self.cascade_depth = 0; // Nước đi mới -> cascade tính lại từ đầu
+            // Góc phần tư của ô bắt đầu swap (anti-farming)
+            let (x, y) = (idx1 % self.width, idx1 / self.width);
+            self.decay.record_region((x * 2 / self.width + 2 * (y * 2 / self.height)) as u8);
+            self.turn_count += 1;
+            self.begin_turn();
+            self.stats.record_swap(true);
+            let w = self.width;
+            self.journal_record([(idx1 % w) as u8, (idx1 / w) as u8, (idx2 % w) as u8, (idx2 / w) as u8]);
+            self.apply_due_hazards();
+            self.report_stability();
+            return Ok(());
+        } else {
+            // Nếu không match, swap lại (Rollback)
+            self.cells.swap(idx1, idx2);
+            return self.reject_swap(idx1, idx2, SwapRejectReason::NoMatch);
+        }
+    }
+
+    // Không cho phép swap vật thể bị khóa (Stone) hoặc tile bị khoá.
+    // Dùng chung cho swap_checked và gợi ý nước đi (find_best_swap_at)
+    fn swap_blocked(&self, idx1: usize, idx2: usize) -> Option<SwapRejectReason> {
+        let c1 = self.cells[idx1];
+        let c2 = self.cells[idx2];
+        if c1.element == 10 || c2.element == 10 { return Some(SwapRejectReason::Stone); }
+        if (c1.flags | c2.flags) & FLAG_LOCKED != 0 { return Some(SwapRejectReason::Locked); }
+        None
+    }
+
+    // Lượt mới (swap hợp lệ): bộ đếm theo lượt của các track đang chạy.
+    // Không đếm theo tick để replay (chỉ tick tới khi ổn định) khớp client tick cả lúc rảnh.
+    fn begin_turn(&mut self) {
+        // Avatar phase hết số lượt
+        for track in 0..2 {
+            let Some(cycle) = self.tracks.get_mut(track) else { continue; };
+            let before = *cycle;
+            if !cycle.turn_avatar() { continue; }
+            let after = *cycle;
+            self.push_event_payload(EventType::AvatarExit as u8, 0, 0, track, after.multiplier);
+            self.push_cycle_milestones(track, &before, &after, false, 0, (0, 0));
+        }
+
+        // Momentum decay: lâu không tiến chuỗi thì multiplier tụt dần
+        for track in 0..2 {
+            let Some(cycle) = self.tracks.get_mut(track) else { continue; };
+            if !cycle.turn_momentum() { continue; }
+            let multiplier = cycle.multiplier;
+            self.push_event_payload(EventType::MomentumDecay as u8, 0, 0, track, multiplier);
+        }
+    }
+
+    // Phát BoardStable / BoardUnstable khi is_stable đổi so với lần báo trước.
+    // Payload theo lượt chứ không theo tick (frame đã có tick) để event log khớp replay.
+    fn report_stability(&mut self) {
+        if self.is_stable == self.reported_stable { return; }
+        self.reported_stable = self.is_stable;
+        let event = if self.is_stable { EventType::BoardStable } else { EventType::BoardUnstable };
+        self.push_event_payload(event as u8, 0, 0, 0, self.turn_count);
+    }
+
+    fn apply_due_hazards(&mut self) {
+        for hazard in self.hazards.take_due(self.turn_count) {
+            if let Some(cell) = self.cells.get_mut(hazard.idx) {
+                cell.flags |= hazard.flag;
+            }
+        }
+    }
+
+    fn reject_swap(&mut self, idx1: usize, idx2: usize, reason: SwapRejectReason) -> Result<(), SwapRejectReason> {
+        self.reject_swap_at((idx1 % self.width, idx1 / self.width), idx2, reason)
+    }
+
+    fn reject_swap_at(&mut self, (x, y): (usize, usize), idx2: usize, reason: SwapRejectReason) -> Result<(), SwapRejectReason> {
+        self.stats.record_swap(false);
+        self.push_event_payload(EventType::SwapRejected as u8, x, y, reason as u8, idx2 as u32);
+        Err(reason)
+    }
+
+    // Swap theo toạ độ (API native). Kiểm tra x < width, y < height trước khi tính index
+    // nên toạ độ lớn tuỳ ý vẫn ra OutOfBounds (kèm SwapRejected) thay vì tràn số.
+    pub(crate) fn swap_at_checked(&mut self, a: (usize, usize), b: (usize, usize)) -> Result<(), SwapRejectReason> {
+        let (w, h) = (self.width, self.height);
+        let index = |(x, y): (usize, usize)| (x < w && y < h).then(|| y * w + x);
+        match (index(a), index(b)) {
+            (Some(idx1), Some(idx2)) => self.plain_swap_checked(idx1, idx2),
+            _ => self.reject_swap_at(a, b.1.saturating_mul(w).saturating_add(b.0), SwapRejectReason::OutOfBounds),
+        }
+    }
+
+    // Field ẩn so sánh trong diff_states (thứ tự cố định). Chỉ gồm field replay dựng lại được:
+    // tick_count bỏ qua vì client tick lúc rảnh; bộ đếm Avatar / momentum đếm theo lượt nên giữ lại.
+    pub(crate) fn state_fields(&self) -> Vec<(&'static str, u64)> {
+        let c = &self.tracks.primary;
+        let dual = self.tracks.dual.unwrap_or_default();
+        vec![
+            ("width", self.width as u64),
+            ("height", self.height as u64),
+            ("score", self.score),
+            ("turn_count", self.turn_count as u64),
+            ("cycle.target", c.target as u64),
+            ("cycle.chain_length", c.chain_length as u64),
+            ("cycle.multiplier", c.multiplier as u64),
+            ("cycle.is_avatar_state", c.is_avatar_state as u64),
+            ("cycle.avatar_matches_left", c.avatar_matches_left as u64),
+            ("cycle.avatar_turns_left", c.avatar_turns_left as u64),
+            ("cycle.grace_left", c.grace_left as u64),
+            ("cycle.idle_turns", c.idle_turns as u64),
+            ("dual_cycle", self.tracks.dual.is_some() as u64),
+            ("dual_cycle.target", dual.target as u64),
+            ("dual_cycle.chain_length", dual.chain_length as u64),
+            ("dual_cycle.multiplier", dual.multiplier as u64),
+            ("resonance_count", self.tracks.resonance_count as u64),
+            ("cascade_depth", self.cascade_depth as u64),
+            ("is_stable", self.is_stable as u64),
+            ("cycle_skip_charges", self.cycle_skip_charges as u64),
+            ("rng.position", self.rng.position()),
+        ]
+    }
+
+    pub(crate) fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    // Số thứ tự event cho consumer trong core (spawner...), xem EventBuffer::events_since
+    pub(crate) fn events_stored(&self) -> u64 {
+        self.events.stored_count()
+    }
+
+    pub(crate) fn events_since(&self, seq: u64) -> impl Iterator<Item = GridEvent> + '_ {
+        self.events.events_since(seq)
+    }
+
+    pub(crate) fn latest_frame_events(&self) -> &[GridEvent] {
+        self.events.latest_frame_events()
+    }
+
+    // Lấy ra toàn bộ event đang buffer (cũ -> mới) dạng có kiểu
+    // Event từ ngoài grid (ECS) vào chung buffer, cùng frame / mask / log như event của grid
+    pub(crate) fn push_external_event(&mut self, event_type: EventType, x: usize, y: usize, intensity: u8, payload: u32) {
+        self.push_event_payload(event_type as u8, x, y, intensity, payload);
+    }
+
+    pub(crate) fn drain_events(&mut self) -> Vec<GridEvent> {
+        self.events.drain()
+    }
+
+    // Ô bị trọng lực dời trong tick gần nhất: (from, to)
+    pub(crate) fn moved_this_tick(&self) -> &[(usize, usize)] {
+        &self.tick_moves
+    }
+
+    // Ô bị clear trong tick gần nhất (match, interaction, special, ultimate)
+    pub(crate) fn cleared_this_tick(&self) -> &[usize] {
+        &self.tick_cleared
+    }
+
+    // moves: [x1, y1, x2, y2, ...]
+    // Snapshot nhị phân (xem sim/snapshot.rs). Thứ tự ghi = thứ tự đọc trong from_snapshot.
+    pub(crate) fn to_snapshot(&self) -> Vec<u8> {
+        self.to_snapshot_version(SNAPSHOT_VERSION)
+    }
+
+    // Ghi theo layout version cũ hơn, field chưa tồn tại ở version đó bị bỏ
+    pub(crate) fn to_snapshot_version(&self, version: u16) -> Vec<u8> {
+        self.write_snapshot(version, &self.move_journal)
+    }
+
+    // --- ROLLBACK FRAME ---
+    // State 1 frame cho ring rollback: snapshot không kèm move journal (journal chỉ append,
+    // nên nhớ độ dài là đủ để cắt lại). Hash trail + config event là runtime, giữ từ grid hiện tại.
+    pub(crate) fn save_frame_state(&self) -> FrameState {
+        FrameState { snapshot: self.write_snapshot(SNAPSHOT_VERSION, &[]), journal_len: self.move_journal.len() }
+    }
+
+    // Returns false nếu state hỏng (grid giữ nguyên)
+    pub(crate) fn restore_frame_state(&mut self, frame: &FrameState) -> bool {
+        let Ok(mut grid) = GridState::from_snapshot(&frame.snapshot) else { return false; };
+        grid.move_journal = std::mem::take(&mut self.move_journal);
+        grid.move_journal.truncate(frame.journal_len);
+        std::mem::swap(&mut grid.events, &mut self.events);
+        grid.events.clear();
+        // Bỏ hash của các tick sau frame
+        grid.hash_trail_every = self.hash_trail_every;
+        grid.hash_trail_first_tick = self.hash_trail_first_tick;
+        grid.hash_trail = std::mem::take(&mut self.hash_trail);
+        if let Some(kept) = grid.tick_count.checked_sub(grid.hash_trail_first_tick).filter(|_| grid.hash_trail_every > 0) {
+            grid.hash_trail.truncate((kept / grid.hash_trail_every) as usize + 1);
+        } else {
+            grid.hash_trail.clear();
+        }
+        *self = grid;
+        true
+    }
+
+    fn write_snapshot(&self, version: u16, move_journal: &[u8]) -> Vec<u8> {
+        let mut w = SnapshotWriter::with_version(version);
+        w.u32(self.width as u32);
+        w.u32(self.height as u32);
+        for cell in &self.cells {
+            w.u8(cell.element);
+            w.u8(cell.flags);
+        }
+        w.u32(self.tick_count);
+        w.score(self.score);
+        w.bytes(&self.match_queue);
+        w.bool(self.is_stable);
+        w.bool(self.reported_stable);
+        w.bool(self.auto_refill);
+        w.rng(&self.rng);
+        w.cycle(&self.tracks.primary);
+        w.bool(self.tracks.dual.is_some());
+        w.cycle(&self.tracks.dual.unwrap_or_default());
+        w.u32(self.tracks.resonance_count);
+        w.u32(self.cascade_depth);
+        w.u32(self.score_modifier_pct);
+        w.u32(self.score_decay.element_step_pct);
+        w.u32(self.score_decay.region_step_pct);
+        w.u32(self.score_decay.floor_pct);
+        w.opt_u32(self.decay.last_element.map(u32::from));
+        w.u32(self.decay.element_streak);
+        w.opt_u32(self.decay.last_region.map(u32::from));
+        w.u32(self.decay.region_streak);
+        if w.since(3) {
+            w.u32(self.drought_pity.threshold);
+            w.u32(self.drought_pity.step_pct);
+            w.u32(self.drought_pity.max_boost_pct);
+            for i in 0..self.drought.current.len() {
+                w.u32(self.drought.current[i]);
+                w.u32(self.drought.longest[i]);
+            }
+            w.u32(self.drought.pity_spawns);
+        }
+        w.u8(self.resolution_policy as u8);
+        w.opt_u32(self.experiment_bucket);
+        w.u32(self.turn_count);
+        w.u32(self.hazards.pending().len() as u32);
+        for h in self.hazards.pending() {
+            w.u32(h.idx as u32);
+            w.u8(h.flag);
+            w.u32(h.turn);
+        }
+        if w.since(4) {
+            w.u32(self.players.len() as u32);
+            for p in &self.players {
+                w.u32(p.id);
+                w.cycle(&p.cycle);
+                w.score(p.score);
+                if w.since(6) { w.u32(p.role_mask); }
+            }
+            w.opt_u32(self.active_player.map(|(i, _)| i as u32));
+            w.score(self.active_player.map_or(0, |(_, start)| start));
+        }
+        if w.since(6) {
+            w.u32(self.turn_window);
+            w.u32(self.turn_slot as u32);
+            w.u32(self.turn_used);
+        }
+        if w.since(7) {
+            w.u32(self.cycle_skip_charges);
+            w.bytes(move_journal);
+        }
+        if w.since(12) {
+            w.bool(self.recording_moves);
+        }
+        if w.since(13) {
+            w.u64(self.seed);
+        }
+        if w.since(8) {
+            let b = &self.score_breakdown;
+            for v in [b.base, b.interaction, b.cascade, b.cycle, b.avatar] {
+                w.score(v);
+            }
+        }
+        if w.since(9) {
+            let st = &self.stats;
+            for &v in &st.matches_by_element {
+                w.u32(v);
+            }
+            for v in [st.largest_cluster, st.longest_cascade, st.longest_chain, st.destructions, st.generations, st.swaps_attempted, st.swaps_rejected] {
+                w.u32(v);
+            }
+        }
+        if w.since(11) {
+            let (c, rw) = (&self.xp_curve, &self.xp_rewards);
+            w.u64(self.xp);
+            for v in [c.base, c.growth_pct, c.max_level, rw.per_match, rw.per_extra_cell, rw.per_interaction] {
+                w.u32(v);
+            }
+        }
+        if w.since(16) {
+            w.u32(self.board_noise.scale);
+            w.u32(self.board_noise.cluster_pct);
+        }
+        w.bool(self.sandbox);
+        w.bool(self.tainted);
+        w.bool(self.frozen_rng.is_some());
+        if let Some(rng) = &self.frozen_rng {
+            w.rng(rng);
+        }
+        w.finish()
+    }
+
+    // Đọc theo layout `from_version`, ghi lại theo SNAPSHOT_VERSION
+    pub(crate) fn migrate_snapshot(data: &[u8], from_version: u16) -> Result<Vec<u8>, SnapshotError> {
+        let version = snapshot_version(data)?;
+        if version != from_version { return Err(SnapshotError::VersionMismatch(version)); }
+        GridState::from_snapshot(data).map(|grid| grid.to_snapshot())
+    }
+
+    pub(crate) fn from_snapshot(data: &[u8]) -> Result<GridState, SnapshotError> {
+        let mut r = SnapshotReader::new(data)?;
+        let width = r.u32()? as usize;
+        let height = r.u32()? as usize;
+        if width == 0 || height == 0 || width * height > 1 << 16 { return Err(SnapshotError::Invalid); }
+        let mut grid = GridState::new_empty(width, height, 0);
+        for cell in grid.cells.iter_mut() {
+            cell.element = r.u8()?;
+            cell.flags = r.u8()?;
+        }
+        grid.tick_count = r.u32()?;
+        grid.score = r.score()?;
+        grid.match_queue = r.bytes()?.to_vec();
+        grid.is_stable = r.bool()?;
+        grid.reported_stable = r.bool()?;
+        grid.auto_refill = r.bool()?;
+        grid.rng = r.rng()?;
+        grid.tracks.primary = r.cycle()?;
+        let has_dual = r.bool()?;
+        let dual = r.cycle()?;
+        grid.tracks.dual = if has_dual { Some(dual) } else { None };
+        grid.tracks.resonance_count = r.u32()?;
+        grid.cascade_depth = r.u32()?;
+        grid.score_modifier_pct = r.u32()?;
+        grid.score_decay = ScoreDecay { element_step_pct: r.u32()?, region_step_pct: r.u32()?, floor_pct: r.u32()? };
+        grid.decay = DecayTracker {
+            last_element: r.opt_u32()?.map(|v| v as u8),
+            element_streak: r.u32()?,
+            last_region: r.opt_u32()?.map(|v| v as u8),
+            region_streak: r.u32()?,
+        };
+        if r.since(3) {
+            grid.drought_pity = DroughtPity { threshold: r.u32()?, step_pct: r.u32()?, max_boost_pct: r.u32()? };
+            for i in 0..grid.drought.current.len() {
+                grid.drought.current[i] = r.u32()?;
+                grid.drought.longest[i] = r.u32()?;
+            }
+            grid.drought.pity_spawns = r.u32()?;
+        }
+        grid.resolution_policy = ResolutionPolicy::from_id(r.u8()?).ok_or(SnapshotError::Invalid)?;
+        grid.experiment_bucket = r.opt_u32()?;
+        grid.turn_count = r.u32()?;
+        for _ in 0..r.u32()? {
+            let idx = r.u32()? as usize;
+            if idx >= grid.cells.len() { return Err(SnapshotError::Invalid); }
+            grid.hazards.schedule(ScheduledHazard { idx, flag: r.u8()?, turn: r.u32()? });
+        }
+        if r.since(4) {
+            for _ in 0..r.u32()? {
+                let (id, cycle, score) = (r.u32()?, r.cycle()?, r.score()?);
+                let role_mask = if r.since(6) { r.u32()? } else { 0 };
+                grid.players.push(PlayerTrack { id, cycle, score, role_mask });
+            }
+            let active = r.opt_u32()?;
+            let start = r.score()?;
+            if let Some(i) = active {
+                if i as usize >= grid.players.len() { return Err(SnapshotError::Invalid); }
+                grid.active_player = Some((i as usize, start));
+            }
+        }
+        if r.since(6) {
+            grid.turn_window = r.u32()?;
+            grid.turn_slot = r.u32()? as usize;
+            grid.turn_used = r.u32()?;
+            if grid.turn_window > 0 && grid.turn_slot >= grid.players.len().max(1) { return Err(SnapshotError::Invalid); }
+        }
+        if r.since(7) {
+            grid.cycle_skip_charges = r.u32()?;
+            grid.move_journal = r.bytes()?.to_vec();
+        }
+        if r.since(12) {
+            grid.recording_moves = r.bool()?;
+        }
+        if r.since(13) {
+            grid.seed = r.u64()?;
+        }
+        // Snapshot cũ không có breakdown: dồn cả vào base để tổng vẫn khớp score
+        grid.score_breakdown = if r.since(8) {
+            ScoreBreakdown { base: r.score()?, interaction: r.score()?, cascade: r.score()?, cycle: r.score()?, avatar: r.score()? }
+        } else {
+            ScoreBreakdown { base: grid.score, ..ScoreBreakdown::default() }
+        };
+        if r.since(9) {
+            for count in grid.stats.matches_by_element.iter_mut() {
+                *count = r.u32()?;
+            }
+            let st = &mut grid.stats;
+            for v in [&mut st.largest_cluster, &mut st.longest_cascade, &mut st.longest_chain, &mut st.destructions, &mut st.generations, &mut st.swaps_attempted, &mut st.swaps_rejected] {
+                *v = r.u32()?;
+            }
+        }
+        if r.since(11) {
+            grid.xp = r.u64()?;
+            grid.xp_curve = XpCurve { base: r.u32()?, growth_pct: r.u32()?, max_level: r.u32()? };
+            grid.xp_rewards = XpRewards { per_match: r.u32()?, per_extra_cell: r.u32()?, per_interaction: r.u32()? };
+            if grid.xp_curve.base == 0 || !(1..=MAX_XP_LEVEL).contains(&grid.xp_curve.max_level) { return Err(SnapshotError::Invalid); }
+        }
+        if r.since(16) {
+            grid.board_noise = BoardNoise { scale: r.u32()?, cluster_pct: r.u32()? };
+            if grid.board_noise.cluster_pct > 100 { return Err(SnapshotError::Invalid); }
+        }
+        grid.sandbox = r.bool()?;
+        grid.tainted = r.bool()?;
+        grid.frozen_rng = if r.bool()? { Some(r.rng()?) } else { None };
+        r.finish()?;
+        Ok(grid)
+    }
+
+    fn replay_moves(grid: &mut GridState, moves: &[u8]) -> u32 {
+        for record in moves.chunks_exact(4) {
+            grid.replay_record(record);
+        }
+        grid.get_score()
+    }
+
+    // Áp dụng 1 record [x1, y1, x2, y2] (hoặc booster [FF, FF, op, 0]), không tick.
+    // Returns false nếu swap / booster bị từ chối.
+    pub(crate) fn apply_input(&mut self, record: &[u8]) -> bool {
+        let (x1, y1, x2, y2) = (record[0] as usize, record[1] as usize, record[2] as usize, record[3] as usize);
+
+        // Record booster trong journal
+        if record[0] == JOURNAL_OP_MARKER && record[1] == JOURNAL_OP_MARKER {
+            return match record[2] {
+                JOURNAL_OP_SKIP_CYCLE => self.skip_cycle_target(),
+                JOURNAL_OP_SWITCH_TRACK if self.is_stable => {
+                    self.switch_cycle_track();
+                    true
+                }
+                JOURNAL_OP_PLAYER if (record[3] as usize) < self.players.len() => {
+                    self.replay_player = Some(record[3] as usize);
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        let (idx1, idx2) = (y1 * self.width + x1, y2 * self.width + x2);
+        match self.replay_player.take() {
+            Some(i) => self.try_swap_as(self.players[i].id, idx1, idx2),
+            None => self.try_swap(idx1, idx2),
+        }
+    }
+
+    // apply_input rồi chạy tới khi ổn định
+    pub(crate) fn replay_record(&mut self, record: &[u8]) -> bool {
+        if !self.apply_input(record) { return false; }
+        // If swap success (match made), Run simulation until stable
+        let mut ticks = 0;
+        let max_ticks = 1000; // prevents infinite loop
+        while !self.is_stable && ticks < max_ticks {
+            self.tick();
+            ticks += 1;
+        }
+        true
+    }
+
+    pub(crate) fn replay_header(&self) -> ReplayHeader {
+        let boosters = self.journal_ops(JOURNAL_OP_SKIP_CYCLE);
+        let mut flags = 0;
+        if self.start_cycle_mode() == CycleMode::Destruction { flags |= REPLAY_FLAG_DESTRUCTION; }
+        if self.tracks.dual.is_some() { flags |= REPLAY_FLAG_DUAL_CYCLE; }
+        ReplayHeader {
+            core_version: ReplayHeader::CORE_VERSION,
+            width: self.width as u16,
+            height: self.height as u16,
+            seed: self.seed,
+            flags,
+            skip_charges: self.cycle_skip_charges + boosters as u32,
+            rules_hash: self.rules_hash(),
+            move_count: (self.move_journal.len() / 4) as u32,
+            final_checksum: self.get_checksum(),
+            final_score: self.score,
+            bucket: self.experiment_bucket,
+            rules: self.replay_rules(),
+        }
+    }
+
+    fn replay_rules(&self) -> ReplayRules {
+        let cycle = &self.tracks.primary;
+        ReplayRules {
+            resolution_policy: self.resolution_policy,
+            auto_refill: self.auto_refill,
+            cycle_grace: cycle.grace_per_chain,
+            momentum_decay_turns: cycle.momentum_decay_turns,
+            score_modifier_pct: self.score_modifier_pct,
+            score_decay: self.score_decay,
+            drought_pity: self.drought_pity,
+            board_noise: self.board_noise,
+            xp_curve: self.xp_curve,
+            xp_rewards: self.xp_rewards,
+            turn_window: self.turn_window,
+            players: self.players.iter().map(|p| (p.id, p.role_mask)).collect(),
+        }
+    }
+
+    fn journal_ops(&self, op: u8) -> usize {
+        self.move_journal.chunks_exact(4).filter(|r| *r == [JOURNAL_OP_MARKER, JOURNAL_OP_MARKER, op, 0]).count()
+    }
+
+    // Mode chính lúc đầu ván: mỗi lần đổi track (có trong journal) lật mode chính 1 lần
+    fn start_cycle_mode(&self) -> CycleMode {
+        let mode = self.tracks.primary.mode;
+        if self.journal_ops(JOURNAL_OP_SWITCH_TRACK) % 2 == 1 { mode.other() } else { mode }
+    }
+
+    // Ván mới đúng cấu hình header (chưa chơi nước nào), cùng thứ tự set với Game::new.
+    // Bucket áp trước: mode / policy / modifier trong header là giá trị cuối cùng, ghi đè variant.
+    pub(crate) fn from_replay_header(header: &ReplayHeader) -> GridState {
+        let r = &header.rules;
+        let mut grid = GridState::new_with_noise(header.width as usize, header.height as usize, header.seed, r.board_noise.scale, r.board_noise.cluster_pct);
+        if let Some(bucket) = header.bucket { grid.set_experiment_bucket(bucket); }
+        grid.set_cycle_mode(if header.flags & REPLAY_FLAG_DESTRUCTION != 0 { CycleMode::Destruction } else { CycleMode::Generation });
+        grid.set_dual_cycle(header.flags & REPLAY_FLAG_DUAL_CYCLE != 0);
+        grid.set_cycle_grace(r.cycle_grace);
+        grid.set_momentum_decay(r.momentum_decay_turns);
+        grid.set_cycle_skip_charges(header.skip_charges);
+        grid.set_resolution_policy(r.resolution_policy);
+        grid.set_score_modifier(r.score_modifier_pct);
+        let decay = r.score_decay;
+        grid.set_score_decay(decay.element_step_pct, decay.region_step_pct, decay.floor_pct);
+        let pity = r.drought_pity;
+        grid.set_drought_pity(pity.threshold, pity.step_pct, pity.max_boost_pct);
+        let (curve, rewards) = (r.xp_curve, r.xp_rewards);
+        grid.set_xp_curve(curve.base, curve.growth_pct, curve.max_level);
+        grid.set_xp_rewards(rewards.per_match, rewards.per_extra_cell, rewards.per_interaction);
+        grid.auto_refill = r.auto_refill;
+        // Co-op: người chơi thêm sau cùng để track của họ mang luật grace / momentum ở trên
+        for &(id, role) in &r.players {
+            grid.add_player(id);
+            grid.set_player_role(id, role);
+        }
+        grid.set_turn_window(r.turn_window);
+        grid
+    }
+
+    pub(crate) fn score_u64(&self) -> u64 {
+        self.score
+    }
+
+    pub(crate) fn rules_hash(&self) -> u64 {
+        let mut h = RuleHasher::new();
+        h.write_u8(RULES_HASH_VERSION);
+
+        // Board / mode
+        h.write_u32(self.width as u32);
+        h.write_u32(self.height as u32);
+        // Mode lúc đầu ván: đổi track giữa ván là input (journal) chứ không phải đổi luật
+        let mode = self.start_cycle_mode();
+        h.write_u8(mode as u8);
+        h.write_u8(self.tracks.dual.map_or(0xFF, |_| mode.other() as u8));
+        h.write_u8(self.resolution_policy as u8);
+        h.write_u8(self.auto_refill as u8);
+        h.write_u32(self.experiment_bucket.map_or(u32::MAX, |b| b));
+        // RNG + board đầu: cùng seed nhưng khác RNG / noise là ván khác
+        h.write_u8(self.is_legacy_rng() as u8);
+        let noise = if self.board_noise.is_off() { BoardNoise::OFF } else { self.board_noise };
+        h.write_u32(noise.scale);
+        h.write_u32(noise.cluster_pct);
+
+        // Interaction matrix
+        h.write_u32(INTERACTION_RULES.len() as u32);
+        for rule in INTERACTION_RULES {
+            h.write_u8(rule.source);
+            h.write_u8(rule.target);
+            h.write_u8(rule.kind as u8);
+            h.write_u8(rule.shape as u8);
+            h.write_u32(rule.bonus);
+            h.write_u8(rule.event_type);
+            h.write_u8(rule.convert_to);
+            h.write_u8(rule.keep_cells as u8);
+            h.write_u8(rule.add_flags);
+        }
+
+        // Scoring
+        h.write_u32(AVATAR_SCORE_FACTOR);
+        h.write_u32(MAX_CASCADE_MULTIPLIER);
+        h.write_u32(self.score_modifier_pct);
+        h.write_u32(AVATAR_ULTIMATE_SCORE);
+        h.write_u32(SUNDER_SCORE);
+        h.write_u32(RESONANCE_BONUS);
+        h.write_u32(self.score_decay.element_step_pct);
+        h.write_u32(self.score_decay.region_step_pct);
+        h.write_u32(self.score_decay.floor_pct);
+        h.write_u8(MAX_SPECIAL_CHAIN_DEPTH);
+
+        // Cycle
+        h.write_u32(AVATAR_MAX_MATCHES);
+        h.write_u32(AVATAR_MAX_TURNS);
+        h.write_u32(AVATAR_EXIT_DECAY_PCT);
+        h.write_u32(self.tracks.primary.grace_per_chain);
+        h.write_u32(self.tracks.primary.momentum_decay_turns);
+
+        // Co-op: luân phiên + vai trò theo thứ tự người chơi
+        h.write_u32(self.turn_window);
+        h.write_u32(self.players.len() as u32);
+        for p in &self.players {
+            h.write_u32(p.role_mask);
+        }
+
+        for mode in [CycleMode::Generation, CycleMode::Destruction] {
+            h.write_u8(mode.start_target());
+            h.write_u32(mode.multiplier_step());
+            h.write_u8(mode.enters_avatar() as u8);
+            for element in 1..=SPAWN_ELEMENT_COUNT {
+                h.write_u8(mode.next_target(element));
+            }
+        }
+
+        // XP
+        let (c, rw) = (&self.xp_curve, &self.xp_rewards);
+        for v in [c.base, c.growth_pct, c.max_level, rw.per_match, rw.per_extra_cell, rw.per_interaction] {
+            h.write_u32(v);
+        }
+
+        // Spawn weights
+        h.write_u8(SPAWN_ELEMENT_COUNT);
+        h.write_u32(self.drought_pity.threshold);
+        h.write_u32(self.drought_pity.step_pct);
+        h.write_u32(self.drought_pity.max_boost_pct);
+
+        h.0
+    }
+
+    fn analyze_match_interaction(&self, m: &MatchResult) -> (InteractionType, u32) {
          // Identify Neighbors
          let mut neighbors = Vec::new();
          for &c_idx in &m.cells {
@@ -816,78 +2989,199 @@ impl GridState {
              }
          }
 
-         let mut affected = Vec::new();
-
-         // 1. Metal (1) cuts Wood (2) -> Cross Clear
-         if m.element == 1 && neighbors.iter().any(|&n| self.cells[n].element == 2) {
-             let center_x = m.center_idx % self.width;
-             let center_y = m.center_idx / self.width;
-             for x in 0..self.width { affected.push(center_y * self.width + x); }
-             for y in 0..self.height { affected.push(y * self.width + center_x); }
-             return InteractionType::Destruction(affected);
-         }
-         
-         // 2. Wood (2) breaks Earth (5) -> Line Clear (Row)
-         if m.element == 2 && neighbors.iter().any(|&n| self.cells[n].element == 5) {
-             let center_y = m.center_idx / self.width;
-             for x in 0..self.width { affected.push(center_y * self.width + x); }
-             return InteractionType::Destruction(affected);
-         }
+         // Luật đầu tiên (theo thứ tự bảng) có neighbor khớp sẽ được kích hoạt
+         let rule = INTERACTION_RULES.iter().find(|r| {
+             r.source == m.element && neighbors.iter().any(|&n| self.cells[n].element == r.target)
+         });
+         let rule = match rule {
+             Some(r) => r,
+             None => return (InteractionType::None, 0),
+         };
 
-         // 3. Water (3) quenches Fire (4) -> Area Clear (3x3)
-         if m.element == 3 && neighbors.iter().any(|&n| self.cells[n].element == 4) {
-             let cx = (m.center_idx % self.width) as isize;
-             let cy = (m.center_idx / self.width) as isize;
-             for dy in -1..=1 {
-                 for dx in -1..=1 {
-                     let nx = cx + dx;
-                     let ny = cy + dy;
-                     if nx >= 0 && nx < self.width as isize && ny >= 0 && ny < self.height as isize {
-                         affected.push((ny as usize) * self.width + (nx as usize));
+         let mut affected = Vec::new();
+         let center_x = m.center_idx % self.width;
+         let center_y = m.center_idx / self.width;
+
+         match rule.shape {
+             AreaShape::Cross => {
+                 for x in 0..self.width { affected.push(center_y * self.width + x); }
+                 for y in 0..self.height { affected.push(y * self.width + center_x); }
+             },
+             AreaShape::Row => {
+                 for x in 0..self.width { affected.push(center_y * self.width + x); }
+             },
+             AreaShape::Area3x3 => {
+                 let cx = center_x as isize;
+                 let cy = center_y as isize;
+                 for dy in -1..=1 {
+                     for dx in -1..=1 {
+                         let nx = cx + dx;
+                         let ny = cy + dy;
+                         if nx >= 0 && nx < self.width as isize && ny >= 0 && ny < self.height as isize {
+                             affected.push((ny as usize) * self.width + (nx as usize));
+                         }
                      }
                  }
-             }
-             return InteractionType::Destruction(affected);
+             },
+             AreaShape::TargetNeighbors => {
+                 for &n in &neighbors {
+                     if self.cells[n].element == rule.target { affected.push(n); }
+                 }
+             },
+             AreaShape::OtherNeighbors => {
+                 for &n in &neighbors {
+                     if self.cells[n].element != rule.target && self.cells[n].element <= 5 { affected.push(n); }
+                 }
+             },
+             AreaShape::MatchCells => {
+                 for &c in &m.cells { affected.push(c); }
+             },
+             AreaShape::Center => {
+                 affected.push(m.center_idx);
+             },
          }
 
-          // 4. Fire (4) melts Metal (1)
-         if m.element == 4 && neighbors.iter().any(|&n| self.cells[n].element == 1) {
-             for &n in &neighbors {
-                 if self.cells[n].element == 1 { affected.push(n); }
-             }
-             return InteractionType::Destruction(affected);
-         }
+         let interaction = match rule.kind {
+             InteractionKind::Destruction => InteractionType::Destruction(affected, rule),
+             InteractionKind::Generation => InteractionType::Generation(affected, rule),
+         };
+         (interaction, rule.bonus)
+    }
 
-         // 5. Earth (5) absorbs Water (3)
-         if m.element == 5 && neighbors.iter().any(|&n| self.cells[n].element == 3) {
-             for &n in &neighbors {
-                 if self.cells[n].element == 3 { affected.push(n); }
-             }
-             return InteractionType::Destruction(affected); // Or conversion? Let's classify as Destruction for now for red glow
-         }
+    // Tra cứu luật sẽ kích hoạt khi element_a match cạnh element_b (dùng cho codex UI)
+    pub fn interaction_rule(element_a: u8, element_b: u8) -> Option<&'static InteractionRule> {
+        INTERACTION_RULES.iter().find(|r| r.source == element_a && r.target == element_b)
+    }
 
-         // GENERATION
-         // 6. Wood (2) -> Fire (4)
-         if m.element == 2 && neighbors.iter().any(|&n| self.cells[n].element == 4) {
-             for &c in &m.cells { affected.push(c); }
-             return InteractionType::Generation(affected);
-         }
+    // Thứ tự áp dụng interaction: cái áp dụng sau cùng sẽ thắng ở các ô chồng lấn
+    fn resolution_order(&self, matches: &[MatchResult], analyzed: &[Option<(InteractionType, u32)>]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..matches.len()).collect();
+        // sort_by_key là stable -> cùng hạng thì giữ thứ tự tìm thấy
+        match self.resolution_policy {
+            ResolutionPolicy::DestructionWins => order.sort_by_key(|&i| match &analyzed[i] {
+                Some((InteractionType::Destruction(..), _)) => 1,
+                _ => 0,
+            }),
+            ResolutionPolicy::GenerationWins => order.sort_by_key(|&i| match &analyzed[i] {
+                Some((InteractionType::Generation(..), _)) => 1,
+                _ => 0,
+            }),
+            ResolutionPolicy::OrderedByPattern => order.sort_by_key(|&i| matches[i].pattern.priority()),
+            ResolutionPolicy::FoundOrder => {}
+        }
+        order
+    }
 
-         // 7. Metal (1) -> Water (3)
-         if m.element == 1 && neighbors.iter().any(|&n| self.cells[n].element == 3) {
-             for &n in &neighbors {
-                 if self.cells[n].element != 3 && self.cells[n].element <= 5 { affected.push(n); }
-             }
-             return InteractionType::Generation(affected);
-         }
+    // Áp dụng hiệu ứng tương tác của 1 match vào tập cells_to_clear
+    fn apply_interaction(&mut self, m: &MatchResult, interaction: InteractionType, cells_to_clear: &mut std::collections::BTreeSet<usize>, staged: &mut StagedInteractions) {
+        
+        match interaction {
+            InteractionType::Destruction(..) => self.stats.destructions += 1,
+            InteractionType::Generation(..) => self.stats.generations += 1,
+            InteractionType::None => {}
+        }
+        if !matches!(interaction, InteractionType::None) {
+            self.gain_xp(self.xp_rewards.per_interaction as u64);
+        }
+        // Hiệu ứng đọc từ luật đã khớp: clear ô, hoặc chuyển hóa ô (convert_to / keep_cells / add_flags)
+        let (target_idxs, rule) = match interaction {
+            InteractionType::Destruction(target_idxs, rule) | InteractionType::Generation(target_idxs, rule) => (target_idxs, rule),
+            InteractionType::None => return, // Normal match, already added to cells_to_clear
+        };
+        for &t_idx in &target_idxs {
+            if rule.convert_to == 0 {
+                cells_to_clear.insert(t_idx);
+            } else {
+                if rule.keep_cells { cells_to_clear.remove(&t_idx); }
+                self.cells[t_idx].element = rule.convert_to;
+                self.cells[t_idx].flags |= rule.add_flags;
+            }
+            staged.stage(rule.event_type, t_idx, m.center_idx);
+        }
+    }
 
-         // 8. Water (3) -> Wood (2)
-         if m.element == 3 && neighbors.iter().any(|&n| self.cells[n].element == 2) {
-             affected.push(m.center_idx);
-             return InteractionType::Generation(affected);
-         }
+    // Execute Clears
+    // Work queue: special tiles bị clear sẽ đẩy vùng nổ của chúng vào queue,
+    // nên chuỗi kích hoạt được xử lý trọn trong cùng 1 tick với thứ tự event ổn định.
+    // combo_sizes: ô -> kích thước cụm match chứa nó (ô bị clear ké thì không có)
+    fn execute_clears(&mut self, cells_to_clear: std::collections::BTreeSet<usize>, combo_sizes: &std::collections::BTreeMap<usize, usize>) {
+        // BTreeSet đã theo thứ tự index -> thứ tự event tất định
+        let mut queue: std::collections::VecDeque<(usize, u8)> =
+            cells_to_clear.into_iter().map(|idx| (idx, 0)).collect();
+        let mut processed = vec![false; self.cells.len()];
 
-         InteractionType::None
+        while let Some((idx, depth)) = queue.pop_front() {
+            if processed[idx] { continue; }
+            processed[idx] = true;
+
+            let cell = self.cells[idx];
+            if cell.element == 0 { continue; } // Check if already cleared
+
+            let (x, y) = (idx % self.width, idx / self.width);
+            // Client can handle "Element 0 event" as "Pop".
+            // v2 payload mang cascade depth + combo size để renderer / audio tăng hiệu ứng
+            let combo = combo_sizes.get(&idx).copied().unwrap_or(0);
+            self.push_event_payload(cell.element, x, y, 50, clear_payload(self.cascade_depth, combo));
+            self.cells[idx] = Cell { element: 0, flags: 0 };
+            self.tick_cleared.push(idx);
+
+            if (cell.flags & FLAG_SPECIAL_MASK) != 0 && depth < MAX_SPECIAL_CHAIN_DEPTH {
+                // Event Type 60 = Special Activation, intensity = chain depth
+                self.push_event_payload(EventType::SpecialActivation as u8, x, y, depth + 1, cell.flags as u32); // payload = special flags
+                for t_idx in self.special_blast_area(idx, cell.flags) {
+                    if !processed[t_idx] {
+                        queue.push_back((t_idx, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+
+    // Avatar Ultimate: đánh dấu clear mọi ô mang element, returns số ô mới bị đánh dấu
+    fn avatar_ultimate(&self, element: u8, cells_to_clear: &mut std::collections::BTreeSet<usize>) -> u32 {
+        let mut cleared = 0;
+        for (idx, cell) in self.cells.iter().enumerate() {
+            if cell.element == element && cells_to_clear.insert(idx) {
+                cleared += 1;
+            }
+        }
+        cleared
+    }
+
+    // Khắc Sunder: đánh dấu clear mọi vật cản (Stone / Dark) và mọi ô bị `element` khắc.
+    // Returns số ô mới bị đánh dấu
+    fn khac_sunder(&self, element: u8, cells_to_clear: &mut std::collections::BTreeSet<usize>) -> u32 {
+        let overcome = CycleMode::Destruction.next_target(element);
+        let mut cleared = 0;
+        for (idx, cell) in self.cells.iter().enumerate() {
+            let hit = cell.element == overcome || cell.element == ElementType::Stone as u8 || cell.element == ElementType::Dark as u8;
+            if hit && cells_to_clear.insert(idx) {
+                cleared += 1;
+            }
+        }
+        cleared
+    }
+
+    // Vùng ảnh hưởng khi 1 special tile bị kích hoạt (có thể gộp nhiều flag)
+    fn special_blast_area(&self, idx: usize, flags: u8) -> Vec<usize> {
+        let cx = idx % self.width;
+        let cy = idx / self.width;
+        let mut area = Vec::new();
+
+        if (flags & FLAG_STRIPED_H) != 0 {
+            for x in 0..self.width { area.push(cy * self.width + x); }
+        }
+        if (flags & FLAG_STRIPED_V) != 0 {
+            for y in 0..self.height { area.push(y * self.width + cx); }
+        }
+        if (flags & FLAG_BOMB) != 0 {
+            for y in cy.saturating_sub(1)..=(cy + 1).min(self.height - 1) {
+                for x in cx.saturating_sub(1)..=(cx + 1).min(self.width - 1) {
+                    area.push(y * self.width + x);
+                }
+            }
+        }
+        area
     }
 
     // Fluid Interaction
@@ -919,78 +3213,3 @@ impl GridState {
         }
     }
 }
-
-// --- CYCLE SYSTEM LOGIC ---
-
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
-pub struct CycleState {
-    pub target: u8,        // ElementType (1-5)
-    pub chain_length: u32,
-    pub multiplier: u32,
-    pub is_avatar_state: bool,
-}
-
-impl CycleState {
-    pub fn new() -> Self {
-        Self {
-            target: 3, // Start with WATER (3)
-            chain_length: 0,
-            multiplier: 1,
-            is_avatar_state: false,
-        }
-    }
-
-    // Check if match continues cycle
-    // Returns: (is_success, multiplier_applied)
-    pub fn process_match(&mut self, element: u8) -> (bool, u32) {
-        if self.is_avatar_state {
-             // In Avatar State, everything is a match/bonus?
-             // Or just huge multiplier?
-             // Let's keep existing logic but with boosted stats
-             self.multiplier += 1;
-             return (true, self.multiplier * 2); 
-        }
-
-        if element == self.target {
-            // SUCCESS
-            self.chain_length += 1;
-            self.multiplier += 1;
-            
-            // Avatar State Check (Chain >= 5)
-            if self.chain_length >= 5 {
-                self.is_avatar_state = true;
-            }
-
-            // Advance Target: Water(3) -> Wood(2) -> Fire(4) -> Earth(5) -> Metal(1) -> Water(3)
-            self.target = match self.target {
-                3 => 2, // Water -> Wood
-                2 => 4, // Wood -> Fire
-                4 => 5, // Fire -> Earth
-                5 => 1, // Earth -> Metal
-                1 => 3, // Metal -> Water
-                _ => 3, // Fallback
-            };
-            
-            return (true, self.multiplier);
-        } else {
-            // BROKEN
-            self.reset();
-            return (false, 1);
-        }
-    }
-    
-    pub fn reset(&mut self) {
-        self.chain_length = 0;
-        self.multiplier = 1;
-        self.target = 3; // Reset to Water
-        self.is_avatar_state = false;
-    }
-    
-    pub fn get_chain_length(&self) -> u32 {
-        self.chain_length
-    }
-    
-    pub fn is_avatar(&self) -> bool {
-        self.is_avatar_state
-    }
-}
@@ -0,0 +1,44 @@
+// --- HAZARD SCHEDULER ---
+// Boss / level lên lịch hiệu ứng (FROZEN, LOCKED, BURNING...) lên 1 ô sau N lượt.
+// 1 lượt = 1 swap được chấp nhận. Hiệu ứng đến hạn được áp ngay khi lượt đó bắt đầu,
+// nên preview nhìn thấy trước đúng những gì sẽ xảy ra sau nước đi tiếp theo.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduledHazard {
+    pub idx: usize,
+    pub flag: u8,
+    pub turn: u32, // Lượt (tuyệt đối) mà hiệu ứng được áp
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HazardScheduler {
+    pending: Vec<ScheduledHazard>, // Giữ thứ tự lên lịch -> áp theo thứ tự tất định
+}
+
+impl HazardScheduler {
+    pub fn schedule(&mut self, hazard: ScheduledHazard) {
+        self.pending.push(hazard);
+    }
+
+    // Lấy ra các hiệu ứng đến hạn ở lượt `turn`
+    pub fn take_due(&mut self, turn: u32) -> Vec<ScheduledHazard> {
+        let (due, rest) = self.pending.iter().partition(|h| h.turn <= turn);
+        self.pending = rest;
+        due
+    }
+
+    // Các hiệu ứng sẽ áp trong `window` lượt tới (current_turn + 1 ..= current_turn + window)
+    pub fn upcoming(&self, current_turn: u32, window: u32) -> impl Iterator<Item = &ScheduledHazard> {
+        self.pending
+            .iter()
+            .filter(move |h| h.turn > current_turn && h.turn - current_turn <= window)
+    }
+
+    pub fn pending(&self) -> &[ScheduledHazard] {
+        &self.pending
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use super::grid::{AreaShape, ElementType, InteractionKind, InteractionRule, INTERACTION_RULES};
+
+// --- LEVEL OBSTACLE VALIDATOR ---
+// Kiểm tra layout vật cản (Stone) lúc load level, trước khi người chơi thấy board.
+// Luật hiện tại của core:
+// - Trọng lực hướng xuống, Stone đứng yên và chặn tile rơi qua.
+// - Refill chỉ đổ vào đoạn cột phía trên Stone cao nhất.
+// - Stone chỉ bị phá bởi luật Destruction dạng vùng (Cross / Row / Area3x3) có tâm match
+//   đúng hàng / cột / cạnh nó; Stone không bao giờ là neighbor kích hoạt luật.
+//   Special / Sunder không tính (không đảm bảo có), Stone không phá được coi là vĩnh viễn.
+// Layout: 1 byte / ô, ElementType::Stone = vật cản, giá trị khác = ô chơi được.
+
+pub const MIN_MATCH_RUN: usize = 3;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutIssue {
+    BadSize = 1,     // Layout không khớp width * height
+    DeadCell = 2,    // Ô chơi được nhưng không bao giờ nằm trong 1 hàng / cột đủ 3 ô -> không clear được
+    NoRefill = 3,    // Mọi cột đều bị chặn ở hàng trên cùng -> board cạn dần, không có tile mới
+    Unreachable = 4, // Ô dưới Stone không phá được: clear xong thì refill không bao giờ tới
+}
+
+fn is_obstacle(element: u8) -> bool {
+    element == ElementType::Stone as u8
+}
+
+// Độ dài đoạn ô không bị chặn liên tiếp chứa `idx` trên hàng (horizontal) hoặc cột của nó
+fn open_run(solid: &[bool], width: usize, height: usize, idx: usize, horizontal: bool) -> usize {
+    let (x, y) = (idx % width, idx / width);
+    let (pos, limit) = if horizontal { (x, width) } else { (y, height) };
+    let at = |p: usize| if horizontal { y * width + p } else { p * width + x };
+    let before = (0..pos).rev().take_while(|&p| !solid[at(p)]).count();
+    let after = (pos + 1..limit).take_while(|&p| !solid[at(p)]).count();
+    before + 1 + after
+}
+
+// Vùng của luật có chạm `stone` khi tâm match ở `center` không
+fn shape_hits(shape: AreaShape, center: (usize, usize), stone: (usize, usize)) -> bool {
+    match shape {
+        AreaShape::Cross => center.0 == stone.0 || center.1 == stone.1,
+        AreaShape::Row => center.1 == stone.1,
+        AreaShape::Area3x3 => center.0.abs_diff(stone.0) <= 1 && center.1.abs_diff(stone.1) <= 1,
+        // Chỉ chạm neighbor / ô match, Stone không thuộc 2 loại này
+        AreaShape::TargetNeighbors | AreaShape::OtherNeighbors | AreaShape::MatchCells | AreaShape::Center => false,
+    }
+}
+
+// Returns các vấn đề (idx, issue) theo thứ tự index, rỗng = layout hợp lệ.
+// NoRefill / BadSize không gắn với ô cụ thể -> idx = 0.
+pub fn validate_obstacles(width: usize, height: usize, layout: &[u8]) -> Vec<(usize, LayoutIssue)> {
+    validate_obstacles_with_rules(width, height, layout, INTERACTION_RULES)
+}
+
+// Như validate_obstacles nhưng theo bảng luật `rules` (tool thử bảng luật mới)
+pub fn validate_obstacles_with_rules(width: usize, height: usize, layout: &[u8], rules: &[InteractionRule]) -> Vec<(usize, LayoutIssue)> {
+    if width == 0 || height == 0 || layout.len() != width * height {
+        return vec![(0, LayoutIssue::BadSize)];
+    }
+    let original: Vec<bool> = layout.iter().map(|&e| is_obstacle(e)).collect();
+    let alive = |solid: &[bool], idx: usize| {
+        !solid[idx] && open_run(solid, width, height, idx, true).max(open_run(solid, width, height, idx, false)) >= MIN_MATCH_RUN
+    };
+
+    // Phá dần Stone: ô match được (kể cả tile ban đầu trong túi) làm tâm cho luật vùng,
+    // Stone vừa phá mở thêm đoạn hàng / cột -> lặp tới khi không phá thêm được
+    let shapes: Vec<AreaShape> = rules.iter().filter(|r| r.kind == InteractionKind::Destruction).map(|r| r.shape).collect();
+    let mut solid = original.clone();
+    loop {
+        let centers: Vec<(usize, usize)> = (0..solid.len()).filter(|&i| alive(&solid, i)).map(|i| (i % width, i / width)).collect();
+        let broken: Vec<usize> = (0..solid.len())
+            .filter(|&i| solid[i])
+            .filter(|&i| centers.iter().any(|&c| shapes.iter().any(|&shape| shape_hits(shape, c, (i % width, i / width)))))
+            .collect();
+        if broken.is_empty() { break; }
+        for i in broken { solid[i] = false; }
+    }
+
+    let mut issues = Vec::new();
+    for idx in 0..layout.len() {
+        if original[idx] { continue; }
+        if !alive(&original, idx) {
+            issues.push((idx, LayoutIssue::DeadCell));
+        } else if (0..idx / width).any(|y| solid[y * width + idx % width]) {
+            issues.push((idx, LayoutIssue::Unreachable));
+        }
+    }
+    if (0..width).all(|x| original[x]) {
+        issues.push((0, LayoutIssue::NoRefill));
+    }
+    issues
+}
+
+// Cho tool thiết kế level. Returns [idx, issue, idx, issue, ...], rỗng = hợp lệ
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn validate_level_layout(width: usize, height: usize, layout: &[u8]) -> Vec<u32> {
+    validate_obstacles(width, height, layout)
+        .into_iter()
+        .flat_map(|(idx, issue)| [idx as u32, issue as u32])
+        .collect()
+}
@@ -1,6 +1,27 @@
 pub mod simulation;
 pub mod systems;
 pub mod grid;
+pub mod cycle;
+pub mod scoring;
+pub mod events;
+pub mod audio;
+pub mod experiment;
+pub mod coords;
+pub mod hazard;
+pub mod spawner;
+pub mod seal;
+pub mod snapshot;
+pub mod photo;
+pub mod level;
+pub mod drought;
+pub mod noise;
+pub mod stats;
+pub mod xp;
+pub mod replay;
+pub mod diff;
+pub mod moves;
 #[cfg(test)]
 mod grid_test;
+#[cfg(test)]
+pub(crate) mod alloc_audit;
 pub mod rng;
@@ -0,0 +1,106 @@
+use super::grid::JOURNAL_OP_MARKER;
+
+// --- COMPACT MOVE ENCODING ---
+// Journal thô tốn 4 byte / nước. Bản nén: mỗi nước 1 token varint (LEB128), bit thấp là tag:
+//   tag 0: swap kề nhau, token >> 1 = zigzag(cell - cell nước trước) * 4 + hướng -> 1-2 byte
+//   tag 1: token >> 1 = MOVE_OP_BOOSTER + 1 byte op, hoặc MOVE_OP_RAW + 4 byte record thô
+// Delta theo ô của nước trước nên nước gần nhau gần như luôn 1 byte.
+// Dùng chung cho journal, validate_replay_compact và lớp network sau này.
+
+pub const MOVE_OP_BOOSTER: u32 = 0;
+pub const MOVE_OP_RAW: u32 = 1; // Swap không kề / ngoài board: giữ nguyên để replay từ chối y hệt
+
+// Hướng từ ô đầu tới ô thứ 2, giữ đúng thứ tự [x1, y1, x2, y2] (idx1 ảnh hưởng anti-farming)
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 { return Some(value); }
+    }
+    None
+}
+
+fn zigzag(delta: i64) -> u64 {
+    ((delta << 1) ^ (delta >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+// moves: format journal (4 byte / nước), phần lẻ cuối bị bỏ như chunks_exact ở replay
+pub fn encode_moves(width: usize, moves: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(moves.len() / 2);
+    let mut prev_cell = 0i64;
+    for record in moves.chunks_exact(4) {
+        if record[0] == JOURNAL_OP_MARKER && record[1] == JOURNAL_OP_MARKER && record[3] == 0 {
+            write_varint(&mut out, ((MOVE_OP_BOOSTER as u64) << 1) | 1);
+            out.push(record[2]);
+            continue;
+        }
+        let (x1, y1, x2, y2) = (record[0] as i32, record[1] as i32, record[2] as i32, record[3] as i32);
+        let dir = DIRECTIONS.iter().position(|&d| d == (x2 - x1, y2 - y1));
+        match dir {
+            Some(dir) if (x1 as usize) < width && (x2 as usize) < width && y2 >= 0 => {
+                let cell = y1 as i64 * width as i64 + x1 as i64;
+                write_varint(&mut out, (zigzag(cell - prev_cell) * 4 + dir as u64) << 1);
+                prev_cell = cell;
+            }
+            _ => {
+                write_varint(&mut out, ((MOVE_OP_RAW as u64) << 1) | 1);
+                out.extend_from_slice(record);
+            }
+        }
+    }
+    out
+}
+
+// Ngược lại encode_moves. None = dữ liệu hỏng / không khớp width.
+pub fn decode_moves(width: usize, data: &[u8]) -> Option<Vec<u8>> {
+    if width == 0 { return None; }
+    let mut out = Vec::with_capacity(data.len() * 4);
+    let mut prev_cell = 0i64;
+    let mut pos = 0;
+    while pos < data.len() {
+        let token = read_varint(data, &mut pos)?;
+        if token & 1 == 1 {
+            match u32::try_from(token >> 1).ok()? {
+                MOVE_OP_BOOSTER => {
+                    let op = *data.get(pos)?;
+                    pos += 1;
+                    out.extend_from_slice(&[JOURNAL_OP_MARKER, JOURNAL_OP_MARKER, op, 0]);
+                }
+                MOVE_OP_RAW => {
+                    out.extend_from_slice(data.get(pos..pos + 4)?);
+                    pos += 4;
+                }
+                _ => return None,
+            }
+            continue;
+        }
+        let value = token >> 1;
+        let cell = prev_cell.checked_add(unzigzag(value / 4))?;
+        if cell < 0 { return None; }
+        if cell / width as i64 > 255 { return None; }
+        let (dx, dy) = DIRECTIONS[(value % 4) as usize];
+        let (x1, y1) = ((cell % width as i64) as i32, (cell / width as i64) as i32);
+        let (x2, y2) = (x1 + dx, y1 + dy);
+        let coords = [x1, y1, x2, y2];
+        if x2 as usize >= width || coords.iter().any(|&c| !(0..=255).contains(&c)) { return None; }
+        out.extend(coords.iter().map(|&c| c as u8));
+        prev_cell = cell;
+    }
+    Some(out)
+}
@@ -0,0 +1,64 @@
+// --- NOISE BOARD SEEDING ---
+// Board đầu level theo cụm tự nhiên (vd 1 "dòng sông" Water vắt ngang) thay vì random đều.
+// Mỗi element có 1 trường value noise riêng; ô thuộc element có noise cao nhất tại đó
+// -> vùng liền mạch, 5 element cân bằng do đối xứng.
+// Số nguyên fixed-point (16 bit) để native / wasm ra cùng board với cùng seed.
+
+pub const NOISE_ONE: u32 = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardNoise {
+    pub scale: u32,       // Số ô giữa 2 điểm lưới noise, lớn = cụm to (0 = tắt)
+    pub cluster_pct: u32, // % ô lấy theo noise, phần còn lại random đều (0 = tắt)
+}
+
+impl BoardNoise {
+    pub const OFF: BoardNoise = BoardNoise { scale: 0, cluster_pct: 0 };
+
+    pub fn is_off(&self) -> bool {
+        self.scale == 0 || self.cluster_pct == 0
+    }
+}
+
+// Giá trị ngẫu nhiên 0..NOISE_ONE tại điểm lưới (SplitMix64 finalizer)
+fn lattice(seed: u64, layer: u32, x: u32, y: u32) -> u32 {
+    let mut z = seed
+        ^ (layer as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (x as u64).wrapping_mul(0xBF58_476D_1CE4_E5B9)
+        ^ (y as u64).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    ((z ^ (z >> 31)) & (NOISE_ONE as u64 - 1)) as u32
+}
+
+// Smoothstep 3t² - 2t³ trên t ∈ [0, NOISE_ONE]
+fn smooth(t: u32) -> u32 {
+    let t = t as u64;
+    let one = NOISE_ONE as u64;
+    (t * t * (3 * one - 2 * t) / (one * one)) as u32
+}
+
+fn lerp(a: u32, b: u32, t: u32) -> u32 {
+    ((a as u64 * (NOISE_ONE - t) as u64 + b as u64 * t as u64) >> 16) as u32
+}
+
+// Value noise tại ô (x, y), 0..NOISE_ONE
+pub fn value_noise(seed: u64, layer: u32, x: u32, y: u32, scale: u32) -> u32 {
+    let scale = scale.max(1);
+    let (gx, gy) = (x / scale, y / scale);
+    let tx = smooth((x % scale) * NOISE_ONE / scale);
+    let ty = smooth((y % scale) * NOISE_ONE / scale);
+    let top = lerp(lattice(seed, layer, gx, gy), lattice(seed, layer, gx + 1, gy), tx);
+    let bottom = lerp(lattice(seed, layer, gx, gy + 1), lattice(seed, layer, gx + 1, gy + 1), tx);
+    lerp(top, bottom, ty)
+}
+
+// Element 1..=count có noise cao nhất tại ô (x, y). 2 octave (scale, scale / 2) cho biên cụm đỡ tròn.
+pub fn noise_element(seed: u64, x: u32, y: u32, scale: u32, count: u8) -> u8 {
+    (1..=count)
+        .max_by_key(|&e| {
+            let layer = e as u32;
+            2 * value_noise(seed, layer, x, y, scale) + value_noise(seed, layer + 16, x, y, scale / 2)
+        })
+        .unwrap_or(1)
+}
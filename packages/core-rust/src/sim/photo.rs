@@ -0,0 +1,60 @@
+use serde::Serialize;
+use crate::ecs::components::Position;
+use super::events::GridEvent;
+use super::grid::GridState;
+
+// --- PHOTO MODE ---
+// Ảnh chụp toàn bộ trạng thái render của 1 frame cho tính năng share screenshot.
+// Renderer vẽ lại ở độ phân giải cao từ đây, không đụng tới gameplay.
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PhotoEntity {
+    pub id: u64,
+    pub x: f32,
+    pub y: f32,
+    pub archetype: u8, // 0 = không phải entity hiệu ứng
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PhotoState {
+    pub width: usize,
+    pub height: usize,
+    pub frame: u32,
+    pub cells: Vec<u16>, // get_cell_full: [flags(8) | element(8)]
+    pub score: u64,
+    pub cycle_target: u8,
+    pub cycle_chain: u32,
+    pub cycle_multiplier: u32,
+    pub avatar: bool,
+    pub dual_cycle_target: u8, // 0 = không bật dual
+    // Phát lại event của frame hiện tại đầy đủ chi tiết: bỏ qua mask / capacity / clear_events
+    pub events: Vec<GridEvent>,
+    pub entities: Vec<PhotoEntity>,
+    pub alpha: f64, // Hệ số nội suy của frame bị đóng băng
+}
+
+impl PhotoState {
+    pub fn capture(grid: &GridState, ids: &[u64], positions: &[Position], archetypes: &[u8], alpha: f64) -> Self {
+        let entities = ids
+            .iter()
+            .zip(positions)
+            .zip(archetypes)
+            .map(|((&id, p), &archetype)| PhotoEntity { id, x: p.x, y: p.y, archetype })
+            .collect();
+        Self {
+            width: grid.get_width(),
+            height: grid.get_height(),
+            frame: grid.get_tick_count(),
+            cells: (0..grid.get_cells_len()).map(|i| grid.get_cell_full(i)).collect(),
+            score: grid.score_u64(),
+            cycle_target: grid.get_cycle_target(),
+            cycle_chain: grid.get_cycle_chain(),
+            cycle_multiplier: grid.get_cycle_multiplier(),
+            avatar: grid.is_avatar_state(),
+            dual_cycle_target: grid.get_dual_cycle_target(),
+            events: grid.latest_frame_events().to_vec(),
+            entities,
+            alpha,
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use super::grid::{Cell, GridState, ResolutionPolicy, JOURNAL_OP_MARKER, JOURNAL_OP_PLAYER, JOURNAL_OP_SKIP_CYCLE, JOURNAL_OP_SWITCH_TRACK};
+use super::drought::DroughtPity;
+use super::noise::BoardNoise;
+use super::scoring::ScoreDecay;
+use super::stats::GameSummary;
+use super::xp::{XpCurve, XpRewards};
+
+// --- REPLAY ---
+// Replay chi tiết từng nước cho anti-cheat: server tìm ra nước đầu tiên client lệch
+// thay vì chỉ so điểm cuối. ReplayPlayer cho replay viewer / spectator đi từng nước.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ReplayStep {
+    pub checksum: u32,    // get_checksum() sau khi board ổn định
+    pub score_delta: u64, // Điểm nước này mang lại (kể cả cascade)
+    pub accepted: bool,   // false = swap / booster bị từ chối, board không đổi
+}
+
+// Chạy moves ([x1, y1, x2, y2] hoặc booster [FF, FF, op, 0]) trên grid, 1 ReplayStep mỗi record
+pub fn replay_steps(grid: &mut GridState, moves: &[u8]) -> Vec<ReplayStep> {
+    moves
+        .chunks_exact(4)
+        .map(|record| {
+            let before = grid.score_u64();
+            let accepted = grid.replay_record(record);
+            ReplayStep { checksum: grid.get_checksum(), score_delta: grid.score_u64() - before, accepted }
+        })
+        .collect()
+}
+
+// Index nước đầu tiên có checksum khác `expected` (None = khớp hết phần chung)
+pub fn first_divergence(steps: &[ReplayStep], expected: &[u32]) -> Option<usize> {
+    steps.iter().zip(expected).position(|(s, &c)| s.checksum != c)
+}
+
+// --- REPLAY FILE ---
+// Container gửi lên validation service: header cố định + journal (4 byte / nước), little-endian.
+//   MAGIC(4) | format u16 | core version u32 | width u16 | height u16 | seed u64 | flags u32
+//   | skip charges u32 | rules hash u64 | move count u32 | final checksum u32 | final score u64
+//   | bucket (u8 có / không + u32) | ruleset (ReplayRules) | moves
+// Header sai / khác core version / khác luật bị loại trước khi simulate.
+// Ruleset mang mọi config nằm trong rules hash nên ván với config bất kỳ đều dựng lại được.
+
+pub const REPLAY_MAGIC: &[u8; 4] = b"LIRP";
+pub const REPLAY_FORMAT_VERSION: u16 = 3; // v2: thêm ruleset, v3: thêm A/B bucket
+pub const REPLAY_HEADER_SIZE: usize = 59 + REPLAY_RULES_SIZE; // Không tính danh sách người chơi
+pub(crate) const REPLAY_RULES_SIZE: usize = 75;
+
+pub const REPLAY_FLAG_DESTRUCTION: u32 = 1; // CycleMode::Destruction
+pub const REPLAY_FLAG_DUAL_CYCLE: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ReplayError {
+    BadHeader,
+    UnsupportedFormat,
+    CoreVersionMismatch,
+    Truncated,           // Số byte moves khác move count
+    RulesMismatch,       // Rules hash trong header khác ván dựng từ header
+    ChecksumMismatch,    // Simulate xong ra board khác
+    ScoreMismatch,
+    Rejected(ReplayRejection), // Không qua check_replay_moves, chưa simulate
+}
+
+// Config của ván ngoài flags / skip charges, cùng các field GameConfig đã nằm trong rules hash.
+//   policy u8 | auto refill u8 | grace u32 | momentum u32 | modifier u32 | decay 3 x u32 | pity 3 x u32
+//   | noise 2 x u32 | xp curve 3 x u32 | xp rewards 3 x u32 | turn window u32 | player count u8 | (id u32, role u32) / người
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayRules {
+    pub resolution_policy: ResolutionPolicy,
+    pub auto_refill: bool,
+    pub cycle_grace: u32,
+    pub momentum_decay_turns: u32,
+    pub score_modifier_pct: u32,
+    pub score_decay: ScoreDecay,
+    pub drought_pity: DroughtPity,
+    pub board_noise: BoardNoise,
+    pub xp_curve: XpCurve,
+    pub xp_rewards: XpRewards,
+    pub turn_window: u32,        // Co-op: số swap mỗi lượt (0 = không luân phiên)
+    pub players: Vec<(u32, u32)>, // (id, role mask) theo thứ tự add_player, tối đa 255
+}
+
+impl ReplayRules {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.resolution_policy as u8);
+        out.push(self.auto_refill as u8);
+        let (d, p, n, c, x) = (&self.score_decay, &self.drought_pity, &self.board_noise, &self.xp_curve, &self.xp_rewards);
+        for v in [
+            self.cycle_grace, self.momentum_decay_turns, self.score_modifier_pct,
+            d.element_step_pct, d.region_step_pct, d.floor_pct,
+            p.threshold, p.step_pct, p.max_boost_pct,
+            n.scale, n.cluster_pct,
+            c.base, c.growth_pct, c.max_level,
+            x.per_match, x.per_extra_cell, x.per_interaction,
+            self.turn_window,
+        ] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.push(self.players.len().min(u8::MAX as usize) as u8);
+        for &(id, role) in self.players.iter().take(u8::MAX as usize) {
+            out.extend_from_slice(&id.to_le_bytes());
+            out.extend_from_slice(&role.to_le_bytes());
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayHeader {
+    pub core_version: u32, // (major << 16) | (minor << 8) | patch của crate ghi file
+    pub width: u16,
+    pub height: u16,
+    pub seed: u64,
+    pub flags: u32,
+    pub skip_charges: u32, // Charge booster lúc bắt đầu ván
+    pub rules_hash: u64,
+    pub move_count: u32,
+    pub final_checksum: u32,
+    pub final_score: u64,
+    pub bucket: Option<u32>, // A/B bucket của ván (None = không tham gia)
+    pub rules: ReplayRules,
+}
+
+impl ReplayHeader {
+    pub const CORE_VERSION: u32 = core_version();
+
+    pub fn encode(&self, moves: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(REPLAY_HEADER_SIZE + moves.len());
+        out.extend_from_slice(REPLAY_MAGIC);
+        out.extend_from_slice(&REPLAY_FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.core_version.to_le_bytes());
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        out.extend_from_slice(&self.skip_charges.to_le_bytes());
+        out.extend_from_slice(&self.rules_hash.to_le_bytes());
+        out.extend_from_slice(&self.move_count.to_le_bytes());
+        out.extend_from_slice(&self.final_checksum.to_le_bytes());
+        out.extend_from_slice(&self.final_score.to_le_bytes());
+        out.push(self.bucket.is_some() as u8);
+        out.extend_from_slice(&self.bucket.unwrap_or(0).to_le_bytes());
+        self.rules.encode(&mut out);
+        out.extend_from_slice(moves);
+        out
+    }
+
+    // Tách header và phần moves, kiểm tra magic / format / core version / độ dài
+    pub fn parse(data: &[u8]) -> Result<(ReplayHeader, &[u8]), ReplayError> {
+        if data.len() < REPLAY_HEADER_SIZE || &data[..4] != REPLAY_MAGIC { return Err(ReplayError::BadHeader); }
+        let mut pos = 4;
+        let mut take = |n: usize| {
+            let field = data.get(pos..pos + n).ok_or(ReplayError::BadHeader);
+            pos += n;
+            field
+        };
+        let u16_at = |b: &[u8]| u16::from_le_bytes([b[0], b[1]]);
+        let u32_at = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap());
+        let u64_at = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap());
+        if u16_at(take(2)?) != REPLAY_FORMAT_VERSION { return Err(ReplayError::UnsupportedFormat); }
+        let core_version = u32_at(take(4)?);
+        if core_version != ReplayHeader::CORE_VERSION { return Err(ReplayError::CoreVersionMismatch); }
+        let (width, height, seed) = (u16_at(take(2)?), u16_at(take(2)?), u64_at(take(8)?));
+        let (flags, skip_charges, rules_hash) = (u32_at(take(4)?), u32_at(take(4)?), u64_at(take(8)?));
+        let (move_count, final_checksum, final_score) = (u32_at(take(4)?), u32_at(take(4)?), u64_at(take(8)?));
+        let has_bucket = take(1)?[0];
+        let bucket = u32_at(take(4)?);
+        let bucket = match has_bucket {
+            0 => None,
+            1 => Some(bucket),
+            _ => return Err(ReplayError::BadHeader),
+        };
+
+        let resolution_policy = ResolutionPolicy::from_id(take(1)?[0]).ok_or(ReplayError::BadHeader)?;
+        let auto_refill = match take(1)?[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ReplayError::BadHeader),
+        };
+        let mut v = [0u32; 18];
+        for slot in v.iter_mut() {
+            *slot = u32_at(take(4)?);
+        }
+        let mut players = Vec::new();
+        for _ in 0..take(1)?[0] {
+            players.push((u32_at(take(4)?), u32_at(take(4)?)));
+        }
+        let rules = ReplayRules {
+            resolution_policy,
+            auto_refill,
+            cycle_grace: v[0],
+            momentum_decay_turns: v[1],
+            score_modifier_pct: v[2],
+            score_decay: ScoreDecay { element_step_pct: v[3], region_step_pct: v[4], floor_pct: v[5] },
+            drought_pity: DroughtPity { threshold: v[6], step_pct: v[7], max_boost_pct: v[8] },
+            board_noise: BoardNoise { scale: v[9], cluster_pct: v[10] },
+            xp_curve: XpCurve { base: v[11], growth_pct: v[12], max_level: v[13] },
+            xp_rewards: XpRewards { per_match: v[14], per_extra_cell: v[15], per_interaction: v[16] },
+            turn_window: v[17],
+            players,
+        };
+        let header = ReplayHeader { core_version, width, height, seed, flags, skip_charges, rules_hash, move_count, final_checksum, final_score, bucket, rules };
+        let moves = &data[pos..];
+        if moves.len() as u64 != header.move_count as u64 * 4 { return Err(ReplayError::Truncated); }
+        Ok((header, moves))
+    }
+}
+
+const fn parse_version_part(s: &str) -> u32 {
+    let bytes = s.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    value
+}
+
+const fn core_version() -> u32 {
+    (parse_version_part(env!("CARGO_PKG_VERSION_MAJOR")) << 16)
+        | (parse_version_part(env!("CARGO_PKG_VERSION_MINOR")) << 8)
+        | parse_version_part(env!("CARGO_PKG_VERSION_PATCH"))
+}
+
+// Kết quả validate_replay_file cho JS: { score, summary } hoặc { error }.
+// summary là GameSummary của ván server vừa replay, số 64 bit ra BigInt.
+#[cfg(feature = "wasm")]
+#[derive(Serialize)]
+pub struct ReplayVerdict {
+    pub score: Option<f64>,
+    pub summary: Option<GameSummary>,
+    pub error: Option<ReplayError>,
+}
+
+#[cfg(feature = "wasm")]
+impl ReplayVerdict {
+    pub fn to_js(result: Result<GameSummary, ReplayError>) -> JsValue {
+        let verdict = match result {
+            Ok(summary) => ReplayVerdict { score: Some(summary.score as f64), summary: Some(summary), error: None },
+            Err(e) => ReplayVerdict { score: None, summary: None, error: Some(e) },
+        };
+        let serializer = serde_wasm_bindgen::Serializer::new().serialize_large_number_types_as_bigints(true);
+        verdict.serialize(&serializer).unwrap_or(JsValue::UNDEFINED)
+    }
+}
+
+// Kiểm tra header + check_replay_moves (ReplayLimits::DEFAULT), dựng ván theo header rồi replay.
+// Ok = điểm đã xác minh. Journal giả mạo quá dài / sai toạ độ bị loại trước khi simulate.
+pub fn verify_replay_file(data: &[u8]) -> Result<u64, ReplayError> {
+    verify_replay_summary(data).map(|summary| summary.score)
+}
+
+// Như verify_replay_file, kèm kết quả đầy đủ của ván đã replay (XP, breakdown, bucket...).
+// Validator duy nhất cho mọi config: luật nằm trong header nên không cần tham số theo từng tính năng.
+pub fn verify_replay_summary(data: &[u8]) -> Result<GameSummary, ReplayError> {
+    let (header, moves) = ReplayHeader::parse(data)?;
+    if header.width == 0 || header.height == 0 || header.width > 255 || header.height > 255 { return Err(ReplayError::BadHeader); }
+    // parse đã khớp move count với độ dài journal, check_replay_moves chặn số nước trước khi quét
+    check_replay_moves(header.width as usize, header.height as usize, moves, &[], &ReplayLimits::DEFAULT).map_err(ReplayError::Rejected)?;
+    let mut grid = GridState::from_replay_header(&header);
+    if grid.get_rules_hash() != header.rules_hash { return Err(ReplayError::RulesMismatch); }
+    for record in moves.chunks_exact(4) {
+        grid.replay_record(record);
+    }
+    if grid.get_checksum() != header.final_checksum { return Err(ReplayError::ChecksumMismatch); }
+    if grid.score_u64() != header.final_score { return Err(ReplayError::ScoreMismatch); }
+    Ok(grid.game_summary())
+}
+
+// --- REPLAY SANITY ---
+// Kiểm tra rẻ trước khi simulate: replay giả mạo thô (quá dài, ô ngoài board, swap không kề,
+// spam input nhanh hơn người chơi được) bị loại mà không tốn 1000 tick / nước.
+
+pub const MAX_REPLAY_MOVES: u32 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ReplayLimits {
+    pub max_moves: u32,
+    pub require_adjacent: bool, // Core không chặn swap xa, client chỉ cho swap 4 hướng
+    pub min_tick_gap: u32,      // Khoảng cách tối thiểu giữa 2 nước (0 = không kiểm tra)
+}
+
+impl ReplayLimits {
+    pub const DEFAULT: ReplayLimits = ReplayLimits { max_moves: MAX_REPLAY_MOVES, require_adjacent: true, min_tick_gap: 0 };
+}
+
+impl Default for ReplayLimits {
+    fn default() -> Self {
+        ReplayLimits::DEFAULT
+    }
+}
+
+// index = thứ tự nước trong moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ReplayRejection {
+    Misaligned, // Độ dài moves không chia hết cho 4
+    TooManyMoves { count: u32 },
+    OutOfBounds { index: u32 },
+    NotAdjacent { index: u32 },
+    UnknownBooster { index: u32 },
+    TickCountMismatch, // move_ticks khác số nước
+    TooFast { index: u32 }, // Cách nước trước < min_tick_gap (hoặc tick đi lùi)
+}
+
+// move_ticks: tick client ghi lúc thực hiện từng nước (get_tick_count), rỗng = bỏ qua kiểm tra nhịp
+pub fn check_replay_moves(width: usize, height: usize, moves: &[u8], move_ticks: &[u32], limits: &ReplayLimits) -> Result<(), ReplayRejection> {
+    if !moves.len().is_multiple_of(4) { return Err(ReplayRejection::Misaligned); }
+    let count = moves.len() / 4;
+    if count > limits.max_moves as usize { return Err(ReplayRejection::TooManyMoves { count: count.min(u32::MAX as usize) as u32 }); }
+
+    for (i, record) in moves.chunks_exact(4).enumerate() {
+        let index = i as u32;
+        if record[0] == JOURNAL_OP_MARKER && record[1] == JOURNAL_OP_MARKER {
+            if ![JOURNAL_OP_SKIP_CYCLE, JOURNAL_OP_SWITCH_TRACK, JOURNAL_OP_PLAYER].contains(&record[2]) { return Err(ReplayRejection::UnknownBooster { index }); }
+            continue;
+        }
+        let (x1, y1, x2, y2) = (record[0] as usize, record[1] as usize, record[2] as usize, record[3] as usize);
+        if x1 >= width || x2 >= width || y1 >= height || y2 >= height {
+            return Err(ReplayRejection::OutOfBounds { index });
+        }
+        if limits.require_adjacent && x1.abs_diff(x2) + y1.abs_diff(y2) != 1 {
+            return Err(ReplayRejection::NotAdjacent { index });
+        }
+    }
+
+    if move_ticks.is_empty() { return Ok(()); }
+    if move_ticks.len() != count { return Err(ReplayRejection::TickCountMismatch); }
+    match move_ticks.windows(2).position(|w| w[1] < w[0].saturating_add(limits.min_tick_gap)) {
+        Some(i) => Err(ReplayRejection::TooFast { index: i as u32 + 1 }),
+        None => Ok(()),
+    }
+}
+
+// check_replay_moves rồi replay trên `grid` (ván mới đúng cấu hình). Ok = điểm cuối.
+pub fn replay_checked(grid: &mut GridState, moves: &[u8], move_ticks: &[u32], limits: &ReplayLimits) -> Result<u64, ReplayError> {
+    check_replay_moves(grid.get_width(), grid.get_height(), moves, move_ticks, limits).map_err(ReplayError::Rejected)?;
+    for record in moves.chunks_exact(4) {
+        grid.replay_record(record);
+    }
+    Ok(grid.score_u64())
+}
+
+// --- REPLAY PLAYER ---
+// GridState + danh sách nước. step() chơi 1 nước (tới khi board ổn định), seek() nhảy tới nước bất kỳ:
+// lùi thì dựng lại từ state đầu rồi chạy tới. Event của step nằm trong buffer như khi chơi thật.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct ReplayPlayer {
+    start: Vec<u8>, // Snapshot state trước nước đầu tiên
+    grid: GridState,
+    moves: Vec<u8>,
+    cursor: usize, // Số nước đã chơi
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ReplayPlayer {
+    // moves: cùng format validate_replay / get_move_journal
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(width: usize, height: usize, seed: u64, moves: Vec<u8>) -> ReplayPlayer {
+        ReplayPlayer::from_grid(GridState::new(width, height, seed), moves)
+    }
+
+    // Replay bắt đầu từ snapshot (vd autosave giữa ván), undefined nếu snapshot hỏng
+    pub fn from_snapshot(snapshot: &[u8], moves: Vec<u8>) -> Option<ReplayPlayer> {
+        GridState::from_snapshot(snapshot).ok().map(|grid| ReplayPlayer::from_grid(grid, moves))
+    }
+
+    // Chơi nước kế tiếp. Returns false nếu đã hết nước.
+    pub fn step(&mut self) -> bool {
+        let Some(record) = self.moves.get(self.cursor * 4..self.cursor * 4 + 4) else { return false };
+        self.grid.replay_record(record);
+        self.cursor += 1;
+        true
+    }
+
+    // Đưa board về trạng thái sau `move_index` nước, xoá event (viewer vẽ lại từ cells).
+    // Returns false nếu vượt quá số nước.
+    pub fn seek(&mut self, move_index: usize) -> bool {
+        if move_index > self.get_move_count() { return false; }
+        if move_index < self.cursor {
+            // Snapshot do chính GridState ghi -> luôn đọc lại được
+            self.grid = GridState::from_snapshot(&self.start).expect("replay start snapshot");
+            self.cursor = 0;
+        }
+        while self.cursor < move_index {
+            self.step();
+        }
+        self.grid.clear_events();
+        self.grid.clear_audio_cues();
+        true
+    }
+
+    pub fn get_move_index(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn get_move_count(&self) -> usize {
+        self.moves.len() / 4
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.get_move_count()
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.grid.get_score()
+    }
+
+    pub fn get_checksum(&self) -> u32 {
+        self.grid.get_checksum()
+    }
+
+    // Buffer live của GridState bên trong (xem GridState::get_cells_ptr / get_events_ptr...)
+    pub fn get_width(&self) -> usize { self.grid.get_width() }
+    pub fn get_height(&self) -> usize { self.grid.get_height() }
+    pub fn get_cells_ptr(&self) -> *const Cell { self.grid.get_cells_ptr() }
+    pub fn get_cells_len(&self) -> usize { self.grid.get_cells_len() }
+    pub fn get_events_ptr(&mut self) -> *const u32 { self.grid.get_events_ptr() }
+    pub fn get_events_len(&self) -> usize { self.grid.get_events_len() }
+    pub fn get_event_types_ptr(&mut self) -> *const u8 { self.grid.get_event_types_ptr() }
+    pub fn get_event_xs_ptr(&mut self) -> *const u16 { self.grid.get_event_xs_ptr() }
+    pub fn get_event_ys_ptr(&mut self) -> *const u16 { self.grid.get_event_ys_ptr() }
+    pub fn get_event_intensities_ptr(&mut self) -> *const u8 { self.grid.get_event_intensities_ptr() }
+    pub fn get_event_payloads_ptr(&mut self) -> *const u32 { self.grid.get_event_payloads_ptr() }
+    pub fn get_event_frames_ptr(&mut self) -> *const u32 { self.grid.get_event_frames_ptr() }
+
+    pub fn clear_events(&mut self) {
+        self.grid.clear_events();
+    }
+}
+
+impl ReplayPlayer {
+    fn from_grid(grid: GridState, moves: Vec<u8>) -> ReplayPlayer {
+        ReplayPlayer { start: grid.to_snapshot(), grid, moves, cursor: 0 }
+    }
+}
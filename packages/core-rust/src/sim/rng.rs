@@ -1,11 +1,14 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 // PCG32 Implementation
 // State: 64-bit
 // Output: 32-bit
 // Period: 2^64
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Pcg32 {
     state: u64,
     inc: u64,
@@ -23,8 +26,15 @@ impl Pcg32 {
         rng
     }
 
-    pub fn seed_from_u64(seed: u64) -> Self {
-        Self::new(seed, 0xda3e39cb94b95bdb)
+    // (state, inc): đủ để tiếp tục đúng chuỗi (snapshot)
+    pub fn to_parts(self) -> (u64, u64) {
+        (self.state, self.inc)
+    }
+
+    // None nếu inc chẵn (PCG cần increment lẻ, không thể ra từ new)
+    pub fn from_parts(state: u64, inc: u64) -> Option<Self> {
+        if inc & 1 == 0 { return None; }
+        Some(Self { state, inc })
     }
 
     pub fn next_u32(&mut self) -> u32 {
@@ -63,9 +73,165 @@ impl Pcg32 {
             }
         }
     }
-    
+
     // Float 0.0..1.0
     pub fn gen_float(&mut self) -> f32 {
          (self.next_u32() >> 8) as f32 * (1.0 / 16777216.0)
     }
 }
+
+// --- GAME RNG ---
+// GridState dùng Pcg32 (state 16 byte, serialize tường minh, không kéo ChaCha vào wasm path mới).
+// Ván tạo trước khi đổi RNG vẫn replay được: seed có bit LEGACY_RNG_SEED_FLAG chạy ChaCha8
+// với phần seed còn lại, đúng như bản cũ. Seed từ JS (< 2^53) không bao giờ tự có bit này.
+
+pub const LEGACY_RNG_SEED_FLAG: u64 = 1 << 63;
+
+// Seed của ván cũ (ChaCha8) -> seed truyền vào GridState::new / validate_replay*
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn legacy_seed(seed: u64) -> u64 {
+    seed | LEGACY_RNG_SEED_FLAG
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn is_legacy_seed(seed: u64) -> bool {
+    seed & LEGACY_RNG_SEED_FLAG != 0
+}
+
+// --- SEED HELPERS ---
+// Room code / daily challenge: mọi client và server tự suy ra cùng seed -> cùng board.
+// Kết quả < 2^53 (an toàn với Number của JS) nên không bao giờ mang cờ legacy.
+
+pub const SEED_MASK: u64 = (1 << 53) - 1;
+
+// Trộn bit splitmix64 (finalizer), ổn định mọi nền tảng
+fn mix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// FNV-1a 64 trên byte UTF-8 rồi trộn. Phân biệt hoa / thường và khoảng trắng: JS tự chuẩn hoá room code.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn seed_from_string(text: &str) -> u64 {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    mix64(hash) & SEED_MASK
+}
+
+// date_days = số ngày từ 1970-01-01 (UTC), salt = theo mode / season để daily mỗi mode khác nhau
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn daily_seed(date_days: u32, salt: u64) -> u64 {
+    mix64(mix64(salt) ^ date_days as u64) & SEED_MASK
+}
+
+// Mỗi subsystem 1 stream Pcg riêng (cùng seed, khác increment): thêm 1 lần rút ở subsystem này
+// không làm lệch chuỗi của subsystem khác -> replay cũ không vỡ âm thầm.
+// Legacy chỉ có 1 ChaCha dùng chung mọi stream, đúng thứ tự rút như bản cũ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngStream {
+    Board = 0,   // Board đầu (new / new_with_noise)
+    Refill = 1,  // Ô mới rơi xuống
+    Special = 2, // spawn_special
+    Shuffle = 3, // Dành cho xáo board
+    Boss = 4,    // Kỹ năng boss (boss_roll)
+}
+
+pub const RNG_STREAM_COUNT: usize = 5;
+
+// Mỗi hàm giữ đúng kiểu range của call site cũ: rand lấy mẫu khác nhau theo kiểu (u8 / u32 / usize)
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameRng {
+    Pcg([Pcg32; RNG_STREAM_COUNT]), // Index = RngStream
+    Legacy(Box<ChaCha8Rng>), // State ChaCha lớn gấp ~8 lần Pcg
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        if is_legacy_seed(seed) {
+            GameRng::Legacy(Box::new(ChaCha8Rng::seed_from_u64(seed & !LEGACY_RNG_SEED_FLAG)))
+        } else {
+            GameRng::Pcg(std::array::from_fn(|stream| Pcg32::new(seed, stream as u64)))
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, GameRng::Legacy(_))
+    }
+
+    // [0, n)
+    pub fn index(&mut self, stream: RngStream, n: usize) -> usize {
+        match self {
+            GameRng::Pcg(rngs) => rngs[stream as usize].gen_range(0..n),
+            GameRng::Legacy(rng) => rng.gen_range(0..n),
+        }
+    }
+
+    // [0, n)
+    pub fn below(&mut self, stream: RngStream, n: u32) -> u32 {
+        match self {
+            GameRng::Pcg(rngs) => rngs[stream as usize].gen_range(0..n as usize) as u32,
+            GameRng::Legacy(rng) => rng.gen_range(0..n),
+        }
+    }
+
+    // [1, max]
+    pub fn element(&mut self, stream: RngStream, max: u8) -> u8 {
+        match self {
+            GameRng::Pcg(rngs) => rngs[stream as usize].gen_range(1..max as usize + 1) as u8,
+            GameRng::Legacy(rng) => rng.gen_range(1..=max),
+        }
+    }
+
+    pub fn next_u64(&mut self, stream: RngStream) -> u64 {
+        match self {
+            GameRng::Pcg(rngs) => rngs[stream as usize].next_u64(),
+            GameRng::Legacy(rng) => rng.gen::<u64>(),
+        }
+    }
+
+    // Vị trí trong chuỗi cho diff_states (Pcg: gộp state mọi stream, ChaCha: word pos)
+    pub fn position(&self) -> u64 {
+        match self {
+            GameRng::Pcg(rngs) => rngs.iter().fold(0, |acc, r| acc.rotate_left(13) ^ r.state),
+            GameRng::Legacy(rng) => rng.get_word_pos() as u64,
+        }
+    }
+}
+
+// --- SHARED RNG (cosmetic) ---
+// Random hiển thị cho frontend (particle jitter...) suy ra từ seed ván + key (vd frame << 16 | index event):
+// replay / spectator dựng lại đúng hiệu ứng. Không đụng stream gameplay, rút bao nhiêu cũng không lệch ván.
+const COSMETIC_STREAM: u64 = RNG_STREAM_COUNT as u64;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct SharedRng {
+    rng: Pcg32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SharedRng {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(seed: u64, key: u32) -> SharedRng {
+        SharedRng { rng: Pcg32::new(mix64(seed) ^ key as u64, COSMETIC_STREAM) }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    // [0, 1)
+    pub fn next_float(&mut self) -> f32 {
+        self.rng.gen_float()
+    }
+
+    // [min, max), min >= max -> min
+    pub fn range(&mut self, min: u32, max: u32) -> u32 {
+        self.rng.gen_range(min as usize..max as usize) as u32
+    }
+
+    // [-amount, amount)
+    pub fn jitter(&mut self, amount: f32) -> f32 {
+        (self.next_float() * 2.0 - 1.0) * amount
+    }
+}
@@ -0,0 +1,164 @@
+use serde::Serialize;
+
+// --- SCORING PIPELINE ---
+// Mọi hệ số nhân điểm đều đi qua đây, UI đọc lại đúng các số này qua get_active_multipliers().
+//
+// Stacking rule (nhân với nhau):
+//   points = base × cycle × avatar × cascade × modifier_pct × decay_pct / 10000
+// - cycle:    multiplier của chu trình (lấy max giữa các track đang bật)
+// - avatar:   x AVATAR_SCORE_FACTOR trong Avatar State
+// - cascade:  độ sâu cascade kể từ swap cuối (1 = lần resolve đầu), chặn ở MAX_CASCADE_MULTIPLIER
+// - modifier: booster / level modifier theo phần trăm (100 = x1)
+// - decay:    anti-farming cho endless mode (xem ScoreDecay), 100 = không giảm
+//
+// Interaction bonus và Resonance là điểm cộng phẳng, KHÔNG đi qua pipeline.
+//
+// Điểm là u64 và mọi phép tính đều saturating: endless run dài với Avatar + cascade không tràn.
+// JS đọc qua get_score_f64 (chính xác tới 2^53) hoặc get_score_hi / get_score_lo.
+
+pub const AVATAR_SCORE_FACTOR: u32 = 2;
+pub const MAX_CASCADE_MULTIPLIER: u32 = 5;
+pub const DEFAULT_MODIFIER_PCT: u32 = 100;
+
+// Cho các API cũ trả điểm u32 (get_score, validate_replay...)
+pub fn saturate_u32(v: u64) -> u32 {
+    v.min(u32::MAX as u64) as u32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ActiveMultipliers {
+    pub cycle: u32,
+    pub avatar: u32,
+    pub cascade: u32,
+    pub modifier_pct: u32,
+    pub decay_pct: u32,
+    pub total_pct: u32, // Hệ số tổng theo phần trăm (100 = x1)
+}
+
+fn total_pct(cycle: u32, avatar: u32, cascade: u32, modifier_pct: u32, decay_pct: u32) -> u32 {
+    let product = [cycle, avatar, cascade, modifier_pct, decay_pct]
+        .iter()
+        .fold(1u64, |acc, &f| acc.saturating_mul(f as u64));
+    saturate_u32(product / 100)
+}
+
+impl ActiveMultipliers {
+    pub fn compute(cycle: u32, in_avatar: bool, cascade_depth: u32, modifier_pct: u32, decay_pct: u32) -> Self {
+        let avatar = if in_avatar { AVATAR_SCORE_FACTOR } else { 1 };
+        let cascade = cascade_depth.clamp(1, MAX_CASCADE_MULTIPLIER);
+        let cycle = cycle.max(1);
+        Self {
+            cycle,
+            avatar,
+            cascade,
+            modifier_pct,
+            decay_pct,
+            total_pct: total_pct(cycle, avatar, cascade, modifier_pct, decay_pct),
+        }
+    }
+
+    pub fn apply(&self, base: u32) -> u64 {
+        base as u64 * self.total_pct as u64 / 100
+    }
+
+    // apply() với chỉ 1 phần hệ số (cùng công thức -> làm tròn giống hệt)
+    fn apply_partial(&self, base: u32, cycle: u32, avatar: u32, cascade: u32) -> u64 {
+        base as u64 * total_pct(cycle, avatar, cascade, self.modifier_pct, self.decay_pct) as u64 / 100
+    }
+}
+
+// --- SCORE BREAKDOWN ---
+// Điểm theo nguồn cho màn kết quả, cộng dồn lúc resolve (không cần simulate lại).
+// Điểm pipeline chia theo thứ tự base -> cycle -> avatar -> cascade: mỗi phần là chênh lệch
+// khi bật thêm 1 hệ số, nên tổng các phần luôn đúng bằng apply().
+// - base:        match × modifier × decay
+// - interaction: bonus phẳng của Tương Sinh / Tương Khắc
+// - cycle:       phần do multiplier chu trình + Khắc Sunder + Resonance
+// - avatar:      phần x2 của Avatar State + Avatar Ultimate
+// - cascade:     phần do độ sâu cascade
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ScoreBreakdown {
+    pub base: u64,
+    pub interaction: u64,
+    pub cascade: u64,
+    pub cycle: u64,
+    pub avatar: u64,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> u64 {
+        [self.base, self.interaction, self.cascade, self.cycle, self.avatar]
+            .iter()
+            .fold(0u64, |acc, &v| acc.saturating_add(v))
+    }
+
+    // Điểm match qua pipeline, returns tổng (= multipliers.apply(base))
+    pub fn add_match(&mut self, multipliers: &ActiveMultipliers, base: u32) -> u64 {
+        let m = multipliers;
+        let plain = m.apply_partial(base, 1, 1, 1);
+        let with_cycle = m.apply_partial(base, m.cycle, 1, 1);
+        let with_avatar = m.apply_partial(base, m.cycle, m.avatar, 1);
+        let total = m.apply(base);
+        self.base = self.base.saturating_add(plain);
+        self.cycle = self.cycle.saturating_add(with_cycle.saturating_sub(plain));
+        self.avatar = self.avatar.saturating_add(with_avatar.saturating_sub(with_cycle));
+        self.cascade = self.cascade.saturating_add(total.saturating_sub(with_avatar));
+        total
+    }
+}
+
+// --- ANTI-FARMING DECAY (endless mode) ---
+// Lặp lại cùng 1 kiểu nước đi được ít điểm dần, tính trong core nên replay validate ra cùng điểm:
+//   decay_pct = max(floor, 100 - element_step × (element_streak - 1) - region_step × (region_streak - 1))
+// - element_streak: số match trực tiếp liên tiếp cùng element (match do cascade không tính)
+// - region_streak:  số swap liên tiếp trong cùng 1 góc phần tư board
+// Mặc định tắt (ScoreDecay::OFF).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoreDecay {
+    pub element_step_pct: u32,
+    pub region_step_pct: u32,
+    pub floor_pct: u32,
+}
+
+impl ScoreDecay {
+    pub const OFF: ScoreDecay = ScoreDecay { element_step_pct: 0, region_step_pct: 0, floor_pct: 100 };
+
+    pub fn is_off(&self) -> bool {
+        self.element_step_pct == 0 && self.region_step_pct == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecayTracker {
+    pub last_element: Option<u8>,
+    pub element_streak: u32,
+    pub last_region: Option<u8>,
+    pub region_streak: u32,
+}
+
+impl DecayTracker {
+    pub fn record_element(&mut self, element: u8) {
+        if self.last_element == Some(element) {
+            self.element_streak += 1;
+        } else {
+            self.last_element = Some(element);
+            self.element_streak = 1;
+        }
+    }
+
+    pub fn record_region(&mut self, region: u8) {
+        if self.last_region == Some(region) {
+            self.region_streak += 1;
+        } else {
+            self.last_region = Some(region);
+            self.region_streak = 1;
+        }
+    }
+
+    pub fn decay_pct(&self, config: &ScoreDecay) -> u32 {
+        if config.is_off() { return 100; }
+        let penalty = config.element_step_pct.saturating_mul(self.element_streak.saturating_sub(1))
+            .saturating_add(config.region_step_pct.saturating_mul(self.region_streak.saturating_sub(1)));
+        100u32.saturating_sub(penalty).max(config.floor_pct.min(100))
+    }
+}
@@ -0,0 +1,112 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+// --- SAVE SEALING ---
+// Niêm phong snapshot / profile lưu local bằng XChaCha20-Poly1305 với device key (32 byte).
+// Không phải DRM: mục tiêu là người chơi không sửa tay được save; sửa 1 bit -> open thất bại.
+//
+// Layout: MAGIC(4) | mode(1) | ...
+//   mode SEALED: nonce(24) | ciphertext + tag(16)   (header 5 byte là associated data)
+//   mode PLAIN:  payload nguyên bản, chỉ dùng khi dev; build release gọi open với allow_plaintext = false
+
+pub const SEAL_MAGIC: &[u8; 4] = b"LIS1";
+pub const SEAL_KEY_LEN: usize = 32;
+const MODE_PLAIN: u8 = 0;
+const MODE_SEALED: u8 = 1;
+const HEADER_LEN: usize = 5;
+const NONCE_LEN: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealError {
+    BadKey,            // Key không đủ 32 byte
+    BadHeader,         // Không phải blob của ta / version lạ
+    Tampered,          // Sai key hoặc dữ liệu bị sửa
+    PlaintextRejected, // Blob dev (không niêm phong) khi không cho phép
+    NoRandom,          // Không lấy được nonce ngẫu nhiên
+}
+
+impl std::fmt::Display for SealError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            SealError::BadKey => "seal key must be 32 bytes",
+            SealError::BadHeader => "not a sealed save blob",
+            SealError::Tampered => "save blob was modified or key is wrong",
+            SealError::PlaintextRejected => "plaintext save blob not allowed",
+            SealError::NoRandom => "no randomness available for nonce",
+        };
+        f.write_str(msg)
+    }
+}
+
+fn cipher(key: &[u8]) -> Result<XChaCha20Poly1305, SealError> {
+    if key.len() != SEAL_KEY_LEN { return Err(SealError::BadKey); }
+    Ok(XChaCha20Poly1305::new(Key::from_slice(key)))
+}
+
+pub fn seal(data: &[u8], key: &[u8]) -> Result<Vec<u8>, SealError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut nonce).map_err(|_| SealError::NoRandom)?;
+    seal_with_nonce(data, key, &nonce)
+}
+
+// Nonce do caller cấp (test / môi trường không có RNG). KHÔNG dùng lại nonce với cùng key.
+pub fn seal_with_nonce(data: &[u8], key: &[u8], nonce: &[u8; NONCE_LEN]) -> Result<Vec<u8>, SealError> {
+    let cipher = cipher(key)?;
+    let mut out = Vec::with_capacity(HEADER_LEN + NONCE_LEN + data.len() + 16);
+    out.extend_from_slice(SEAL_MAGIC);
+    out.push(MODE_SEALED);
+    let sealed = cipher
+        .encrypt(XNonce::from_slice(nonce), Payload { msg: data, aad: &out[..HEADER_LEN] })
+        .map_err(|_| SealError::Tampered)?;
+    out.extend_from_slice(nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+// Dev mode: cùng container nhưng không mã hoá
+pub fn wrap_plaintext(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + data.len());
+    out.extend_from_slice(SEAL_MAGIC);
+    out.push(MODE_PLAIN);
+    out.extend_from_slice(data);
+    out
+}
+
+pub fn open(blob: &[u8], key: &[u8], allow_plaintext: bool) -> Result<Vec<u8>, SealError> {
+    if blob.len() < HEADER_LEN || &blob[..4] != SEAL_MAGIC { return Err(SealError::BadHeader); }
+    match blob[4] {
+        MODE_PLAIN if allow_plaintext => Ok(blob[HEADER_LEN..].to_vec()),
+        MODE_PLAIN => Err(SealError::PlaintextRejected),
+        MODE_SEALED => {
+            let cipher = cipher(key)?;
+            if blob.len() < HEADER_LEN + NONCE_LEN { return Err(SealError::Tampered); }
+            let (nonce, sealed) = blob[HEADER_LEN..].split_at(NONCE_LEN);
+            cipher
+                .decrypt(XNonce::from_slice(nonce), Payload { msg: sealed, aad: &blob[..HEADER_LEN] })
+                .map_err(|_| SealError::Tampered)
+        }
+        _ => Err(SealError::BadHeader),
+    }
+}
+
+// --- JS API ---
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn seal_save(data: &[u8], key: &[u8]) -> Result<Vec<u8>, JsValue> {
+    seal(data, key).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn wrap_save_plaintext(data: &[u8]) -> Vec<u8> {
+    wrap_plaintext(data)
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn open_save(blob: &[u8], key: &[u8], allow_plaintext: bool) -> Result<Vec<u8>, JsValue> {
+    open(blob, key, allow_plaintext).map_err(|e| JsValue::from_str(&e.to_string()))
+}
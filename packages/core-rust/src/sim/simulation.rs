@@ -1,10 +1,26 @@
 use crate::ecs::world::World;
-use crate::ecs::components::{Position, Velocity, Player};
-use crate::sim::systems::MovementSystem;
-use crate::sim::grid::GridState;
+use crate::ecs::schedule::{Schedule, Stage};
+use crate::ecs::components::{Position, Velocity, Player, GridBound, Effect, EntityDied, Frozen, CellAnchor, Lifetime, Name};
+use crate::ecs::events::EventReader;
+use crate::ecs::prefab::Prefab;
+use crate::ecs::stats::WorldStats;
+use crate::sim::systems::{MovementSystem, CellBindingSystem, EventSpawnSystem, LifetimeSystem};
+use crate::sim::spawner::{SpawnBinding, DEFAULT_SPAWN_BINDINGS};
+use crate::sim::grid::{FrameState, GridState};
+use crate::sim::coords::{CellMapping, PositionSpace};
+use crate::sim::photo::PhotoState;
+use crate::sim::rng::SharedRng;
+use crate::sim::events::EventType;
+use std::collections::VecDeque;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
+#[cfg(feature = "wasm")]
 use serde::Serialize;
 
+// Fixed timestep 60Hz
+const FIXED_DT: f64 = 1.0 / 60.0;
+
+#[cfg(feature = "wasm")]
 #[derive(Serialize)]
 struct EntityState {
     id: u64,
@@ -12,13 +28,14 @@ struct EntityState {
     vel: Option<Velocity>,
 }
 
+#[cfg(feature = "wasm")]
 #[derive(Serialize)]
 struct GameState {
     entities: Vec<EntityState>,
     time: f64,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Simulation {
     world: World,
     grid: GridState,
@@ -26,16 +43,47 @@ pub struct Simulation {
     game_time: f64,
     frame_count: u64,
 
+    // Grid <-> world mapping, và không gian toạ độ khi export buffer
+    cell_mapping: CellMapping,
+    position_space: PositionSpace,
+
+    // Event -> entity hiệu ứng (xem sim/spawner.rs)
+    spawn_bindings: Vec<SpawnBinding>,
+    spawn_cursor: u64, // Số thứ tự event kế tiếp chưa xử lý
+
+    // ECS event -> event buffer cho JS (xem forward_ecs_events)
+    entity_died_reader: EventReader<EntityDied>,
+
+    // Autosave: snapshot grid sau mỗi N tick ổn định, giữ K bản mới nhất (0 = tắt)
+    autosave_every: u32,
+    autosave_slots: usize,
+    stable_ticks: u32,
+    autosaves: VecDeque<Vec<u8>>,
+    last_autosave: Option<(u32, u32, u32)>, // (turn, score, checksum) của bản mới nhất
+
+    // Rollback netcode: ring K snapshot theo frame (= tick của grid), 0 = tắt
+    rollback_capacity: usize,
+    rollback_frames: VecDeque<(u32, FrameState)>,
+
+    // Photo mode: đóng băng sim (không tick, không nhận input) để chụp ảnh share
+    photo_mode: bool,
+
+    // System chạy mỗi tick theo stage (xem ecs/schedule.rs)
+    schedule: Schedule<Simulation>,
+
     // Zero-Copy Buffers
     entity_ids: Vec<u64>,
     positions: Vec<Position>,
     velocities: Vec<Velocity>,
+    archetypes: Vec<u8>, // Effect archetype, 0 = không phải entity hiệu ứng
+    synced_tick: Option<u32>, // Change tick lúc sync_buffers gần nhất, None = buộc build lại
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Simulation {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        #[cfg(feature = "wasm")]
         console_error_panic_hook::set_once();
         
         // Init Physics World
@@ -45,12 +93,28 @@ impl Simulation {
         world.register_component::<Position>();
         world.register_component::<Velocity>();
         world.register_component::<Player>();
+        world.add_event::<EntityDied>();
+        world.track_changes::<Position>();
+        world.track_changes::<Velocity>();
+        // Tên lưu trong world snapshot, không đổi
+        world.register_persistent::<Position>("position");
+        world.register_persistent::<Velocity>("velocity");
+        world.register_persistent::<Player>("player");
+        world.register_persistent::<GridBound>("grid_bound");
+        world.register_persistent::<Frozen>("frozen");
+        world.register_persistent::<CellAnchor>("cell_anchor");
+        world.register_persistent::<Lifetime>("lifetime");
+        world.register_persistent::<Effect>("effect");
+        world.register_persistent::<Name>("name");
         
+        world.register_prefab("player", Prefab::new()
+            .with(Position { x: 100.0, y: 100.0 })
+            .with(Velocity { x: 10.0, y: 5.0 })
+            .with(Player { id: 1 })
+            .with(Name("player_1".to_string())));
+
         // Init Test State directly here for now
-        let e = world.create_entity();
-        world.add_component(e, Position { x: 100.0, y: 100.0 });
-        world.add_component(e, Velocity { x: 10.0, y: 5.0 });
-        world.add_component(e, Player { id: 1 });
+        world.spawn_prefab("player", Prefab::new());
         
         // Init Grid
         let grid = GridState::new(width, height, seed);
@@ -61,20 +125,47 @@ impl Simulation {
             accumulator: 0.0,
             game_time: 0.0,
             frame_count: 0,
+            cell_mapping: CellMapping::default(),
+            position_space: PositionSpace::World,
+            spawn_bindings: DEFAULT_SPAWN_BINDINGS.to_vec(),
+            spawn_cursor: 0,
+            entity_died_reader: EventReader::default(),
+            autosave_every: 0,
+            autosave_slots: 0,
+            stable_ticks: 0,
+            autosaves: VecDeque::new(),
+            last_autosave: None,
+            rollback_capacity: 0,
+            rollback_frames: VecDeque::new(),
+            photo_mode: false,
+            schedule: Self::default_schedule(),
             entity_ids: Vec::with_capacity(1024),
             positions: Vec::with_capacity(1024),
             velocities: Vec::with_capacity(1024),
+            archetypes: Vec::with_capacity(1024),
+            synced_tick: None,
         }
     }
 
+    // Như new() nhưng board đầu sinh theo cụm noise (xem GridState::new_with_noise)
+    pub fn with_board_noise(width: usize, height: usize, seed: u64, scale: u32, cluster_pct: u32) -> Self {
+        let mut sim = Self::new(width, height, seed);
+        sim.grid = GridState::new_with_noise(width, height, seed, scale, cluster_pct);
+        sim
+    }
+
     /// Fixed Timestep Loop
     pub fn update(&mut self, dt_ms: f64) -> f64 {
-        const FIXED_DT: f64 = 1.0 / 60.0;
         const MAX_FRAME_TIME: f64 = 0.25;
 
         // Convert ms to seconds
         let dt_sec = dt_ms / 1000.0;
         
+        // Photo mode: giữ nguyên frame (và alpha) đang chụp
+        if self.photo_mode {
+            return self.accumulator / FIXED_DT;
+        }
+
         // Clamp frame time to avoid spiral of death
         let dt_clamped = if dt_sec > MAX_FRAME_TIME { MAX_FRAME_TIME } else { dt_sec };
 
@@ -92,34 +183,298 @@ impl Simulation {
     }
 
     fn tick(&mut self, dt: f64) {
-        MovementSystem::update(&mut self.world, dt);
-        // Step grid logic
-        self.grid.tick();
+        // Tách schedule ra để system nhận &mut Simulation
+        let mut schedule = std::mem::take(&mut self.schedule);
+        schedule.run(self, dt);
+        self.schedule = schedule;
+    }
+
+    // Bật / tắt 1 stage (0 input, 1 logic, 2 grid, 3 post). Returns false nếu stage không tồn tại.
+    pub fn set_stage_enabled(&mut self, stage: u8, enabled: bool) -> bool {
+        let Some(stage) = Stage::from_id(stage) else { return false };
+        self.schedule.set_stage_enabled(stage, enabled);
+        true
+    }
+
+    pub fn is_stage_enabled(&self, stage: u8) -> bool {
+        Stage::from_id(stage).is_some_and(|s| self.schedule.is_stage_enabled(s))
+    }
+
+    fn autosave_tick(&mut self) {
+        if self.autosave_every == 0 { return; }
+        if !self.grid.is_stable() {
+            self.stable_ticks = 0;
+            return;
+        }
+        self.stable_ticks += 1;
+        if self.stable_ticks != self.autosave_every { return; }
+
+        // Board đứng yên lâu thì không ghi thêm bản trùng
+        let key = (self.grid.get_turn_count(), self.grid.get_score(), self.grid.get_checksum());
+        if self.last_autosave == Some(key) { return; }
+        self.last_autosave = Some(key);
+        if self.autosaves.len() >= self.autosave_slots {
+            self.autosaves.pop_front();
+        }
+        self.autosaves.push_back(self.grid.to_snapshot());
+    }
+
+    // ECS event cần renderer biết -> event buffer của grid (chung frame, mask, spawn binding)
+    fn forward_ecs_events(&mut self) {
+        let width = self.grid.get_width();
+        for died in self.world.read_events(&mut self.entity_died_reader) {
+            let cell = died.cell as usize;
+            self.grid.push_external_event(EventType::EntityDied, cell % width, cell / width, 0, died.entity.id());
+        }
+    }
+
+    // Event mới kể từ lần trước (gồm cả event phát lúc swap) -> entity hiệu ứng
+    fn spawn_from_events(&mut self) {
+        let events = self.grid.events_since(self.spawn_cursor);
+        EventSpawnSystem::run(&mut self.world, &self.spawn_bindings, events, self.grid.get_width(), &self.cell_mapping);
+        self.spawn_cursor = self.grid.events_stored();
     }
 
     /// Synchronize ECS state to continuous buffers for Zero-Copy access
+    /// Skips the rebuild when no Position / Velocity was added, written or removed since the last sync
     pub fn sync_buffers(&mut self) {
+        if let Some(since) = self.synced_tick {
+            if !self.world.any_changed_since::<Position>(since) && !self.world.any_changed_since::<Velocity>(since) { return; }
+        }
         self.entity_ids.clear();
         self.positions.clear();
         self.velocities.clear();
+        self.archetypes.clear();
 
-        for (e, (pos, vel)) in self.world.inner().query::<(&Position, Option<&Velocity>)>().iter() {
+        for (e, (pos, vel, grid_bound, effect)) in self.world.query::<(&Position, Option<&Velocity>, Option<&GridBound>, Option<&Effect>)>().iter() {
+            let vel = vel.copied().unwrap_or(Velocity { x: 0.0, y: 0.0 });
+            let (pos, vel) = self.cell_mapping.export(*pos, vel, grid_bound.is_some(), self.position_space);
             self.entity_ids.push(e.to_bits().get());
-            self.positions.push(*pos);
-            self.velocities.push(vel.copied().unwrap_or(Velocity { x: 0.0, y: 0.0 }));
+            self.positions.push(pos);
+            self.velocities.push(vel);
+            self.archetypes.push(effect.map_or(0, |f| f.archetype));
         }
+        self.synced_tick = Some(self.world.advance_change_tick());
+    }
+
+    // --- GRID COORDINATES ---
+    // origin = góc trên-trái ô (0,0) trong world, cell_size = cạnh ô (world units)
+    pub fn set_cell_mapping(&mut self, origin_x: f32, origin_y: f32, cell_size: f32) {
+        if cell_size <= 0.0 { return; }
+        self.cell_mapping = CellMapping { origin_x, origin_y, cell_size };
+        self.synced_tick = None;
+    }
+
+    // Không gian toạ độ của positions / velocities buffer sau sync_buffers
+    pub fn set_position_space(&mut self, space: PositionSpace) {
+        self.position_space = space;
+        self.synced_tick = None;
+    }
+
+    // Returns [x, y] world của tâm ô (chấp nhận toạ độ ô lẻ)
+    pub fn cell_to_world(&self, cell_x: f32, cell_y: f32) -> Vec<f32> {
+        let p = self.cell_mapping.cell_to_world(Position { x: cell_x, y: cell_y });
+        vec![p.x, p.y]
+    }
+
+    // Returns [cell_x, cell_y] của ô chứa điểm world (có thể ngoài board)
+    pub fn world_to_cell(&self, x: f32, y: f32) -> Vec<i32> {
+        let (cx, cy) = self.cell_mapping.world_to_cell_index(Position { x, y });
+        vec![cx, cy]
     }
 
     pub fn get_entity_ids_ptr(&self) -> *const u64 { self.entity_ids.as_ptr() }
     pub fn get_positions_ptr(&self) -> *const Position { self.positions.as_ptr() }
     pub fn get_velocities_ptr(&self) -> *const Velocity { self.velocities.as_ptr() }
+    pub fn get_entity_archetypes_ptr(&self) -> *const u8 { self.archetypes.as_ptr() }
     pub fn get_entities_count(&self) -> usize { self.entity_ids.len() }
+
+    // --- AUTOSAVE ---
+    // Snapshot grid sau mỗi `every_stable_ticks` sim tick ổn định liên tiếp, giữ `slots` bản mới nhất.
+    // every_stable_ticks = 0 hoặc slots = 0 -> tắt và xoá các bản cũ.
+    pub fn set_autosave(&mut self, every_stable_ticks: u32, slots: usize) {
+        self.autosave_every = if slots == 0 { 0 } else { every_stable_ticks };
+        self.autosave_slots = slots;
+        self.stable_ticks = 0;
+        self.last_autosave = None;
+        self.autosaves.clear();
+    }
+
+    pub fn get_autosave_count(&self) -> usize {
+        self.autosaves.len()
+    }
+
+    // 0 = bản mới nhất. Rỗng nếu không có.
+    pub fn get_autosave(&self, index_from_newest: usize) -> Vec<u8> {
+        self.autosaves
+            .iter()
+            .rev()
+            .nth(index_from_newest)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Snapshot grid ngay lúc này (suspend khi app xuống background), resume bằng restore_snapshot
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.grid.to_bytes()
+    }
+
+    // Resume từ snapshot (autosave hoặc bản JS lưu). Giữ nguyên ECS world.
+    // Returns false nếu snapshot hỏng / khác version (state hiện tại giữ nguyên).
+    pub fn restore_snapshot(&mut self, data: &[u8]) -> bool {
+        let Ok(grid) = GridState::from_snapshot(data) else { return false; };
+        self.grid = grid;
+        self.spawn_cursor = self.grid.events_stored();
+        self.stable_ticks = 0;
+        true
+    }
+
+    // ECS world (entity + component, id giữ nguyên), đi cặp với to_bytes của grid
+    pub fn get_world_snapshot(&self) -> Vec<u8> {
+        self.world.save()
+    }
+
+    // Returns false nếu snapshot hỏng / có component lạ (world hiện tại giữ nguyên)
+    pub fn restore_world_snapshot(&mut self, data: &[u8]) -> bool {
+        if self.world.load(data).is_err() { return false; }
+        self.synced_tick = None;
+        true
+    }
+
+    // --- ROLLBACK (GGPO-style) ---
+    // Ring state theo frame nằm hoàn toàn trong wasm (không kèm move journal), JS chỉ gửi frame + input.
+    // capacity = 0 -> tắt và xoá ring.
+    pub fn set_rollback_capacity(&mut self, frames: usize) {
+        self.rollback_capacity = frames;
+        self.rollback_frames.clear();
+    }
+
+    // Frame hiện tại = tick count của grid
+    pub fn get_tick_count(&self) -> u32 {
+        self.grid.get_tick_count()
+    }
+
+    pub fn get_rollback_frame_count(&self) -> usize {
+        self.rollback_frames.len()
+    }
+
+    // Lưu state của frame hiện tại (ghi đè nếu frame đã có), bỏ bản cũ nhất khi đầy.
+    // Returns frame đã lưu (= tick count của grid).
+    pub fn save_frame(&mut self) -> u32 {
+        let frame = self.grid.get_tick_count();
+        if self.rollback_capacity == 0 { return frame; }
+        self.rollback_frames.retain(|(f, _)| *f != frame);
+        self.rollback_frames.push_back((frame, self.grid.save_frame_state()));
+        while self.rollback_frames.len() > self.rollback_capacity {
+            self.rollback_frames.pop_front();
+        }
+        frame
+    }
+
+    // Quay về frame đã lưu, bỏ các frame mới hơn. Returns false nếu frame không còn trong ring.
+    pub fn rollback_to(&mut self, frame: u32) -> bool {
+        let Some(pos) = self.rollback_frames.iter().position(|(f, _)| *f == frame) else { return false; };
+        if !self.grid.restore_frame_state(&self.rollback_frames[pos].1) { return false; }
+        self.rollback_frames.truncate(pos + 1);
+        self.spawn_cursor = self.grid.events_stored();
+        CellBindingSystem::update(&mut self.world, &self.grid, &self.cell_mapping);
+        true
+    }
+
+    // Chạy lại tới `to_frame` với input đã xác nhận: cặp [frame, record] nối liền, record = 4 byte
+    // journal (x1, y1, x2, y2 hoặc booster) đọc little-endian thành u32. Input của frame F áp dụng
+    // trước tick đưa grid lên F + 1. Lưu frame sau mỗi tick. Không spawn hiệu ứng cho event chạy lại.
+    // Returns số tick đã chạy.
+    pub fn resimulate_with_inputs(&mut self, to_frame: u32, inputs: &[u32]) -> u32 {
+        let mut ticks = 0;
+        while self.grid.get_tick_count() < to_frame {
+            let frame = self.grid.get_tick_count();
+            for pair in inputs.chunks_exact(2).filter(|p| p[0] == frame) {
+                self.grid.apply_input(&pair[1].to_le_bytes());
+            }
+            self.grid.tick();
+            self.save_frame();
+            ticks += 1;
+        }
+        self.spawn_cursor = self.grid.events_stored();
+        CellBindingSystem::update(&mut self.world, &self.grid, &self.cell_mapping);
+        ticks
+    }
+
+    // --- PHOTO MODE ---
+    // Đóng băng sim và export toàn bộ render state của frame hiện tại:
+    // board, cycle HUD, entity (toạ độ theo position space), event của frame phát lại đầy đủ.
+    #[cfg(feature = "wasm")]
+    pub fn enter_photo_mode(&mut self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.capture_photo()).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    pub fn exit_photo_mode(&mut self) {
+        self.photo_mode = false;
+    }
+
+    pub fn is_photo_mode(&self) -> bool {
+        self.photo_mode
+    }
+
+    // --- EFFECT SPAWN BINDINGS ---
+    // Thêm luật: event_type -> entity hiệu ứng `archetype` sống `lifetime_ticks` sim tick
+    pub fn add_spawn_binding(&mut self, event_type: u8, archetype: u8, lifetime_ticks: u32, anchor_to_cell: bool) {
+        self.spawn_bindings.push(SpawnBinding { event_type, archetype, lifetime_ticks, anchor_to_cell });
+    }
+
+    pub fn clear_spawn_bindings(&mut self) {
+        self.spawn_bindings.clear();
+    }
+
+    pub fn reset_spawn_bindings(&mut self) {
+        self.spawn_bindings = DEFAULT_SPAWN_BINDINGS.to_vec();
+    }
     
+    // Debug overlay: số entity, số / bytes component theo kiểu (xem ecs/stats.rs)
+    #[cfg(feature = "wasm")]
+    pub fn get_world_stats(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.world.stats()).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    // Spawn prefab đã đăng ký tại (x, y) world. Returns id (như entity_ids buffer), 0 nếu không có prefab.
+    pub fn spawn_prefab_at(&mut self, name: &str, x: f32, y: f32) -> u64 {
+        self.world
+            .spawn_prefab(name, Prefab::new().with(Position { x, y }))
+            .map_or(0, |e| e.to_bits().get())
+    }
+
+    // Gắn nhãn cho script / debug tool. Returns false nếu entity không còn sống.
+    pub fn set_entity_name(&mut self, id: u64, name: &str) -> bool {
+        let Some(entity) = hecs::Entity::from_bits(id) else { return false };
+        if !self.world.contains(entity) { return false; }
+        self.world.add_component(entity, Name(name.to_string()));
+        true
+    }
+
+    // id như entity_ids buffer, 0 nếu không có entity mang tên này
+    pub fn find_entity_by_name(&self, name: &str) -> u64 {
+        self.world.find_by_name(name).map_or(0, |e| e.to_bits().get())
+    }
+
+    // id = entity_ids buffer. Returns false nếu entity không còn sống.
+    pub fn set_entity_frozen(&mut self, id: u64, frozen: bool) -> bool {
+        let Some(entity) = hecs::Entity::from_bits(id) else { return false };
+        if !self.world.contains(entity) { return false; }
+        if frozen {
+            self.world.add_component(entity, Frozen);
+        } else {
+            self.world.remove_component::<Frozen>(entity);
+        }
+        true
+    }
+
     // Helper to get raw pointer to world for other WASM modules (if needed)
     pub fn world_ptr(&self) -> *const World {
         &self.world
     }
     
+    #[cfg(feature = "wasm")]
     pub fn get_state(&self) -> Result<JsValue, JsValue> {
         // Optimized legacy bridge: uses synced buffers if they match current state, 
         // or just re-runs query. For SOTA we avoid this, but keeping for compatibility.
@@ -153,24 +508,105 @@ impl Simulation {
         self.grid.get_cells_len()
     }
 
+    pub fn query_cells(&self, element_mask: u32, flag_mask: u8, x: usize, y: usize, w: usize, h: usize) -> Vec<u32> {
+        self.grid.query_cells(element_mask, flag_mask, x, y, w, h)
+    }
+
     pub fn check_matches(&mut self) -> Vec<usize> {
         // Not exposed in new GridState
         Vec::new()
     }
 
     pub fn swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) -> bool {
+         if self.photo_mode { return false; }
          let w = self.grid.get_width();
          let idx1 = y1 * w + x1;
          let idx2 = y2 * w + x2;
-         self.grid.try_swap(idx1, idx2)
+         let swapped = self.grid.try_swap(idx1, idx2);
+         if swapped {
+             CellBindingSystem::apply_swap(&mut self.world, &self.grid, &self.cell_mapping, idx1, idx2);
+         }
+         swapped
+    }
+
+    // Shared board: swap tính cho người chơi `player_id` (xem GridState::try_swap_as)
+    pub fn swap_as(&mut self, player_id: u32, x1: usize, y1: usize, x2: usize, y2: usize) -> bool {
+        if self.photo_mode { return false; }
+        let w = self.grid.get_width();
+        let (idx1, idx2) = (y1 * w + x1, y2 * w + x2);
+        let swapped = self.grid.try_swap_as(player_id, idx1, idx2);
+        if swapped {
+            CellBindingSystem::apply_swap(&mut self.world, &self.grid, &self.cell_mapping, idx1, idx2);
+        }
+        swapped
+    }
+
+    pub fn add_player(&mut self, player_id: u32) -> bool {
+        self.grid.add_player(player_id)
+    }
+
+    pub fn clear_players(&mut self) {
+        self.grid.clear_players();
+    }
+
+    pub fn set_player_role(&mut self, player_id: u32, element_mask: u32) -> bool {
+        self.grid.set_player_role(player_id, element_mask)
+    }
+
+    pub fn set_turn_window(&mut self, swaps: u32) {
+        self.grid.set_turn_window(swaps);
+    }
+
+    pub fn get_turn_player(&self) -> Option<u32> {
+        self.grid.get_turn_player()
+    }
+
+    // Co-op 2 người: A = player 1 (Metal / Water), B = player 2 (Wood / Fire / Earth)
+    pub fn setup_coop(&mut self, window: u32) {
+        self.grid.setup_coop(window);
+    }
+
+    pub fn get_active_player(&self) -> Option<u32> {
+        self.grid.get_active_player()
+    }
+
+    pub fn get_player_score(&self, player_id: u32) -> u32 {
+        self.grid.get_player_score(player_id)
+    }
+
+    pub fn get_player_cycle_target(&self, player_id: u32) -> u8 {
+        self.grid.get_player_cycle_target(player_id)
+    }
+
+    pub fn get_player_cycle_chain(&self, player_id: u32) -> u32 {
+        self.grid.get_player_cycle_chain(player_id)
+    }
+
+    pub fn get_player_cycle_multiplier(&self, player_id: u32) -> u32 {
+        self.grid.get_player_cycle_multiplier(player_id)
+    }
+
+    // Accessibility: single-tap match assist. Returns [x1, y1, x2, y2] or empty.
+    pub fn assist_tap(&mut self, x: usize, y: usize) -> Vec<u8> {
+        if self.photo_mode { return Vec::new(); }
+        let mv = self.grid.assist_tap(x, y);
+        if let [x1, y1, x2, y2] = mv[..] {
+            let w = self.grid.get_width();
+            let (idx1, idx2) = (y1 as usize * w + x1 as usize, y2 as usize * w + x2 as usize);
+            CellBindingSystem::apply_swap(&mut self.world, &self.grid, &self.cell_mapping, idx1, idx2);
+        }
+        mv
     }
 
     pub fn tick_grid(&mut self) {
+        if self.photo_mode { return; }
         self.grid.tick();
+        CellBindingSystem::update(&mut self.world, &self.grid, &self.cell_mapping);
+        self.spawn_from_events();
     }
     
     // Updated Event API
-    pub fn get_events_ptr(&self) -> *const u32 {
+    pub fn get_events_ptr(&mut self) -> *const u32 {
         self.grid.get_events_ptr()
     }
 
@@ -182,14 +618,105 @@ impl Simulation {
         self.grid.clear_events();
     }
 
+    pub fn set_event_capacity(&mut self, capacity: usize) {
+        self.grid.set_event_capacity(capacity);
+    }
+
+    pub fn set_event_overflow_policy(&mut self, policy: crate::sim::events::EventOverflowPolicy) {
+        self.grid.set_event_overflow_policy(policy);
+    }
+
+    pub fn events_dropped(&self) -> u32 {
+        self.grid.events_dropped()
+    }
+
+    pub fn set_event_mask(&mut self, mask: u32) {
+        self.grid.set_event_mask(mask);
+    }
+
+    pub fn get_audio_cues_ptr(&mut self) -> *const u32 {
+        self.grid.get_audio_cues_ptr()
+    }
+
+    pub fn get_audio_cues_len(&self) -> usize {
+        self.grid.get_audio_cues_len()
+    }
+
+    pub fn clear_audio_cues(&mut self) {
+        self.grid.clear_audio_cues();
+    }
+
+    // Event format v2 (struct-of-arrays)
+    pub fn get_event_format_version(&self) -> u32 { self.grid.get_event_format_version() }
+    pub fn get_event_types_ptr(&mut self) -> *const u8 { self.grid.get_event_types_ptr() }
+    pub fn get_event_xs_ptr(&mut self) -> *const u16 { self.grid.get_event_xs_ptr() }
+    pub fn get_event_ys_ptr(&mut self) -> *const u16 { self.grid.get_event_ys_ptr() }
+    pub fn get_event_intensities_ptr(&mut self) -> *const u8 { self.grid.get_event_intensities_ptr() }
+    pub fn get_event_payloads_ptr(&mut self) -> *const u32 { self.grid.get_event_payloads_ptr() }
+    pub fn get_event_frames_ptr(&mut self) -> *const u32 { self.grid.get_event_frames_ptr() }
+    pub fn get_event_delays_ptr(&mut self) -> *const u16 { self.grid.get_event_delays_ptr() }
+    pub fn get_event_durations_ptr(&mut self) -> *const u16 { self.grid.get_event_durations_ptr() }
+
+    pub fn set_event_log(&mut self, enabled: bool) {
+        self.grid.set_event_log(enabled);
+    }
+
+    pub fn get_event_log(&self) -> Vec<u8> {
+        self.grid.get_event_log()
+    }
+
+    pub fn set_hash_trail(&mut self, every: u32) {
+        self.grid.set_hash_trail(every);
+    }
+
+    pub fn get_hash_trail_ptr(&self) -> *const u64 {
+        self.grid.get_hash_trail_ptr()
+    }
+
+    pub fn get_hash_trail_len(&self) -> usize {
+        self.grid.get_hash_trail_len()
+    }
+
+    pub fn get_hash_trail_first_tick(&self) -> u32 {
+        self.grid.get_hash_trail_first_tick()
+    }
+
+    pub fn clear_hash_trail(&mut self) {
+        self.grid.clear_hash_trail();
+    }
+
+    pub fn get_next_event_frame(&self) -> Option<u32> {
+        self.grid.get_next_event_frame()
+    }
+
+    pub fn drain_events_for_frame(&mut self) -> Vec<u32> {
+        self.grid.drain_events_for_frame()
+    }
+
     pub fn get_score(&self) -> u32 {
         self.grid.get_score()
     }
 
+    pub fn get_score_f64(&self) -> f64 {
+        self.grid.get_score_f64()
+    }
+
+    pub fn get_score_hi(&self) -> u32 {
+        self.grid.get_score_hi()
+    }
+
+    pub fn get_score_lo(&self) -> u32 {
+        self.grid.get_score_lo()
+    }
+
     pub fn get_cycle_target(&self) -> u8 {
         self.grid.get_cycle_target()
     }
 
+    pub fn get_cycle_targets(&self, count: usize) -> Vec<u8> {
+        self.grid.get_cycle_targets(count)
+    }
+
     pub fn get_cycle_chain(&self) -> u32 {
         self.grid.get_cycle_chain()
     }
@@ -198,6 +725,83 @@ impl Simulation {
         self.grid.get_cycle_multiplier()
     }
 
+    #[cfg(feature = "wasm")]
+    pub fn get_session_stats(&self) -> JsValue {
+        self.grid.get_session_stats()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_game_summary(&self) -> JsValue {
+        self.grid.get_game_summary()
+    }
+
+    pub fn reset_session_stats(&mut self) {
+        self.grid.reset_session_stats();
+    }
+
+    pub fn set_xp_curve(&mut self, base: u32, growth_pct: u32, max_level: u32) {
+        self.grid.set_xp_curve(base, growth_pct, max_level);
+    }
+
+    pub fn set_xp_rewards(&mut self, per_match: u32, per_extra_cell: u32, per_interaction: u32) {
+        self.grid.set_xp_rewards(per_match, per_extra_cell, per_interaction);
+    }
+
+    pub fn set_xp(&mut self, xp: f64) {
+        self.grid.set_xp(xp);
+    }
+
+    pub fn get_xp(&self) -> f64 {
+        self.grid.get_xp()
+    }
+
+    pub fn get_xp_level(&self) -> u32 {
+        self.grid.get_xp_level()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_xp_progress(&self) -> JsValue {
+        self.grid.get_xp_progress()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_score_breakdown(&self) -> JsValue {
+        self.grid.get_score_breakdown()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_active_multipliers(&self) -> JsValue {
+        self.grid.get_active_multipliers()
+    }
+
+    pub fn set_score_modifier(&mut self, modifier_pct: u32) {
+        self.grid.set_score_modifier(modifier_pct);
+    }
+
+    pub fn set_score_decay(&mut self, element_step_pct: u32, region_step_pct: u32, floor_pct: u32) {
+        self.grid.set_score_decay(element_step_pct, region_step_pct, floor_pct);
+    }
+
+    pub fn set_drought_pity(&mut self, threshold: u32, step_pct: u32, max_boost_pct: u32) {
+        self.grid.set_drought_pity(threshold, step_pct, max_boost_pct);
+    }
+
+    pub fn get_drought_stats(&self) -> Vec<u32> {
+        self.grid.get_drought_stats()
+    }
+
+    pub fn set_experiment_bucket(&mut self, bucket: u32) {
+        self.grid.set_experiment_bucket(bucket);
+    }
+
+    pub fn get_experiment_bucket(&self) -> Option<u32> {
+        self.grid.get_experiment_bucket()
+    }
+
+    pub fn get_experiment_variant(&self) -> String {
+        self.grid.get_experiment_variant()
+    }
+
     pub fn get_match_queue_ptr(&self) -> *const u8 {
         self.grid.get_match_queue_ptr()
     }
@@ -212,7 +816,7 @@ impl Simulation {
     
     // PREVIEW API
     // Returns flat array: [index, type, index, type...]
-    // Type: 1 = Destruction (Red), 2 = Generation (Blue)
+    // Type: 1 = Destruction (Red), 2 = Generation (Blue), 3 = Hazard áp ngay sau nước đi này
     pub fn preview_swap(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) -> Vec<u32> {
          let w = self.grid.get_width();
          let idx1 = y1 * w + x1;
@@ -224,6 +828,22 @@ impl Simulation {
         self.grid.preview_neighbors(x, y)
     }
 
+    // BOSS HAZARDS: [index, flag, số lượt còn lại] trong `window` lượt tới
+    pub fn schedule_hazard(&mut self, x: usize, y: usize, flag: u8, turns: u32) {
+        if x >= self.grid.get_width() || y >= self.grid.get_height() { return; }
+        let idx = y * self.grid.get_width() + x;
+        self.grid.schedule_hazard(idx, flag, turns);
+    }
+
+    pub fn preview_hazards(&self, window: u32) -> Vec<u32> {
+        self.grid.preview_hazards(window)
+    }
+
+    pub fn compute_match_heatmap(&mut self) -> Vec<f32> {
+        self.grid.compute_match_heatmap()
+    }
+
+    #[cfg(feature = "wasm")]
     pub fn get_fluid_events(&self) -> JsValue {
         JsValue::UNDEFINED
     }
@@ -241,8 +861,104 @@ impl Simulation {
     pub fn is_avatar_state(&self) -> bool {
         self.grid.is_avatar_state()
     }
+
+    pub fn set_cycle_mode(&mut self, mode: crate::sim::cycle::CycleMode) {
+        self.grid.set_cycle_mode(mode);
+    }
+
+    pub fn set_dual_cycle(&mut self, enabled: bool) {
+        self.grid.set_dual_cycle(enabled);
+    }
+
+    pub fn set_momentum_decay(&mut self, turns: u32) {
+        self.grid.set_momentum_decay(turns);
+    }
+
+    pub fn set_cycle_skip_charges(&mut self, charges: u32) {
+        self.grid.set_cycle_skip_charges(charges);
+    }
+
+    pub fn get_cycle_skip_charges(&self) -> u32 {
+        self.grid.get_cycle_skip_charges()
+    }
+
+    // Booster skip target (xem GridState::skip_cycle_target)
+    pub fn skip_cycle_target(&mut self) -> bool {
+        if self.photo_mode { return false; }
+        self.grid.skip_cycle_target()
+    }
+
+    pub fn record_moves(&mut self, enabled: bool) {
+        self.grid.record_moves(enabled);
+    }
+
+    pub fn is_recording_moves(&self) -> bool {
+        self.grid.is_recording_moves()
+    }
+
+    pub fn get_move_journal(&self) -> Vec<u8> {
+        self.grid.get_move_journal()
+    }
+
+    pub fn get_move_journal_compact(&self) -> Vec<u8> {
+        self.grid.get_move_journal_compact()
+    }
+
+    pub fn clear_move_journal(&mut self) {
+        self.grid.clear_move_journal();
+    }
+
+    pub fn set_cycle_grace(&mut self, misses: u32) {
+        self.grid.set_cycle_grace(misses);
+    }
+
+    pub fn get_cycle_grace(&self) -> u32 {
+        self.grid.get_cycle_grace()
+    }
+
+    pub fn get_cycle_grace_left(&self) -> u32 {
+        self.grid.get_cycle_grace_left()
+    }
+
+    pub fn get_dual_cycle_grace_left(&self) -> u32 {
+        self.grid.get_dual_cycle_grace_left()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn export_cycle_state(&self) -> JsValue {
+        self.grid.export_cycle_state()
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn import_cycle_state(&mut self, state: JsValue) -> bool {
+        self.grid.import_cycle_state(state)
+    }
+
+    pub fn switch_cycle_track(&mut self) -> crate::sim::cycle::CycleMode {
+        self.grid.switch_cycle_track()
+    }
+
+    pub fn get_dual_cycle_target(&self) -> u8 {
+        self.grid.get_dual_cycle_target()
+    }
+
+    pub fn get_dual_cycle_chain(&self) -> u32 {
+        self.grid.get_dual_cycle_chain()
+    }
+
+    pub fn get_dual_cycle_multiplier(&self) -> u32 {
+        self.grid.get_dual_cycle_multiplier()
+    }
+
+    pub fn get_resonance_count(&self) -> u32 {
+        self.grid.get_resonance_count()
+    }
     
     // Setters
+    pub fn apply_level_layout(&mut self, layout: &[u8]) -> Vec<u32> {
+        self.grid.apply_level_layout(layout)
+    }
+
     pub fn set_cell_element(&mut self, idx: usize, element: u8) {
         self.grid.set_cell_element(idx, element);
     }
@@ -259,7 +975,102 @@ impl Simulation {
         self.grid.spawn_special(count, element, flags, exclude_element)
     }
 
+    pub fn convert_area(&mut self, center_idx: usize, radius: usize, element: u8) -> Vec<usize> {
+        self.grid.convert_area(center_idx, radius, element)
+    }
+
+    pub fn set_resolution_policy(&mut self, policy: crate::sim::grid::ResolutionPolicy) {
+        self.grid.set_resolution_policy(policy);
+    }
+
+    // SANDBOX BRIDGE
+    pub fn set_sandbox(&mut self, enabled: bool) {
+        self.grid.set_sandbox(enabled);
+    }
+
+    pub fn is_submittable(&self) -> bool {
+        self.grid.is_submittable()
+    }
+
+    pub fn sandbox_place(&mut self, idx: usize, element: u8, flags: u8) -> bool {
+        self.grid.sandbox_place(idx, element, flags)
+    }
+
+    pub fn sandbox_trigger(&mut self, center_idx: usize, element: u8) -> bool {
+        self.grid.sandbox_trigger(center_idx, element)
+    }
+
+    pub fn set_rng_frozen(&mut self, frozen: bool) {
+        self.grid.set_rng_frozen(frozen);
+    }
+
+    pub fn get_rng_state(&self) -> Vec<u8> {
+        self.grid.get_rng_state()
+    }
+
+    pub fn set_rng_state(&mut self, data: &[u8]) -> bool {
+        self.grid.set_rng_state(data)
+    }
+
+    pub fn boss_roll(&mut self, n: u32) -> u32 {
+        self.grid.boss_roll(n)
+    }
+
+    pub fn get_shared_rng(&self, key: u32) -> SharedRng {
+        self.grid.get_shared_rng(key)
+    }
+
+    pub fn get_state_hash(&self) -> u64 {
+        self.grid.get_state_hash()
+    }
+
     pub fn get_checksum(&self) -> u32 {
         self.grid.get_checksum()
     }
+
+    pub fn get_rules_hash(&self) -> u64 {
+        self.grid.get_rules_hash()
+    }
+}
+
+impl Simulation {
+    fn default_schedule() -> Schedule<Simulation> {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Input, "ecs_events", |s: &mut Simulation, _| s.world.update_events())
+            .add_system(Stage::Input, "change_tick", |s: &mut Simulation, _| { s.world.advance_change_tick(); })
+            .add_system(Stage::Logic, "movement", |s: &mut Simulation, dt| MovementSystem::update(&mut s.world, dt))
+            .add_system(Stage::Logic, "lifetime", |s: &mut Simulation, _| LifetimeSystem::update(&mut s.world))
+            .add_system(Stage::Grid, "grid", |s: &mut Simulation, _| s.grid.tick())
+            .add_system(Stage::Grid, "cell_binding", |s: &mut Simulation, _| CellBindingSystem::update(&mut s.world, &s.grid, &s.cell_mapping))
+            .add_system(Stage::Post, "ecs_event_forward", |s: &mut Simulation, _| s.forward_ecs_events())
+            .add_system(Stage::Post, "event_spawn", |s: &mut Simulation, _| s.spawn_from_events())
+            .add_system(Stage::Post, "autosave", |s: &mut Simulation, _| s.autosave_tick());
+        schedule
+    }
+
+    // Nhiều Simulation độc lập (desktop / bot) trên rayon pool, mỗi cái như update(dt_ms)
+    #[cfg(feature = "parallel")]
+    pub fn update_all(sims: &mut [Simulation], dt_ms: f64) {
+        use rayon::prelude::*;
+        sims.par_iter_mut().for_each(|sim| { sim.update(dt_ms); });
+    }
+
+    // World::stats có kiểu cho native / soak test (wasm: get_world_stats)
+    pub fn world_stats(&self) -> WorldStats {
+        self.world.stats()
+    }
+
+    // Thêm system native (debug overlay, AI...) vào stage bất kỳ
+    pub fn schedule_mut(&mut self) -> &mut Schedule<Simulation> {
+        &mut self.schedule
+    }
+
+    // Photo mode có kiểu cho native / test (wasm: enter_photo_mode)
+    pub fn capture_photo(&mut self) -> PhotoState {
+        self.photo_mode = true;
+        self.sync_buffers();
+        let alpha = self.accumulator / FIXED_DT;
+        PhotoState::capture(&self.grid, &self.entity_ids, &self.positions, &self.archetypes, alpha)
+    }
 }
@@ -0,0 +1,273 @@
+use rand_chacha::ChaCha8Rng;
+use rand::SeedableRng;
+use super::cycle::{CycleMode, CycleState, AVATAR_MAX_TURNS};
+use super::rng::{GameRng, Pcg32, RNG_STREAM_COUNT};
+
+// --- BINARY SNAPSHOT ---
+// Trạng thái gameplay của GridState dạng byte (little-endian), đủ để resume đúng chỗ
+// và tiếp tục ra cùng chuỗi refill. Không gồm buffer event / audio (thuần hiển thị).
+//
+// Header: MAGIC(4) | version u16. Thêm field thì append cuối + tăng SNAPSHOT_VERSION.
+//
+// MIGRATION: reader đọc mọi version từ SNAPSHOT_MIN_VERSION, field mới hơn version của
+// snapshot lấy giá trị mặc định (= hành vi trước khi có field đó). Writer gate theo cùng
+// bảng nên ghi được cả version cũ, test roundtrip từng version để 2 phía không lệch nhau.
+// GridState::migrate nâng 1 save cũ lên SNAPSHOT_VERSION (đọc theo version cũ, ghi lại bản mới).
+//   v1: bản đầu (autosave)
+//   v2: CycleState.grace_per_chain / grace_left
+//   v3: drought pity + drought tracker
+//   v4: per-player cycle tracks + người chơi đang đi
+//   v5: CycleState.momentum_decay_turns / idle_turns (trước đây đếm theo tick, cùng layout)
+//   v6: PlayerTrack.role_mask + lượt co-op
+//   v7: charge booster skip target + move journal
+//   v8: ScoreBreakdown
+//   v9: SessionStats
+//   v10: điểm (score, điểm người chơi, breakdown) u64
+//   v11: XP (curve, rewards, xp)
+//   v12: cờ record_moves
+//   v13: seed của ván (header replay file)
+//   v14: RNG Pcg32 (byte loại RNG, ChaCha8 chỉ còn cho seed legacy)
+//   v15: Pcg32 tách stream theo subsystem (số stream + state từng stream)
+//   v16: tham số board noise (thuộc rules hash)
+
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"LISN";
+pub const SNAPSHOT_VERSION: u16 = 16;
+pub const SNAPSHOT_MIN_VERSION: u16 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadHeader,
+    UnsupportedVersion(u16),
+    Truncated,
+    Invalid, // Giá trị ngoài miền (enum lạ, kích thước board sai...)
+    VersionMismatch(u16), // migrate: header ghi version khác version caller khai báo
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::BadHeader => f.write_str("not a grid snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            SnapshotError::Truncated => f.write_str("snapshot truncated"),
+            SnapshotError::Invalid => f.write_str("snapshot contains invalid values"),
+            SnapshotError::VersionMismatch(v) => write!(f, "snapshot header says version {}", v),
+        }
+    }
+}
+
+pub struct SnapshotWriter {
+    buf: Vec<u8>,
+    version: u16,
+}
+
+impl SnapshotWriter {
+    // Ghi theo layout của `version` (cho client cũ / test migration)
+    pub fn with_version(version: u16) -> Self {
+        let mut buf = Vec::with_capacity(256);
+        buf.extend_from_slice(SNAPSHOT_MAGIC);
+        buf.extend_from_slice(&version.to_le_bytes());
+        Self { buf, version }
+    }
+
+    // Layout có field được thêm ở `version` không
+    pub fn since(&self, version: u16) -> bool {
+        self.version >= version
+    }
+
+    pub fn u8(&mut self, v: u8) { self.buf.push(v); }
+    pub fn bool(&mut self, v: bool) { self.buf.push(v as u8); }
+    pub fn u32(&mut self, v: u32) { self.buf.extend_from_slice(&v.to_le_bytes()); }
+    pub fn u64(&mut self, v: u64) { self.buf.extend_from_slice(&v.to_le_bytes()); }
+    pub fn u128(&mut self, v: u128) { self.buf.extend_from_slice(&v.to_le_bytes()); }
+    pub fn bytes(&mut self, v: &[u8]) {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v);
+    }
+
+    pub fn opt_u32(&mut self, v: Option<u32>) {
+        self.bool(v.is_some());
+        self.u32(v.unwrap_or(0));
+    }
+
+    // Điểm: u64 từ v10, trước đó u32
+    pub fn score(&mut self, v: u64) {
+        if self.since(10) { self.u64(v) } else { self.u32(v.min(u32::MAX as u64) as u32) }
+    }
+
+    // RNG: khôi phục đúng vị trí trong chuỗi. Từ v14: byte loại | Pcg (state, inc) hoặc ChaCha,
+    // từ v15 Pcg = số stream | (state, inc) từng stream.
+    // Layout cũ chỉ có ChaCha: ván Pcg ghi ChaCha mặc định (client cũ không bao giờ có ván Pcg).
+    pub fn rng(&mut self, rng: &GameRng) {
+        if !self.since(14) {
+            let placeholder = ChaCha8Rng::seed_from_u64(0);
+            return self.chacha(match rng { GameRng::Legacy(r) => r, GameRng::Pcg(_) => &placeholder });
+        }
+        match rng {
+            GameRng::Pcg(rngs) => {
+                self.u8(0);
+                // v14 chỉ có 1 stream
+                let streams = if self.since(15) { &rngs[..] } else { &rngs[..1] };
+                if self.since(15) { self.u8(streams.len() as u8); }
+                for r in streams {
+                    let (state, inc) = r.to_parts();
+                    self.u64(state);
+                    self.u64(inc);
+                }
+            }
+            GameRng::Legacy(r) => {
+                self.u8(1);
+                self.chacha(r);
+            }
+        }
+    }
+
+    // ChaCha = (seed, stream, word pos)
+    fn chacha(&mut self, rng: &ChaCha8Rng) {
+        self.buf.extend_from_slice(&rng.get_seed());
+        self.u64(rng.get_stream());
+        self.u128(rng.get_word_pos());
+    }
+
+    pub fn cycle(&mut self, c: &CycleState) {
+        self.u8(c.mode as u8);
+        self.u8(c.target);
+        self.u32(c.chain_length);
+        self.u32(c.multiplier);
+        self.bool(c.is_avatar_state);
+        self.u32(c.avatar_matches_left);
+        self.u32(c.avatar_turns_left);
+        if self.since(2) {
+            self.u32(c.grace_per_chain);
+            self.u32(c.grace_left);
+        }
+        if self.since(5) {
+            self.u32(c.momentum_decay_turns);
+            self.u32(c.idle_turns);
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+// Version trong header (không kiểm tra có hỗ trợ hay không)
+pub fn snapshot_version(data: &[u8]) -> Result<u16, SnapshotError> {
+    if data.len() < 6 || &data[..4] != SNAPSHOT_MAGIC { return Err(SnapshotError::BadHeader); }
+    Ok(u16::from_le_bytes([data[4], data[5]]))
+}
+
+pub struct SnapshotReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    version: u16,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, SnapshotError> {
+        let version = snapshot_version(data)?;
+        if !(SNAPSHOT_MIN_VERSION..=SNAPSHOT_VERSION).contains(&version) {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        Ok(Self { data, pos: 6, version })
+    }
+
+    // Snapshot có field được thêm ở `version` không (không có -> caller dùng mặc định)
+    pub fn since(&self, version: u16) -> bool {
+        self.version >= version
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self.pos.checked_add(n).ok_or(SnapshotError::Truncated)?;
+        let slice = self.data.get(self.pos..end).ok_or(SnapshotError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, SnapshotError> { Ok(self.take(1)?[0]) }
+    pub fn bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+    pub fn u32(&mut self) -> Result<u32, SnapshotError> { Ok(u32::from_le_bytes(self.array()?)) }
+    pub fn u64(&mut self) -> Result<u64, SnapshotError> { Ok(u64::from_le_bytes(self.array()?)) }
+    pub fn u128(&mut self) -> Result<u128, SnapshotError> { Ok(u128::from_le_bytes(self.array()?)) }
+    pub fn bytes(&mut self) -> Result<&'a [u8], SnapshotError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub fn score(&mut self) -> Result<u64, SnapshotError> {
+        if self.since(10) { self.u64() } else { Ok(self.u32()? as u64) }
+    }
+
+    pub fn opt_u32(&mut self) -> Result<Option<u32>, SnapshotError> {
+        let some = self.bool()?;
+        let v = self.u32()?;
+        Ok(if some { Some(v) } else { None })
+    }
+
+    pub fn rng(&mut self) -> Result<GameRng, SnapshotError> {
+        if !self.since(14) { return Ok(GameRng::Legacy(Box::new(self.chacha()?))); }
+        match self.u8()? {
+            0 => {
+                // v14: 1 stream dùng cho mọi subsystem
+                let count = if self.since(15) { self.u8()? as usize } else { 1 };
+                if self.since(15) && count != RNG_STREAM_COUNT { return Err(SnapshotError::Invalid); }
+                let mut streams = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let (state, inc) = (self.u64()?, self.u64()?);
+                    streams.push(Pcg32::from_parts(state, inc).ok_or(SnapshotError::Invalid)?);
+                }
+                Ok(GameRng::Pcg(std::array::from_fn(|i| streams[i.min(count - 1)])))
+            }
+            1 => Ok(GameRng::Legacy(Box::new(self.chacha()?))),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+
+    fn chacha(&mut self) -> Result<ChaCha8Rng, SnapshotError> {
+        let mut rng = ChaCha8Rng::from_seed(self.array()?);
+        rng.set_stream(self.u64()?);
+        rng.set_word_pos(self.u128()?);
+        Ok(rng)
+    }
+
+    pub fn cycle(&mut self) -> Result<CycleState, SnapshotError> {
+        let mode = CycleMode::from_id(self.u8()?).ok_or(SnapshotError::Invalid)?;
+        let mut cycle = CycleState {
+            mode,
+            target: self.u8()?,
+            chain_length: self.u32()?,
+            multiplier: self.u32()?,
+            is_avatar_state: self.bool()?,
+            avatar_matches_left: self.u32()?,
+            // Save cũ đếm theo tick (tối đa 600): chặn về số lượt
+            avatar_turns_left: self.u32()?.min(AVATAR_MAX_TURNS),
+            ..CycleState::with_mode(mode)
+        };
+        if self.since(2) {
+            cycle.grace_per_chain = self.u32()?;
+            cycle.grace_left = self.u32()?;
+        }
+        if self.since(5) {
+            cycle.momentum_decay_turns = self.u32()?;
+            cycle.idle_turns = self.u32()?;
+        }
+        Ok(cycle)
+    }
+
+    // Snapshot phải được đọc hết, dư byte = hỏng / sai version
+    pub fn finish(self) -> Result<(), SnapshotError> {
+        if self.pos == self.data.len() { Ok(()) } else { Err(SnapshotError::Invalid) }
+    }
+}
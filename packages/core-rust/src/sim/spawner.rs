@@ -0,0 +1,34 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use super::events::EventType;
+
+// --- EVENT -> ENTITY SPAWN BINDINGS ---
+// Bảng data-driven: grid event nào sinh entity hiệu ứng nào, sống bao lâu.
+// Simulation chạy bảng này sau mỗi lần grid thay đổi (xem EventSpawnSystem),
+// nên thêm hiệu ứng mới = thêm 1 dòng ở đây hoặc add_spawn_binding từ JS, không sửa tick().
+// Chỉ event được ghi vào buffer mới spawn: category bị set_event_mask tắt thì không spawn.
+
+// Archetype có sẵn; frontend tự map id -> sprite / shader. Id khác dùng thoải mái qua add_spawn_binding.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectArchetype {
+    AvatarAura = 1,
+    SpecialBurst = 2,
+    ResonanceRing = 3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpawnBinding {
+    pub event_type: u8,
+    pub archetype: u8,
+    pub lifetime_ticks: u32,  // Sim tick (60Hz)
+    pub anchor_to_cell: bool, // true = gắn ô của event (CellAnchor), đi theo tile / huỷ khi ô bị clear
+}
+
+pub const DEFAULT_SPAWN_BINDINGS: &[SpawnBinding] = &[
+    SpawnBinding { event_type: EventType::CycleCompleteSinh as u8, archetype: EffectArchetype::AvatarAura as u8, lifetime_ticks: 120, anchor_to_cell: false },
+    SpawnBinding { event_type: EventType::CycleCompleteKhac as u8, archetype: EffectArchetype::AvatarAura as u8, lifetime_ticks: 120, anchor_to_cell: false },
+    SpawnBinding { event_type: EventType::SpecialActivation as u8, archetype: EffectArchetype::SpecialBurst as u8, lifetime_ticks: 30, anchor_to_cell: true },
+    SpawnBinding { event_type: EventType::Resonance as u8, archetype: EffectArchetype::ResonanceRing as u8, lifetime_ticks: 90, anchor_to_cell: false },
+];
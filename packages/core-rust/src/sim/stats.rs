@@ -0,0 +1,57 @@
+use serde::Serialize;
+use super::events::MAX_CLEAR_TYPE;
+use super::scoring::ScoreBreakdown;
+use super::xp::XpProgress;
+
+// --- SESSION STATS ---
+// Thống kê cả phiên chơi cho meta layer / analytics, đếm ngay trong core nên JS không phải
+// tự instrument. Không ảnh hưởng gameplay, không nằm trong rules hash.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SessionStats {
+    pub matches_by_element: [u32; MAX_CLEAR_TYPE as usize + 1], // Index = element
+    pub largest_cluster: u32,   // Số ô của cụm match lớn nhất
+    pub longest_cascade: u32,   // Độ sâu cascade lớn nhất sau 1 swap
+    pub longest_chain: u32,     // Chuỗi chu trình dài nhất (mọi track)
+    pub destructions: u32,      // Số lần Tương Khắc kích hoạt
+    pub generations: u32,       // Số lần Tương Sinh kích hoạt
+    pub swaps_attempted: u32,
+    pub swaps_rejected: u32,
+}
+
+impl SessionStats {
+    pub fn record_match(&mut self, element: u8, cluster: usize) {
+        if let Some(count) = self.matches_by_element.get_mut(element as usize) {
+            *count += 1;
+        }
+        self.largest_cluster = self.largest_cluster.max(cluster as u32);
+    }
+
+    pub fn record_swap(&mut self, accepted: bool) {
+        self.swaps_attempted += 1;
+        if !accepted { self.swaps_rejected += 1; }
+    }
+
+    pub fn total_matches(&self) -> u32 {
+        self.matches_by_element.iter().sum()
+    }
+}
+
+// --- GAME SUMMARY ---
+// Kết quả cuối ván. rules_hash giống header replay file: server so với
+// GridState::official_rules_hash để biết điểm đạt được dưới ruleset chính thức.
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GameSummary {
+    pub seed: u64,
+    pub rules_hash: u64,
+    pub score: u64,
+    pub checksum: u32,
+    pub turns: u32,      // Swap được chấp nhận
+    pub move_count: u32, // Record trong move journal (swap + booster)
+    pub bucket: Option<u32>, // A/B bucket, phân tích phía server biết ván chạy variant nào
+    pub player_scores: Vec<u64>, // Co-op / versus: điểm từng người theo thứ tự trong header
+    pub breakdown: ScoreBreakdown,
+    pub stats: SessionStats,
+    pub xp: XpProgress,
+}
@@ -1,14 +1,121 @@
 use crate::ecs::world::World;
-use crate::ecs::components::{Position, Velocity};
+use crate::ecs::change::ChangeTicks;
+use crate::ecs::commands::Commands;
+use crate::ecs::components::{Position, Velocity, CellAnchor, GridBound, Lifetime, Effect, EntityDied, Frozen};
+use crate::sim::grid::GridState;
+use crate::sim::coords::CellMapping;
+use crate::sim::events::GridEvent;
+use crate::sim::spawner::SpawnBinding;
 
 pub struct MovementSystem;
 
 impl MovementSystem {
     pub fn update(world: &mut World, dt: f64) {
-        // Use hecs query for efficient iteration
-        for (_, (pos, vel)) in world.inner_mut().query::<(&mut Position, &Velocity)>().iter() {
+        let tick = world.change_tick();
+        let moving = world.query_mut::<(&mut Position, &Velocity, Option<&mut ChangeTicks<Position>>)>().without::<&Frozen>();
+        for (_, (pos, vel, ticks)) in moving {
+            if vel.x == 0.0 && vel.y == 0.0 { continue; }
             pos.x += vel.x * dt as f32;
             pos.y += vel.y * dt as f32;
+            if let Some(ticks) = ticks { ticks.mark(tick); }
         }
     }
 }
+
+// Giữ entity gắn ô đồng bộ với board, đọc thay đổi ô của tick vừa chạy ngay trong sim
+// để không còn minion lơ lửng trên ô trống.
+pub struct CellBindingSystem;
+
+impl CellBindingSystem {
+    pub fn update(world: &mut World, grid: &GridState, mapping: &CellMapping) {
+        let moved = grid.moved_this_tick();
+        let cleared = grid.cleared_this_tick();
+        if moved.is_empty() && cleared.is_empty() { return; }
+
+        let width = grid.get_width();
+        let tick = world.change_tick();
+        let mut commands = Commands::new();
+        for (e, (anchor, pos, grid_bound, ticks)) in world.query_mut::<(&mut CellAnchor, Option<&mut Position>, Option<&GridBound>, Option<&mut ChangeTicks<Position>>)>() {
+            let cell = anchor.cell as usize;
+            if cleared.contains(&cell) {
+                let died = EntityDied { entity: e, cell: anchor.cell };
+                commands.despawn(e);
+                commands.add(move |world| { world.send_event(died); });
+                continue;
+            }
+            if let Some(&(_, to)) = moved.iter().find(|(from, _)| *from == cell) {
+                anchor.cell = to as u32;
+                if let Some(pos) = pos {
+                    *pos = Self::anchor_position(to, width, grid_bound.is_some(), mapping);
+                }
+                if let Some(ticks) = ticks { ticks.mark(tick); }
+            }
+        }
+        world.apply_commands(&mut commands);
+    }
+
+    // Swap thành công: entity đi theo tile của nó
+    pub fn apply_swap(world: &mut World, grid: &GridState, mapping: &CellMapping, idx1: usize, idx2: usize) {
+        let width = grid.get_width();
+        let tick = world.change_tick();
+        for (_, (anchor, pos, grid_bound, ticks)) in world.query_mut::<(&mut CellAnchor, Option<&mut Position>, Option<&GridBound>, Option<&mut ChangeTicks<Position>>)>() {
+            let to = match anchor.cell as usize {
+                c if c == idx1 => idx2,
+                c if c == idx2 => idx1,
+                _ => continue,
+            };
+            anchor.cell = to as u32;
+            if let Some(pos) = pos {
+                *pos = Self::anchor_position(to, width, grid_bound.is_some(), mapping);
+            }
+            if let Some(ticks) = ticks { ticks.mark(tick); }
+        }
+    }
+
+    pub(crate) fn anchor_position(cell: usize, width: usize, grid_bound: bool, mapping: &CellMapping) -> Position {
+        let in_cells = Position { x: (cell % width) as f32, y: (cell / width) as f32 };
+        if grid_bound { in_cells } else { mapping.cell_to_world(in_cells) }
+    }
+}
+
+// Sinh entity hiệu ứng theo bảng SpawnBinding cho các event mới của grid
+pub struct EventSpawnSystem;
+
+impl EventSpawnSystem {
+    pub fn run(
+        world: &mut World,
+        bindings: &[SpawnBinding],
+        events: impl Iterator<Item = GridEvent>,
+        width: usize,
+        mapping: &CellMapping,
+    ) {
+        if bindings.is_empty() { return; }
+        for event in events {
+            for binding in bindings.iter().filter(|b| b.event_type == event.event_type) {
+                let cell = event.y as usize * width + event.x as usize;
+                let e = world.spawn((
+                    CellBindingSystem::anchor_position(cell, width, false, mapping),
+                    Effect { archetype: binding.archetype, event_type: event.event_type },
+                    Lifetime { ticks: binding.lifetime_ticks },
+                ));
+                if binding.anchor_to_cell {
+                    world.add_component(e, CellAnchor { cell: cell as u32 });
+                }
+            }
+        }
+    }
+}
+
+// Đếm ngược Lifetime mỗi sim tick, hết thì despawn
+pub struct LifetimeSystem;
+
+impl LifetimeSystem {
+    pub fn update(world: &mut World) {
+        let mut commands = Commands::new();
+        for (e, lifetime) in world.query_mut::<&mut Lifetime>() {
+            lifetime.ticks = lifetime.ticks.saturating_sub(1);
+            if lifetime.ticks == 0 { commands.despawn(e); }
+        }
+        world.apply_commands(&mut commands);
+    }
+}
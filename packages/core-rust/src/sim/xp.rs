@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+// --- XP / LEVELING ---
+// Tiến trình người chơi tính ngay trong core: XP từ match / tương tác, level theo curve.
+// Deterministic như điểm -> replay validate được XP client khai báo.
+// Không ảnh hưởng gameplay, không nằm trong rules hash.
+
+// Trần max_level: level_for_xp / xp_for_level quét tuyến tính theo level
+pub const MAX_XP_LEVEL: u32 = 1000;
+
+// XP cần cho level tiếp theo: base, rồi nhân growth_pct% sau mỗi level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct XpCurve {
+    pub base: u32,
+    pub growth_pct: u32, // 100 = tuyến tính
+    pub max_level: u32,
+}
+
+impl XpCurve {
+    pub const DEFAULT: XpCurve = XpCurve { base: 500, growth_pct: 150, max_level: 99 };
+
+    // Tổng XP tích lũy để đạt `level` (level 1 = 0)
+    pub fn xp_for_level(&self, level: u32) -> u64 {
+        let mut total = 0u64;
+        let mut step = self.base.max(1) as u64;
+        for _ in 1..level.min(self.max_level) {
+            total = total.saturating_add(step);
+            step = (step.saturating_mul(self.growth_pct as u64) / 100).max(1);
+        }
+        total
+    }
+
+    // 1 lượt cộng dồn như xp_for_level, dừng ở level đầu tiên chưa đủ XP
+    pub fn level_for_xp(&self, xp: u64) -> u32 {
+        let mut level = 1;
+        let mut total = 0u64;
+        let mut step = self.base.max(1) as u64;
+        while level < self.max_level {
+            total = total.saturating_add(step);
+            if xp < total { break; }
+            level += 1;
+            step = (step.saturating_mul(self.growth_pct as u64) / 100).max(1);
+        }
+        level
+    }
+}
+
+impl Default for XpCurve {
+    fn default() -> Self {
+        XpCurve::DEFAULT
+    }
+}
+
+// XP thưởng cho từng loại hành động
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct XpRewards {
+    pub per_match: u32,
+    pub per_extra_cell: u32, // Mỗi ô vượt quá match-3
+    pub per_interaction: u32, // Tương Sinh / Tương Khắc kích hoạt
+}
+
+impl XpRewards {
+    pub const DEFAULT: XpRewards = XpRewards { per_match: 10, per_extra_cell: 5, per_interaction: 15 };
+
+    pub fn for_match(&self, cluster: usize) -> u64 {
+        self.per_match as u64 + self.per_extra_cell as u64 * cluster.saturating_sub(3) as u64
+    }
+}
+
+impl Default for XpRewards {
+    fn default() -> Self {
+        XpRewards::DEFAULT
+    }
+}
+
+// Trả về cho JS qua get_xp_progress
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct XpProgress {
+    pub xp: u64,
+    pub level: u32,
+    pub level_start: u64, // Tổng XP tại đầu level hiện tại
+    pub next_level: u64,  // Tổng XP cần cho level sau (= level_start khi đã max)
+}
+
+impl XpProgress {
+    pub fn new(curve: &XpCurve, xp: u64) -> Self {
+        let level = curve.level_for_xp(xp);
+        let next = if level < curve.max_level { level + 1 } else { level };
+        Self { xp, level, level_start: curve.xp_for_level(level), next_level: curve.xp_for_level(next) }
+    }
+}
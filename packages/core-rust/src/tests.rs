@@ -3,14 +3,14 @@ mod tests {
     use crate::ecs::world::World;
     use crate::ecs::component::Component;
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     struct Position {
         x: f32,
         y: f32,
     }
     // impl Component for Position {} // Removed: hecs has blanket impl
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     struct Velocity {
         x: f32,
         y: f32,
@@ -25,6 +25,411 @@ mod tests {
         assert!(!world.destroy_entity(entity)); // Should return false (already destroyed)
     }
 
+    #[test]
+    fn test_destroyed_entity_index_reuse_starts_clean() {
+        let mut world = World::new();
+        let old = world.create_entity();
+        world.add_component(old, Position { x: 1.0, y: 2.0 });
+        world.add_component(old, Velocity { x: 3.0, y: 4.0 });
+        assert!(world.destroy_entity(old));
+
+        let recycled = world.create_entity();
+        assert_eq!(recycled.id(), old.id());
+        assert_ne!(recycled, old);
+        assert!(world.get_component::<Position>(recycled).is_none());
+        assert!(world.get_component::<Velocity>(recycled).is_none());
+        assert_eq!(world.query::<&Position>().iter().count(), 0);
+        // Handle cũ không đọc / ghi được vào entity mới
+        world.add_component(old, Position { x: 9.0, y: 9.0 });
+        assert!(world.get_component::<Position>(old).is_none());
+        assert!(world.get_component::<Position>(recycled).is_none());
+    }
+
+    // Churn nặng trên cùng 1 index: handle cũ không bao giờ sống lại
+    #[test]
+    fn test_entity_churn_never_resurrects_stale_handles() {
+        use std::collections::BTreeSet;
+
+        let mut world = World::new();
+        let first = world.create_entity();
+        world.add_component(first, Position { x: 0.0, y: 0.0 });
+        let mut handles = BTreeSet::new();
+        handles.insert(first.to_bits().get());
+        let mut current = first;
+        for _ in 0..20_000 {
+            assert!(world.destroy_entity(current));
+            current = world.create_entity();
+            assert_eq!(current.id(), first.id());
+            assert!(handles.insert(current.to_bits().get()), "generation quay vòng");
+        }
+        assert!(!world.contains(first));
+        assert!(world.get_component::<Position>(first).is_none());
+        assert!(!world.destroy_entity(first));
+        assert!(world.contains(current));
+    }
+
+    #[test]
+    fn test_remove_component_keeps_entity() {
+        let mut world = World::new();
+        let entity = world.create_entity();
+        world.add_component(entity, Position { x: 1.0, y: 2.0 });
+        world.add_component(entity, Velocity { x: 3.0, y: 4.0 });
+
+        assert_eq!(world.remove_component::<Velocity>(entity), Some(Velocity { x: 3.0, y: 4.0 }));
+        assert_eq!(world.remove_component::<Velocity>(entity), None);
+        assert!(world.get_component::<Velocity>(entity).is_none());
+        assert_eq!(*world.get_component::<Position>(entity).unwrap(), Position { x: 1.0, y: 2.0 });
+
+        assert!(world.destroy_entity(entity));
+        assert_eq!(world.remove_component::<Position>(entity), None);
+    }
+
+    #[test]
+    fn test_world_resources() {
+        #[derive(Debug, PartialEq)]
+        struct TimeScale(f32);
+
+        let mut world = World::new();
+        assert!(world.get_resource::<TimeScale>().is_none());
+        assert_eq!(world.insert_resource(TimeScale(1.0)), None);
+        world.insert_resource(Position { x: 0.0, y: 0.0 }); // Kiểu khác -> slot riêng
+        world.get_resource_mut::<TimeScale>().unwrap().0 = 0.5;
+        assert_eq!(world.get_resource::<TimeScale>(), Some(&TimeScale(0.5)));
+        assert_eq!(world.insert_resource(TimeScale(2.0)), Some(TimeScale(0.5)));
+        assert_eq!(world.remove_resource::<TimeScale>(), Some(TimeScale(2.0)));
+        assert!(world.get_resource::<TimeScale>().is_none());
+        assert_eq!(world.get_resource::<Position>(), Some(&Position { x: 0.0, y: 0.0 }));
+    }
+
+    #[test]
+    fn test_world_events_double_buffered() {
+        use crate::ecs::events::EventReader;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Hit(u32);
+
+        let mut world = World::new();
+        assert!(!world.send_event(Hit(0))); // Chưa add_event -> bỏ
+        world.add_event::<Hit>();
+        let mut early = world.events_mut::<Hit>().unwrap().reader();
+        let mut late = EventReader::<Hit>::default();
+        assert_eq!(world.read_events(&mut early), vec![]);
+
+        world.send_event(Hit(1));
+        assert_eq!(world.read_events(&mut early), vec![Hit(1)]);
+        world.update_events();
+        world.send_event(Hit(2));
+        // Reader độc lập: early chỉ thấy event mới, late vẫn thấy cả frame trước
+        assert_eq!(world.read_events(&mut early), vec![Hit(2)]);
+        assert_eq!(world.read_events(&mut late), vec![Hit(1), Hit(2)]);
+        assert!(world.read_events(&mut late).is_empty());
+
+        // Sau 2 lần update event bị bỏ
+        let mut fresh = EventReader::<Hit>::default();
+        world.update_events();
+        world.update_events();
+        assert!(world.read_events(&mut fresh).is_empty());
+        world.send_event(Hit(3));
+        let events = world.events_mut::<Hit>().unwrap();
+        assert_eq!((events.len(), events.is_empty()), (1, false));
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![Hit(3)]);
+        assert!(world.read_events(&mut fresh).is_empty());
+    }
+
+    #[test]
+    fn test_world_change_detection() {
+        use crate::ecs::components::{Position, Velocity};
+
+        let mut world = World::new();
+        world.track_changes::<Position>();
+        let moving = world.create_entity();
+        world.add_component(moving, Position { x: 0.0, y: 0.0 });
+        world.add_component(moving, Velocity { x: 1.0, y: 0.0 }); // Không track
+        let still = world.create_entity();
+        world.add_component(still, Position { x: 5.0, y: 5.0 });
+
+        let spawned = world.advance_change_tick();
+        assert_eq!(world.added::<Position>(spawned - 1).len(), 2);
+        assert!(world.changed::<Position>(spawned).is_empty());
+        assert!(!world.any_changed_since::<Position>(spawned));
+        assert!(!world.any_changed_since::<Velocity>(0)); // Không track -> không bao giờ đổi
+
+        crate::sim::systems::MovementSystem::update(&mut world, 1.0);
+        assert_eq!(world.changed::<Position>(spawned), vec![moving]);
+        assert!(world.added::<Position>(spawned).is_empty());
+
+        let moved = world.advance_change_tick();
+        world.get_component_mut::<Position>(still).unwrap().x = 6.0;
+        assert_eq!(world.changed::<Position>(moved), vec![still]);
+
+        // Gỡ / huỷ không để lại dấu trong changed(), nhưng any_changed_since vẫn báo
+        let edited = world.advance_change_tick();
+        world.remove_component::<Position>(still);
+        assert!(world.changed::<Position>(edited).is_empty());
+        assert!(world.any_changed_since::<Position>(edited));
+    }
+
+    #[test]
+    fn test_commands_deferred_until_apply() {
+        use crate::ecs::commands::Commands;
+
+        let mut world = World::new();
+        let a = world.create_entity();
+        world.add_component(a, Position { x: 0.0, y: 0.0 });
+        let b = world.create_entity();
+        world.add_component(b, Position { x: -1.0, y: 0.0 });
+
+        let mut commands = Commands::new();
+        for (e, pos) in world.query_mut::<&mut Position>() {
+            if pos.x < 0.0 {
+                commands.despawn(e);
+                commands.spawn(|world, child| world.add_component(child, Position { x: 5.0, y: 5.0 }));
+            } else {
+                commands.insert(e, Velocity { x: 1.0, y: 0.0 });
+                commands.remove::<Position>(e);
+            }
+        }
+        assert_eq!(commands.len(), 4);
+        assert_eq!(world.query::<&Position>().iter().count(), 2); // Chưa apply
+
+        world.apply_commands(&mut commands);
+        assert!(commands.is_empty());
+        assert!(!world.contains(b));
+        assert!(world.get_component::<Position>(a).is_none());
+        assert_eq!(*world.get_component::<Velocity>(a).unwrap(), Velocity { x: 1.0, y: 0.0 });
+        let spawned: Vec<_> = world.query::<&Position>().iter().map(|(_, p)| (p.x, p.y)).collect();
+        assert_eq!(spawned, vec![(5.0, 5.0)]);
+    }
+
+    #[test]
+    fn test_world_save_load_round_trip() {
+        use crate::ecs::components::{CellAnchor, Position, Velocity};
+        use crate::ecs::save::WorldSaveError;
+
+        let mut world = World::new();
+        world.register_persistent::<Position>("position");
+        world.register_persistent::<CellAnchor>("cell_anchor");
+        let a = world.create_entity();
+        let b = world.create_entity();
+        world.destroy_entity(a); // Chừa lỗ trong allocator
+        world.add_component(b, Position { x: 1.5, y: -2.0 });
+        world.add_component(b, CellAnchor { cell: 7 });
+        world.add_component(b, Velocity { x: 9.0, y: 9.0 }); // Không đăng ký -> không lưu
+        let saved = world.save();
+
+        let mut restored = World::new();
+        restored.register_persistent::<Position>("position");
+        restored.register_persistent::<CellAnchor>("cell_anchor");
+        restored.create_entity();
+        assert_eq!(restored.load(&saved), Ok(()));
+        assert!(restored.contains(b));
+        assert_eq!(restored.query::<()>().iter().count(), 1);
+        let pos = *restored.get_component::<Position>(b).unwrap();
+        assert_eq!((pos.x, pos.y), (1.5, -2.0));
+        assert_eq!(restored.get_component::<CellAnchor>(b).unwrap().cell, 7);
+        assert!(restored.get_component::<Velocity>(b).is_none());
+        assert_eq!(restored.save(), saved);
+
+        // Save hỏng / thiếu kiểu: world giữ nguyên
+        let mut partial = World::new();
+        partial.register_persistent::<Position>("position");
+        assert_eq!(partial.load(&saved), Err(WorldSaveError::UnknownComponent("cell_anchor".to_string())));
+        assert_eq!(restored.load(&saved[..saved.len() - 1]), Err(WorldSaveError::Truncated));
+        assert!(restored.contains(b));
+    }
+
+    #[test]
+    fn test_prefab_spawn_with_overrides() {
+        use crate::ecs::prefab::Prefab;
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Damage(u32);
+
+        let mut world = World::new();
+        world.register_prefab("fireball", Prefab::new()
+            .with(Position { x: 0.0, y: 0.0 })
+            .with(Velocity { x: 4.0, y: 0.0 })
+            .with(Damage(10)));
+        assert!(world.has_prefab("fireball"));
+        assert_eq!(world.spawn_prefab("iceball", Prefab::new()), None);
+
+        let plain = world.spawn_prefab("fireball", Prefab::new()).unwrap();
+        let aimed = world.spawn_prefab("fireball", Prefab::new().with(Position { x: 3.0, y: 1.0 })).unwrap();
+        assert_ne!(plain, aimed);
+        assert_eq!(*world.get_component::<Position>(plain).unwrap(), Position { x: 0.0, y: 0.0 });
+        assert_eq!(*world.get_component::<Position>(aimed).unwrap(), Position { x: 3.0, y: 1.0 });
+        assert_eq!(*world.get_component::<Damage>(aimed).unwrap(), Damage(10));
+        assert_eq!(world.query::<(&Position, &Velocity, &Damage)>().iter().count(), 2);
+    }
+
+    #[test]
+    fn test_find_by_name_follows_name_changes() {
+        use crate::ecs::components::Name;
+        use crate::sim::simulation::Simulation;
+
+        let mut world = World::new();
+        let boss = world.create_entity();
+        world.add_component(boss, Name("boss_core".to_string()));
+        assert_eq!(world.find_by_name("boss_core"), Some(boss));
+        assert_eq!(world.find_by_name("player_1"), None);
+
+        world.add_component(boss, Name("boss_phase_2".to_string()));
+        assert_eq!(world.find_by_name("boss_core"), None);
+        assert_eq!(world.find_by_name("boss_phase_2"), Some(boss));
+        world.get_component_mut::<Name>(boss).unwrap().0 = "renamed".to_string();
+        assert_eq!(world.find_by_name("boss_phase_2"), None); // Sửa tại chỗ không vào index
+        world.remove_component::<Name>(boss);
+        world.add_component(boss, Name("boss_core".to_string()));
+        world.destroy_entity(boss);
+        assert_eq!(world.find_by_name("boss_core"), None);
+
+        let mut sim = Simulation::new(6, 6, 1);
+        let player = sim.find_entity_by_name("player_1");
+        assert_ne!(player, 0);
+        assert!(sim.set_entity_name(player, "hero"));
+        assert_eq!(sim.find_entity_by_name("hero"), player);
+        assert_eq!(sim.find_entity_by_name("player_1"), 0);
+    }
+
+    #[test]
+    fn test_world_iteration() {
+        let mut world = World::new();
+        let a = world.create_entity();
+        world.add_component(a, Position { x: 1.0, y: 0.0 });
+        let b = world.create_entity();
+        world.add_component(b, Velocity { x: 0.0, y: 0.0 });
+        let c = world.create_entity();
+        world.add_component(c, Position { x: 2.0, y: 0.0 });
+        world.destroy_entity(b);
+
+        let mut alive: Vec<_> = world.entities().collect();
+        alive.sort_by_key(|e| e.id());
+        assert_eq!(alive, vec![a, c]);
+        for (_, pos) in world.iter_mut::<Position>() {
+            pos.y = pos.x * 10.0;
+        }
+        let mut seen: Vec<_> = world.iter::<Position>().map(|(e, pos)| (e, pos.y)).collect();
+        seen.sort_by_key(|(e, _)| e.id());
+        assert_eq!(seen, vec![(a, 10.0), (c, 20.0)]);
+        assert_eq!(world.iter::<Velocity>().count(), 0);
+    }
+
+    #[test]
+    fn test_spawn_batch() {
+        use crate::ecs::components::{Name, Position, Velocity};
+
+        let mut world = World::new();
+        world.track_changes::<Position>();
+        let since = world.advance_change_tick();
+        let burst = world.spawn_batch(50, |i| (Position { x: i as f32, y: 0.0 }, Velocity { x: 0.0, y: -1.0 }));
+        assert_eq!(burst.len(), 50);
+        assert_eq!(world.get_component::<Position>(burst[7]).unwrap().x, 7.0);
+        assert_eq!(world.query::<(&Position, &Velocity)>().iter().count(), 50);
+        assert_eq!(world.added::<Position>(since).len(), 50); // Change detection thấy cả entity spawn theo lô
+
+        let named = world.spawn_batch(1, |_| (Name("wave_leader".to_string()),));
+        assert_eq!(world.find_by_name("wave_leader"), Some(named[0]));
+        assert!(world.spawn_batch(0, |_| (Name(String::new()),)).is_empty());
+    }
+
+    #[test]
+    fn test_despawn_recursive_removes_subtree() {
+        use crate::ecs::components::Parent;
+
+        let mut world = World::new();
+        let boss = world.create_entity();
+        let arm = world.create_entity();
+        world.add_component(arm, Parent(boss));
+        let claw = world.create_entity();
+        world.add_component(claw, Parent(arm));
+        world.add_component(claw, Position { x: 0.0, y: 0.0 });
+        let core = world.create_entity();
+        world.add_component(core, Parent(boss));
+        let bystander = world.create_entity();
+        let emitter = world.create_entity();
+        world.add_component(bystander, Parent(emitter));
+
+        assert_eq!(world.children(boss).len(), 2);
+        assert_eq!(world.despawn_recursive(arm), 2);
+        assert!(world.contains(boss) && !world.contains(claw));
+        assert_eq!(world.children(boss), vec![core]);
+        assert_eq!(world.despawn_recursive(boss), 2);
+        assert_eq!(world.despawn_recursive(boss), 0);
+        assert!(world.contains(bystander) && world.contains(emitter));
+        assert_eq!(world.query::<&Position>().iter().count(), 0);
+
+        // Chuỗi sâu (mỗi tầng 1 con) và Parent vòng: mỗi entity huỷ đúng 1 lần
+        let root = world.create_entity();
+        let mut tail = root;
+        for _ in 0..2000 {
+            let next = world.create_entity();
+            world.add_component(next, Parent(tail));
+            tail = next;
+        }
+        assert_eq!(world.despawn_recursive(root), 2001);
+        world.add_component(emitter, Parent(bystander));
+        assert_eq!(world.despawn_recursive(emitter), 2);
+        assert_eq!(world.entities().count(), 0);
+    }
+
+    #[test]
+    fn test_world_stats_counts_components() {
+        use crate::ecs::components::{Lifetime, Position};
+        use crate::sim::simulation::Simulation;
+
+        let mut world = World::new();
+        world.register_component::<Position>();
+        for i in 0..3 {
+            let e = world.create_entity();
+            world.add_component(e, Position { x: i as f32, y: 0.0 });
+            if i == 0 { world.add_component(e, Lifetime { ticks: 1 }); } // Chưa đăng ký
+        }
+        world.create_entity();
+        let stats = world.stats();
+        assert_eq!(stats.entities, 4);
+        assert_eq!(stats.components.len(), 1);
+        assert!(stats.components[0].name.ends_with("Position"));
+        assert_eq!(stats.components[0].count, 3);
+        assert_eq!(stats.component_bytes, 3 * std::mem::size_of::<Position>());
+        assert_eq!(stats.unregistered_components, 1);
+
+        // Soak: chạy 1 lúc không được rò entity
+        let mut sim = Simulation::new(6, 6, 3);
+        let before = sim.world_stats();
+        assert_eq!(before.entities, 1);
+        assert_eq!(before.named_entities, 1);
+        for _ in 0..120 { sim.update(16.0); }
+        assert_eq!(sim.world_stats().entities, before.entities);
+    }
+
+    #[test]
+    fn test_bundle_spawn_and_insert() {
+        use crate::ecs::components::{Name, Position, Velocity};
+
+        let mut world = World::new();
+        world.track_changes::<Position>();
+        world.track_changes::<Velocity>();
+        let since = world.advance_change_tick();
+        let e = world.spawn((Position { x: 1.0, y: 2.0 }, Name("drone".to_string())));
+        assert_eq!(world.find_by_name("drone"), Some(e));
+        assert_eq!(world.added::<Position>(since), vec![e]);
+
+        assert!(world.insert(e, (Velocity { x: 3.0, y: 0.0 }, Position { x: 5.0, y: 5.0 })));
+        assert_eq!(world.get_component::<Position>(e).unwrap().x, 5.0);
+        assert_eq!(world.added::<Velocity>(since), vec![e]);
+        assert_eq!(world.query::<(&Position, &Velocity, &Name)>().iter().count(), 1);
+
+        // Thay component đã track: changed, không phải added; kiểu ngoài bundle không bị đánh dấu
+        let since = world.advance_change_tick();
+        assert!(world.insert(e, (Position { x: 7.0, y: 7.0 },)));
+        assert_eq!(world.changed::<Position>(since), vec![e]);
+        assert!(world.added::<Position>(since).is_empty());
+        assert!(world.changed::<Velocity>(since).is_empty());
+
+        world.destroy_entity(e);
+        assert!(!world.insert(e, (Velocity { x: 0.0, y: 0.0 },)));
+    }
+
     #[test]
     fn test_component_storage() {
         let mut world = World::new();
@@ -44,4 +449,693 @@ mod tests {
         assert_eq!(vel.x, 1.0);
         assert_eq!(vel.y, 1.0);
     }
+
+    #[test]
+    fn test_world_query_visits_only_matching_entities() {
+        let mut world = World::new();
+        let moving = world.create_entity();
+        world.add_component(moving, Position { x: 0.0, y: 0.0 });
+        world.add_component(moving, Velocity { x: 1.0, y: 2.0 });
+        let still = world.create_entity();
+        world.add_component(still, Position { x: 5.0, y: 5.0 });
+        world.create_entity();
+
+        for (_, (pos, vel)) in world.query_mut::<(&mut Position, &Velocity)>() {
+            pos.x += vel.x;
+            pos.y += vel.y;
+        }
+        let moved: Vec<_> = world.query::<(&Position, &Velocity)>().iter().map(|(e, _)| e).collect();
+        assert_eq!(moved, vec![moving]);
+        assert_eq!(*world.get_component::<Position>(moving).unwrap(), Position { x: 1.0, y: 2.0 });
+        assert_eq!(*world.get_component::<Position>(still).unwrap(), Position { x: 5.0, y: 5.0 });
+        // Option<&T>: mọi entity có Position
+        assert_eq!(world.query::<(&Position, Option<&Velocity>)>().iter().count(), 2);
+    }
+
+    #[test]
+    fn test_query_with_without_filters() {
+        use hecs::{With, Without};
+
+        struct Player;
+        struct Frozen;
+
+        let mut world = World::new();
+        let player = world.create_entity();
+        world.add_component(player, Position { x: 0.0, y: 0.0 });
+        world.add_component(player, Player);
+        let frozen = world.create_entity();
+        world.add_component(frozen, Position { x: 1.0, y: 1.0 });
+        world.add_component(frozen, Player);
+        world.add_component(frozen, Frozen);
+        let npc = world.create_entity();
+        world.add_component(npc, Position { x: 2.0, y: 2.0 });
+
+        let active: Vec<_> = world.query::<&Position>().with::<&Player>().without::<&Frozen>().iter().map(|(e, _)| e).collect();
+        assert_eq!(active, vec![player]);
+        for (_, pos) in world.query_mut::<&mut Position>().without::<&Player>() {
+            pos.x = 9.0;
+        }
+        assert_eq!(world.get_component::<Position>(npc).unwrap().x, 9.0);
+        assert_eq!(world.query::<Without<With<&Position, &Player>, &Frozen>>().iter().count(), 1);
+    }
+
+    #[test]
+    fn test_frozen_entity_skips_movement() {
+        use crate::sim::simulation::Simulation;
+
+        let mut sim = Simulation::new(6, 6, 1);
+        let photo = |sim: &mut Simulation| {
+            let state = sim.capture_photo();
+            sim.exit_photo_mode();
+            (state.entities[0].id, state.entities[0].x, state.entities[0].y)
+        };
+        let (id, x, y) = photo(&mut sim);
+        assert!(sim.set_entity_frozen(id, true));
+        sim.update(100.0);
+        assert_eq!(photo(&mut sim), (id, x, y));
+        assert!(sim.set_entity_frozen(id, false));
+        sim.update(100.0);
+        assert_ne!(photo(&mut sim), (id, x, y));
+        assert!(!sim.set_entity_frozen(u64::MAX, true));
+    }
+
+    // Feature `parallel` chạy board trên thread pool
+    #[test]
+    fn test_boards_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<crate::api::Game>();
+        assert_send::<crate::sim::simulation::Simulation>();
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_step_matches_sequential() {
+        use crate::api::{step_all, validate_replay_checked, validate_replays_checked, Game, GameConfig, ReplayJob, ReplayLimits};
+
+        let configs: Vec<GameConfig> = (0..6).map(|seed| GameConfig { seed, ..GameConfig::default() }).collect();
+        let mut games: Vec<Game> = configs.iter().map(Game::new).collect();
+        step_all(&mut games, 40);
+        for (config, game) in configs.iter().zip(&games) {
+            let mut sequential = Game::new(config);
+            for _ in 0..40 { sequential.step(); }
+            assert_eq!(game.state_hash(), sequential.state_hash());
+        }
+
+        let moves = [0u8, 0, 1, 0];
+        let jobs: Vec<ReplayJob> = configs.iter().map(|config| ReplayJob { config, moves: &moves, move_ticks: &[] }).collect();
+        let results = validate_replays_checked(&jobs, &ReplayLimits::DEFAULT);
+        for (config, result) in configs.iter().zip(results) {
+            assert_eq!(result, validate_replay_checked(config, &moves, &[], &ReplayLimits::DEFAULT));
+        }
+    }
+
+    #[test]
+    fn test_schedule_runs_stages_in_order() {
+        use crate::ecs::schedule::{Schedule, Stage};
+        use crate::sim::simulation::Simulation;
+
+        let mut schedule: Schedule<Vec<&str>> = Schedule::new();
+        schedule
+            .add_system(Stage::Post, "post", |log: &mut Vec<&str>, _| log.push("post"))
+            .add_system(Stage::Input, "input", |log: &mut Vec<&str>, _| log.push("input"))
+            .add_system(Stage::Logic, "a", |log: &mut Vec<&str>, _| log.push("a"))
+            .add_system(Stage::Logic, "b", |log: &mut Vec<&str>, _| log.push("b"));
+        let mut log = Vec::new();
+        schedule.run(&mut log, 0.0);
+        assert_eq!(log, ["input", "a", "b", "post"]);
+        schedule.set_stage_enabled(Stage::Logic, false);
+        log.clear();
+        schedule.run(&mut log, 0.0);
+        assert_eq!(log, ["input", "post"]);
+        assert_eq!(schedule.system_names(Stage::Logic).collect::<Vec<_>>(), ["a", "b"]);
+
+        // Tắt stage grid: board đứng yên, entity vẫn chạy
+        let mut sim = Simulation::new(8, 8, 7);
+        assert!(sim.set_stage_enabled(Stage::Grid as u8, false));
+        assert!(!sim.set_stage_enabled(9, false));
+        let tick = unsafe { (*sim.get_grid()).get_tick_count() };
+        sim.update(100.0);
+        assert_eq!(unsafe { (*sim.get_grid()).get_tick_count() }, tick);
+        assert!(sim.set_stage_enabled(Stage::Grid as u8, true));
+        sim.update(100.0);
+        assert!(unsafe { (*sim.get_grid()).get_tick_count() } > tick);
+    }
+
+    #[test]
+    fn test_parallel_systems_batch_by_access() {
+        use crate::ecs::schedule::{Access, Schedule, Stage};
+
+        #[derive(Default)]
+        struct Ctx { a: u32, b: u32, sum: u32, log: Vec<&'static str> }
+
+        let mut schedule: Schedule<Ctx> = Schedule::new();
+        schedule
+            .add_parallel_system(Stage::Logic, "a", Access::new().write("a"), |c: &Ctx, _| {
+                let a = c.a + 1;
+                Box::new(move |c: &mut Ctx| c.a = a)
+            })
+            .add_parallel_system(Stage::Logic, "b", Access::new().read("a").write("b"), |c: &Ctx, _| {
+                let b = c.b + c.a;
+                Box::new(move |c: &mut Ctx| c.b = b)
+            })
+            .add_parallel_system(Stage::Logic, "c", Access::new().write("log"), |_: &Ctx, _| {
+                Box::new(|c: &mut Ctx| c.log.push("c"))
+            })
+            .add_system(Stage::Logic, "sum", |c: &mut Ctx, _| c.sum = c.a + c.b)
+            .add_parallel_system(Stage::Post, "d", Access::new().read("sum"), |_: &Ctx, _| Box::new(|c: &mut Ctx| c.log.push("d")));
+
+        // b đọc a mà a ghi -> tách batch; c không đụng a / b -> chạy cùng batch với b; sum exclusive
+        assert_eq!(schedule.batches(), [(Stage::Logic, vec![0]), (Stage::Logic, vec![1, 2]), (Stage::Logic, vec![3]), (Stage::Post, vec![0])]);
+        assert!(Access::new().write("x").conflicts(&Access::new().read("x")));
+        assert!(!Access::new().read("x").conflicts(&Access::new().read("x")));
+
+        let mut ctx = Ctx::default();
+        for _ in 0..3 { schedule.run(&mut ctx, 0.0); }
+        // Có bật `parallel` hay không kết quả như nhau
+        assert_eq!((ctx.a, ctx.b, ctx.sum), (3, 6, 9));
+        assert_eq!(ctx.log, ["c", "d", "c", "d", "c", "d"]);
+    }
+
+    #[test]
+    fn test_parallel_conflicting_pair_matches_sequential() {
+        use crate::ecs::schedule::{Access, Schedule, Stage};
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        struct Ctx { value: u64, seen: Vec<u64> }
+
+        // Cả 2 ghi "value" -> 2 batch riêng: y phải thấy value x vừa commit
+        let build = || {
+            let mut schedule: Schedule<Ctx> = Schedule::new();
+            schedule
+                .add_parallel_system(Stage::Logic, "x", Access::new().write("value"), |c: &Ctx, _| {
+                    let value = c.value * 3 + 1;
+                    Box::new(move |c: &mut Ctx| c.value = value)
+                })
+                .add_parallel_system(Stage::Logic, "y", Access::new().read("value").write("value").write("seen"), |c: &Ctx, _| {
+                    let seen = c.value;
+                    Box::new(move |c: &mut Ctx| {
+                        c.seen.push(seen);
+                        c.value += 2;
+                    })
+                });
+            schedule
+        };
+        let mut batched = build();
+        assert_eq!(batched.batches(), [(Stage::Logic, vec![0]), (Stage::Logic, vec![1])]);
+        let mut sequential = build();
+        let (mut a, mut b) = (Ctx::default(), Ctx::default());
+        for _ in 0..4 {
+            batched.run(&mut a, 0.0);
+            sequential.run_sequential(&mut b, 0.0);
+        }
+        assert_eq!(a, b);
+        assert_eq!(a.seen, [1, 10, 37, 118]);
+    }
+
+    #[test]
+    fn test_cell_mapping_round_trip() {
+        use crate::ecs::components::{Position as Pos, Velocity as Vel};
+        use crate::sim::coords::{CellMapping, PositionSpace};
+
+        let mapping = CellMapping { origin_x: 10.0, origin_y: 20.0, cell_size: 32.0 };
+        let world = mapping.cell_to_world(Pos { x: 2.0, y: 3.0 });
+        assert_eq!((world.x, world.y), (10.0 + 2.5 * 32.0, 20.0 + 3.5 * 32.0));
+        let back = mapping.world_to_cell(world);
+        assert_eq!((back.x, back.y), (2.0, 3.0));
+        assert_eq!(mapping.world_to_cell_index(world), (2, 3));
+        assert_eq!(mapping.world_to_cell_index(Pos { x: 9.0, y: 20.0 }), (-1, 0));
+
+        // Entity gắn lưới export ra world, entity thường giữ nguyên
+        let (p, v) = mapping.export(Pos { x: 0.0, y: 0.0 }, Vel { x: 1.0, y: 0.0 }, true, PositionSpace::World);
+        assert_eq!((p.x, p.y, v.x), (26.0, 36.0, 32.0));
+        let (p, _) = mapping.export(Pos { x: 5.0, y: 5.0 }, Vel { x: 0.0, y: 0.0 }, false, PositionSpace::World);
+        assert_eq!((p.x, p.y), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_cell_anchor_follows_falls_and_despawns_on_clear() {
+        use crate::ecs::components::{CellAnchor, EntityDied, GridBound, Position as Pos};
+        use crate::ecs::events::EventReader;
+        use crate::sim::coords::CellMapping;
+        use crate::sim::grid::GridState;
+        use crate::sim::systems::CellBindingSystem;
+
+        let mut grid = GridState::new_empty(4, 4, 0);
+        grid.auto_refill = false;
+        let mapping = CellMapping::default();
+        let mut world = World::new();
+        world.add_event::<EntityDied>();
+        let mut died = EventReader::<EntityDied>::default();
+
+        // Tile Fire ở (0,0) rơi xuống (0,3)
+        grid.set_cell_element(0, 4);
+        let rider = world.create_entity();
+        world.add_component(rider, CellAnchor { cell: 0 });
+        world.add_component(rider, Pos { x: 0.0, y: 0.0 });
+        world.add_component(rider, GridBound);
+        grid.tick();
+        CellBindingSystem::update(&mut world, &grid, &mapping);
+        assert_eq!(world.get_component::<CellAnchor>(rider).unwrap().cell, 12);
+        assert_eq!(world.get_component::<Pos>(rider).unwrap().y, 3.0);
+
+        // Hàng đáy match -> ô (0,3) bị clear -> despawn
+        grid.set_cell_element(13, 4);
+        grid.set_cell_element(14, 4);
+        grid.tick();
+        CellBindingSystem::update(&mut world, &grid, &mapping);
+        assert!(!world.destroy_entity(rider));
+        let events = world.read_events(&mut died);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].entity, events[0].cell), (rider, 12));
+    }
+
+    #[test]
+    fn test_native_api_game_loop() {
+        use crate::api::{EventType, Game, GameConfig, SwapRejectReason};
+
+        let mut game = Game::new(&GameConfig { seed: 42, ..GameConfig::default() });
+        assert_eq!(game.cells().len(), 64);
+        assert!(game.cell(8, 0).is_none());
+        assert_eq!(game.swap((0, 0), (0, 0)), Err(SwapRejectReason::SameCell));
+        assert_eq!(game.swap((9, 0), (0, 0)), Err(SwapRejectReason::OutOfBounds));
+        // y ngoài board (kể cả cực lớn) bị từ chối, không tràn index, vẫn phát SwapRejected
+        assert_eq!(game.swap((0, 7), (0, 8)), Err(SwapRejectReason::OutOfBounds));
+        assert_eq!(game.swap((0, usize::MAX), (0, 0)), Err(SwapRejectReason::OutOfBounds));
+        let rejected = game.drain_events().iter().filter(|e| e.event_type == EventType::SwapRejected as u8).count();
+        assert_eq!(rejected, 4);
+
+        // Tìm 1 swap hợp lệ rồi chạy cascade
+        let mut swapped = false;
+        'search: for y in 0..8 {
+            for x in 0..7 {
+                if game.swap((x, y), (x + 1, y)).is_ok() { swapped = true; break 'search; }
+            }
+        }
+        assert!(swapped);
+        game.run_until_stable(1000);
+        assert!(game.is_stable());
+        assert!(game.score() > 0);
+        assert!(!game.drain_events().is_empty());
+        assert!(game.drain_events().is_empty());
+
+        // Factory wasm đi qua GameConfig: cùng board và luật với GridState::new
+        let (wasm, native) = (crate::create_grid(8, 8, 42), crate::GridState::new(8, 8, 42));
+        assert_eq!((wasm.get_checksum(), wasm.get_rules_hash()), (native.get_checksum(), native.get_rules_hash()));
+    }
+
+    #[test]
+    fn test_replay_detailed_pinpoints_divergence() {
+        use crate::api::{first_divergence, validate_replay_detailed, Game, GameConfig};
+
+        let config = GameConfig { seed: 42, ..GameConfig::default() };
+        let mut game = Game::new(&config);
+        let mut checksums = Vec::new();
+        for _ in 0..3 {
+            let found = (0..8).flat_map(|y| (0..7).map(move |x| (x, y))).any(|(x, y)| game.swap((x, y), (x + 1, y)).is_ok());
+            assert!(found);
+            game.run_until_stable(1000);
+            checksums.push(game.checksum());
+        }
+
+        let journal = game.move_journal();
+        let steps = validate_replay_detailed(&config, &journal);
+        assert_eq!(steps.len(), 3);
+        assert!(steps.iter().all(|s| s.accepted));
+        assert_eq!(steps.iter().map(|s| s.checksum).collect::<Vec<_>>(), checksums);
+        assert_eq!(steps.iter().map(|s| s.score_delta).sum::<u64>(), game.score());
+        assert_eq!(first_divergence(&steps, &checksums), None);
+
+        // Nước thứ 2 bị sửa thành swap không hợp lệ -> lệch đúng tại index 1
+        let mut tampered = journal.clone();
+        tampered[4..8].copy_from_slice(&[0, 0, 0, 0]);
+        let steps = validate_replay_detailed(&config, &tampered);
+        assert!(!steps[1].accepted);
+        assert_eq!(first_divergence(&steps, &checksums), Some(1));
+    }
+
+    #[test]
+    fn test_seed_helpers_are_stable() {
+        use crate::api::{daily_seed, is_legacy_seed, seed_from_string, Game, GameConfig};
+
+        // Giá trị cố định: đổi hash là mọi room code / daily cũ ra board khác
+        assert_eq!(seed_from_string("ROOM-42"), 5805606098097077);
+        assert_eq!(daily_seed(20_000, 0), 6708670943565577);
+        assert_ne!(seed_from_string("ROOM-42"), seed_from_string("room-42"));
+        assert_ne!(daily_seed(20_000, 0), daily_seed(20_001, 0));
+        assert_ne!(daily_seed(20_000, 0), daily_seed(20_000, 1));
+        for seed in [seed_from_string(""), seed_from_string("ROOM-42"), daily_seed(u32::MAX, u64::MAX)] {
+            assert!(seed < 1 << 53);
+            assert!(!is_legacy_seed(seed));
+        }
+
+        let config = GameConfig { seed: seed_from_string("ROOM-42"), ..GameConfig::default() };
+        assert_eq!(Game::new(&config).checksum(), Game::new(&config).checksum());
+    }
+
+    #[test]
+    fn test_shared_rng_reproducible_per_event() {
+        use crate::api::{Game, GameConfig, SharedRng};
+
+        let game = Game::new(&GameConfig { seed: 42, ..GameConfig::default() });
+        let hash = game.state_hash();
+        let draw = |rng: &mut SharedRng| (0..8).map(|_| rng.next_u32()).collect::<Vec<_>>();
+        let a = draw(&mut game.shared_rng(7));
+        // Replay / spectator dựng lại cùng chuỗi, key khác ra chuỗi khác
+        assert_eq!(a, draw(&mut SharedRng::new(42, 7)));
+        assert_ne!(a, draw(&mut game.shared_rng(8)));
+        assert_ne!(a, draw(&mut SharedRng::new(43, 7)));
+        // Không đụng RNG gameplay
+        assert_eq!(game.state_hash(), hash);
+
+        let mut rng = game.shared_rng(1);
+        for _ in 0..100 {
+            assert!((0.0..1.0).contains(&rng.next_float()));
+            assert!((-2.0..2.0).contains(&rng.jitter(2.0)));
+            assert!((3..9).contains(&rng.range(3, 9)));
+        }
+        assert_eq!(rng.range(5, 5), 5);
+    }
+
+    #[test]
+    fn test_replay_sanity_rejects_forged_moves() {
+        use crate::api::{validate_replay_checked, Game, GameConfig, ReplayError, ReplayLimits, ReplayRejection};
+
+        let config = GameConfig { seed: 42, ..GameConfig::default() };
+        let mut game = Game::new(&config);
+        for _ in 0..2 {
+            let found = (0..8).flat_map(|y| (0..7).map(move |x| (x, y))).any(|(x, y)| game.swap((x, y), (x + 1, y)).is_ok());
+            assert!(found);
+            game.run_until_stable(1000);
+        }
+        let journal = game.move_journal();
+        let limits = ReplayLimits::default();
+        assert_eq!(validate_replay_checked(&config, &journal, &[], &limits), Ok(game.score()));
+        assert_eq!(validate_replay_checked(&config, &journal, &[10, 40], &ReplayLimits { min_tick_gap: 30, ..limits }), Ok(game.score()));
+
+        let rejected = |moves: &[u8], ticks: &[u32], limits: &ReplayLimits| match validate_replay_checked(&config, moves, ticks, limits) {
+            Err(ReplayError::Rejected(reason)) => reason,
+            other => panic!("expected rejection, got {:?}", other),
+        };
+        assert_eq!(rejected(&journal[..5], &[], &limits), ReplayRejection::Misaligned);
+        assert_eq!(rejected(&journal, &[], &ReplayLimits { max_moves: 1, ..limits }), ReplayRejection::TooManyMoves { count: 2 });
+        assert_eq!(rejected(&[0, 0, 1, 0, 8, 0, 7, 0], &[], &limits), ReplayRejection::OutOfBounds { index: 1 });
+        assert_eq!(rejected(&[0, 0, 2, 0], &[], &limits), ReplayRejection::NotAdjacent { index: 0 });
+        assert_eq!(rejected(&[0xFF, 0xFF, 9, 0], &[], &limits), ReplayRejection::UnknownBooster { index: 0 });
+        assert_eq!(rejected(&journal, &[10], &limits), ReplayRejection::TickCountMismatch);
+        assert_eq!(rejected(&journal, &[10, 20], &ReplayLimits { min_tick_gap: 30, ..limits }), ReplayRejection::TooFast { index: 1 });
+
+        // validate_replay / verify_replay_file đi qua cùng giới hạn mặc định
+        use crate::api::{verify_replay_file, ReplayHeader};
+        use crate::sim::replay::MAX_REPLAY_MOVES;
+        use crate::sim::grid::GridState;
+        assert_eq!(GridState::validate_replay(8, 8, 42, &journal) as u64, game.score());
+        assert_eq!(GridState::validate_replay(8, 8, 42, &[0, 0, 2, 0]), 0);
+        let (header, _) = ReplayHeader::parse(&game.replay_file()).unwrap();
+        let forge = |moves: &[u8]| ReplayHeader { move_count: (moves.len() / 4) as u32, ..header.clone() }.encode(moves);
+        let flood = [0, 0, 1, 0].repeat(MAX_REPLAY_MOVES as usize + 1);
+        assert_eq!(verify_replay_file(&forge(&flood)), Err(ReplayError::Rejected(ReplayRejection::TooManyMoves { count: MAX_REPLAY_MOVES + 1 })));
+        assert_eq!(verify_replay_file(&forge(&[0, 0, 2, 0])), Err(ReplayError::Rejected(ReplayRejection::NotAdjacent { index: 0 })));
+    }
+
+    #[test]
+    fn test_game_bytes_checkpoint_resumes_exact_state() {
+        use crate::api::{CycleMode, Game, GameConfig, SnapshotError};
+
+        let config = GameConfig { seed: 7, cycle_mode: CycleMode::Destruction, cycle_grace: 2, score_modifier_pct: 150, ..GameConfig::default() };
+        let mut game = Game::new(&config);
+        let play = |g: &mut Game| {
+            let found = (0..8).flat_map(|y| (0..7).map(move |x| (x, y))).any(|(x, y)| g.swap((x, y), (x + 1, y)).is_ok());
+            assert!(found);
+            g.run_until_stable(1000);
+        };
+        play(&mut game);
+
+        let bytes = game.to_bytes();
+        let mut resumed = Game::from_bytes(&bytes).unwrap();
+        assert_eq!(resumed.rules_hash(), game.rules_hash());
+        for g in [&mut game, &mut resumed] {
+            play(g);
+        }
+        assert_eq!((resumed.score(), resumed.checksum()), (game.score(), game.checksum()));
+        assert_eq!(resumed.cycle_state(), game.cycle_state());
+
+        assert!(matches!(Game::from_bytes(&bytes[1..]), Err(SnapshotError::BadHeader)));
+        assert!(matches!(Game::from_bytes(&bytes[..bytes.len() - 1]), Err(SnapshotError::Truncated)));
+    }
+
+    #[test]
+    fn test_game_summary_matches_replay_header() {
+        use crate::api::{verify_replay_file, Game, GameConfig, ReplayHeader};
+        use crate::sim::grid::GridState;
+
+        let mut game = Game::new(&GameConfig { seed: 21, ..GameConfig::default() });
+        for _ in 0..3 {
+            let found = (0..8).flat_map(|y| (0..7).map(move |x| (x, y))).any(|(x, y)| game.swap((x, y), (x + 1, y)).is_ok());
+            assert!(found);
+            game.run_until_stable(1000);
+        }
+        let summary = game.summary();
+        let file = game.replay_file();
+        let (header, _) = ReplayHeader::parse(&file).unwrap();
+        assert_eq!((summary.seed, summary.rules_hash, summary.move_count), (header.seed, header.rules_hash, header.move_count));
+        assert_eq!((summary.score, summary.checksum), (header.final_score, header.final_checksum));
+        assert_eq!(summary.rules_hash, GridState::official_rules_hash(8, 8));
+        assert_eq!(summary.breakdown.total(), game.score());
+        assert_eq!(summary.turns, 3);
+        assert_eq!(verify_replay_file(&file), Ok(game.score()));
+    }
+
+    #[test]
+    fn test_hash_trail_detects_desync_at_first_tick() {
+        use crate::api::{Game, GameConfig};
+        use crate::sim::grid::GridState;
+
+        let config = GameConfig { seed: 9, ..GameConfig::default() };
+        let (mut a, mut b) = (Game::new(&config), Game::new(&config));
+        for g in [&mut a, &mut b] {
+            g.set_hash_trail(2);
+            g.run_until_stable(1000);
+        }
+        for _ in 0..10 {
+            a.step();
+            b.step();
+        }
+        let (first, trail_a) = a.hash_trail();
+        assert_eq!(first % 2, 0);
+        assert_eq!(trail_a, b.hash_trail().1);
+        assert_eq!(GridState::diff_hash_trails(trail_a, b.hash_trail().1), -1);
+
+        // Peer B nhận input lệch -> trail khác ngay ở entry sau nước đó
+        let synced = trail_a.len();
+        a.clear_hash_trail();
+        b.clear_hash_trail();
+        let found = (0..8).flat_map(|y| (0..7).map(move |x| (x, y))).any(|(x, y)| b.swap((x, y), (x + 1, y)).is_ok());
+        assert!(found);
+        for _ in 0..4 {
+            a.step();
+            b.step();
+        }
+        assert!(synced > 0);
+        assert_eq!(GridState::diff_hash_trails(a.hash_trail().1, b.hash_trail().1), 0);
+    }
+
+    #[test]
+    fn test_rollback_resimulates_late_input() {
+        use crate::Simulation;
+        use crate::sim::grid::GridState;
+
+        // Tìm 1 swap hợp lệ trên board của seed này
+        let mut probe = GridState::new(8, 8, 5);
+        for _ in 0..100 { probe.tick(); }
+        let mv = (0..64).map(|i| probe.assist_tap(i % 8, i / 8)).find(|m| !m.is_empty()).unwrap();
+        let input = u32::from_le_bytes([mv[0], mv[1], mv[2], mv[3]]);
+
+        let run = |late: bool| -> Simulation {
+            let mut sim = Simulation::new(8, 8, 5);
+            sim.set_rollback_capacity(8);
+            sim.set_hash_trail(1);
+            for _ in 0..100 { sim.tick_grid(); }
+            let input_frame = sim.save_frame();
+            if !late { assert!(sim.swap(mv[0] as usize, mv[1] as usize, mv[2] as usize, mv[3] as usize)); }
+            sim.tick_grid();
+            for _ in 0..5 {
+                sim.save_frame();
+                sim.tick_grid();
+            }
+            if late {
+                // Input của peer tới trễ 6 frame: quay lại frame của input rồi chạy lại
+                let now = sim.get_tick_count();
+                assert!(sim.rollback_to(input_frame));
+                assert_eq!(sim.get_rollback_frame_count(), 1);
+                assert_eq!(sim.resimulate_with_inputs(now, &[input_frame, input]), 6);
+            }
+            sim
+        };
+        let (on_time, late) = (run(false), run(true));
+        assert_eq!(late.get_tick_count(), on_time.get_tick_count());
+        assert_eq!(late.get_state_hash(), on_time.get_state_hash());
+        // Journal + hash trail không bị mất / nhân đôi qua rollback
+        assert_eq!(late.get_move_journal(), mv);
+        assert_eq!(late.get_move_journal(), on_time.get_move_journal());
+        assert_eq!(late.get_hash_trail_len(), on_time.get_tick_count() as usize);
+        assert_eq!(late.get_hash_trail_first_tick(), on_time.get_hash_trail_first_tick());
+        assert!(late.get_rollback_frame_count() <= 8);
+        assert!(!Simulation::new(8, 8, 5).rollback_to(0));
+    }
+
+    #[test]
+    fn test_replay_diagnosis_reports_divergent_cells() {
+        use crate::api::{diagnose_replay, Game, GameConfig};
+
+        let config = GameConfig { seed: 42, ..GameConfig::default() };
+        let mut game = Game::new(&config);
+        for _ in 0..2 {
+            let found = (0..8).flat_map(|y| (0..7).map(move |x| (x, y))).any(|(x, y)| game.swap((x, y), (x + 1, y)).is_ok());
+            assert!(found);
+            game.run_until_stable(1000);
+        }
+        let journal = game.move_journal();
+        let honest = diagnose_replay(8, 8, 42, &journal, &game.to_bytes()).unwrap();
+        assert!(honest.checksum_match && honest.diff.is_empty());
+        assert_eq!(honest.score, game.score());
+
+        // Client bỏ nước cuối khỏi journal: board và field ẩn đều lệch
+        let cheated = diagnose_replay(8, 8, 42, &journal[..4], &game.to_bytes()).unwrap();
+        assert!(!cheated.checksum_match);
+        assert!(cheated.diff.cell_count > 0);
+        assert_eq!(cheated.diff.cells.len() as u32, cheated.diff.cell_count.min(32));
+        let first = cheated.diff.cells[0];
+        assert_eq!(first.index, first.y * 8 + first.x);
+        assert!(first.element.0 != first.element.1 || first.flags.0 != first.flags.1);
+        let score = cheated.diff.fields.iter().find(|f| f.field == "score").unwrap();
+        assert_eq!((score.left, score.right), (cheated.score, game.score()));
+        assert!(cheated.diff.fields.iter().any(|f| f.field == "turn_count"));
+
+        assert!(game.diff(&Game::from_bytes(&game.to_bytes()).unwrap()).is_empty());
+        assert!(diagnose_replay(8, 8, 42, &journal, b"junk").is_err());
+    }
+
+    #[test]
+    fn test_event_spawn_bindings() {
+        use crate::Simulation;
+
+        let effects = |sim: &mut Simulation| -> Vec<u8> {
+            sim.sync_buffers();
+            let archetypes = unsafe { std::slice::from_raw_parts(sim.get_entity_archetypes_ptr(), sim.get_entities_count()) };
+            archetypes.iter().copied().filter(|&a| a != 0).collect()
+        };
+
+        let mut sim = Simulation::new(8, 8, 7);
+        sim.add_spawn_binding(34, 9, 3, false); // Conversion -> archetype 9, sống 3 tick
+        sim.tick_grid();
+        assert!(effects(&mut sim).is_empty());
+
+        sim.set_cell_element(18, 4);
+        sim.convert_area(18, 0, 2);
+        sim.tick_grid();
+        assert_eq!(effects(&mut sim), vec![9]);
+        // Event đã xử lý không spawn lại
+        sim.tick_grid();
+        assert_eq!(effects(&mut sim), vec![9]);
+
+        // Hết Lifetime -> despawn
+        sim.update(100.0);
+        assert!(effects(&mut sim).is_empty());
+
+        sim.clear_spawn_bindings();
+        sim.convert_area(18, 0, 3);
+        sim.tick_grid();
+        assert!(effects(&mut sim).is_empty());
+    }
+
+    #[test]
+    fn test_autosave_ring_resumes_exact_state() {
+        use crate::Simulation;
+
+        let mut sim = Simulation::new(8, 8, 42);
+        sim.set_autosave(3, 2);
+        sim.update(100.0);
+        // Board đứng yên: chỉ 1 bản dù chạy thêm nhiều tick
+        assert_eq!(sim.get_autosave_count(), 1);
+        sim.update(200.0);
+        assert_eq!(sim.get_autosave_count(), 1);
+
+        // Mỗi nước đi mới -> 1 bản, ring giữ 2 bản mới nhất
+        for _ in 0..3 {
+            let moved = (0..64).any(|i| !sim.assist_tap(i % 8, i / 8).is_empty());
+            assert!(moved);
+            sim.update(250.0);
+            sim.update(250.0);
+        }
+        assert_eq!(sim.get_autosave_count(), 2);
+        let latest = sim.get_autosave(0);
+        assert!(sim.get_autosave(2).is_empty());
+
+        // "Tab bị kill": sim mới resume từ autosave, chơi tiếp ra đúng như sim gốc
+        let mut resumed = Simulation::new(8, 8, 0);
+        assert!(resumed.restore_snapshot(&latest));
+        assert_eq!((resumed.get_score(), resumed.get_checksum()), (sim.get_score(), sim.get_checksum()));
+        for s in [&mut sim, &mut resumed] {
+            let _ = (0..64).any(|i| !s.assist_tap(i % 8, i / 8).is_empty());
+            for _ in 0..1000 { s.tick_grid(); }
+        }
+        assert_eq!((resumed.get_score(), resumed.get_checksum()), (sim.get_score(), sim.get_checksum()));
+
+        assert!(!resumed.restore_snapshot(&latest[..latest.len() - 1]));
+        sim.set_autosave(0, 2);
+        assert_eq!(sim.get_autosave_count(), 0);
+    }
+
+    #[test]
+    fn test_photo_mode_freezes_and_exports_full_frame() {
+        use crate::Simulation;
+
+        let mut sim = Simulation::new(8, 8, 7);
+        sim.update(100.0);
+        // Máy yếu tắt hết event: ảnh chụp vẫn phải có event của frame
+        sim.set_event_mask(0);
+        sim.set_cell_element(18, 4);
+        sim.convert_area(18, 0, 2);
+        assert_eq!(sim.get_events_len(), 0);
+
+        let (tick, checksum) = (unsafe { (*sim.get_grid()).get_tick_count() }, sim.get_checksum());
+        let photo = sim.capture_photo();
+        assert!(sim.is_photo_mode());
+        assert_eq!((photo.width, photo.height, photo.frame), (8, 8, tick));
+        assert_eq!(photo.cells[18] & 0xFF, 2);
+        assert_eq!(photo.events.iter().map(|e| e.event_type).collect::<Vec<_>>(), vec![34]);
+        assert_eq!(photo.entities.len(), sim.get_entities_count());
+
+        // Đóng băng: không tick, không nhận input
+        sim.update(500.0);
+        sim.tick_grid();
+        assert!(!sim.swap(0, 0, 1, 0));
+        assert!(sim.assist_tap(0, 0).is_empty());
+        assert_eq!(unsafe { (*sim.get_grid()).get_tick_count() }, tick);
+        assert_eq!(sim.get_checksum(), checksum);
+
+        sim.exit_photo_mode();
+        sim.update(100.0);
+        assert!(unsafe { (*sim.get_grid()).get_tick_count() } > tick);
+    }
+
+    #[cfg(feature = "gif-export")]
+    #[test]
+    fn test_gif_export_renders_replay() {
+        use crate::api::{Cell, GameConfig};
+        use crate::gif_export::{export_replay_gif, render_board, GifOptions};
+
+        // 2x1 board, ô 4px: Fire | Stone có special -> chấm trắng giữa ô
+        let cells = [Cell { element: 4, flags: 0 }, Cell { element: 10, flags: 64 }];
+        let pixels = render_board(&cells, 2, 4);
+        assert_eq!(pixels.len(), 8 * 4);
+        assert_eq!((pixels[0], pixels[4], pixels[8 + 5]), (4, 6, 8));
+
+        let config = GameConfig { width: 6, height: 6, seed: 1, ..GameConfig::default() };
+        let mut gif = Vec::new();
+        // Nước đi ngoài board bị bỏ qua, vẫn có frame board ban đầu
+        let frames = export_replay_gif(&config, &[9, 9, 9, 8], &GifOptions::default(), &mut gif).unwrap();
+        assert_eq!(frames, 1);
+        assert_eq!(&gif[..6], b"GIF89a");
+    }
 }
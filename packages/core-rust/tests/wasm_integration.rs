@@ -0,0 +1,136 @@
+// Browser-executed integration suite for the zero-copy pointer contracts.
+// Run with: wasm-pack test --headless --chrome (or --firefox)
+#![cfg(target_arch = "wasm32")]
+
+use core_rust::Simulation;
+use js_sys::{Uint32Array, Uint8Array};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn memory_buffer() -> js_sys::ArrayBuffer {
+    wasm_bindgen::memory()
+        .dyn_into::<js_sys::WebAssembly::Memory>()
+        .unwrap()
+        .buffer()
+        .dyn_into::<js_sys::ArrayBuffer>()
+        .unwrap()
+}
+
+// Đọc buffer sự kiện giống hệt cách frontend làm: view Uint32Array trên wasm memory
+fn events_view(sim: &mut Simulation) -> Vec<u32> {
+    let view = Uint32Array::new_with_byte_offset_and_length(
+        &memory_buffer(),
+        sim.get_events_ptr() as u32,
+        sim.get_events_len() as u32,
+    );
+    view.to_vec()
+}
+
+#[wasm_bindgen_test]
+fn cell_buffer_layout_matches_cell_full() {
+    let mut sim = Simulation::new(8, 8, 42);
+    sim.set_cell_flag(5, 8); // WET
+    let bytes = Uint8Array::new_with_byte_offset_and_length(
+        &memory_buffer(),
+        sim.get_cells_ptr() as u32,
+        (sim.get_cells_len() * 2) as u32,
+    )
+    .to_vec();
+    let grid = unsafe { &*sim.get_grid() };
+
+    for idx in 0..sim.get_cells_len() {
+        // Cell = [element, flags], 2 bytes, repr(C), align 1 (không dùng Uint16Array view)
+        let full = grid.get_cell_full(idx);
+        assert_eq!(bytes[idx * 2] as u16, full & 0xFF);
+        assert_eq!(bytes[idx * 2 + 1] as u16, full >> 8);
+    }
+    assert_eq!(bytes[5 * 2 + 1] & 8, 8);
+}
+
+#[wasm_bindgen_test]
+fn event_packing_round_trips_through_uint32array() {
+    let mut sim = Simulation::new(8, 8, 7);
+    sim.clear_events();
+    sim.set_cell_element(18, 4);
+    sim.convert_area(18, 0, 2); // (2,2) Fire -> Wood
+
+    let events = events_view(&mut sim);
+    assert_eq!(events.len(), 1);
+    let e = events[0];
+    assert_eq!(e >> 24, 34);
+    assert_eq!((e >> 16) & 0xFF, 2);
+    assert_eq!((e >> 8) & 0xFF, 2);
+    assert_eq!(e & 0xFF, 2);
+}
+
+#[wasm_bindgen_test]
+fn event_buffer_reads_after_ring_wraparound() {
+    let mut sim = Simulation::new(8, 8, 7);
+    sim.clear_events();
+    sim.set_event_capacity(100);
+
+    // Đổi màu toàn board nhiều lần để ring buffer quay vòng (DropOldest mặc định)
+    let mut pushed = 0;
+    for element in [1u8, 2, 3, 4, 5, 1, 2, 3] {
+        pushed += sim.convert_area(27, 8, element).len();
+    }
+    assert!(pushed > 100);
+
+    // JS lấy pointer sau khi ghi; view phải là 1 đoạn liền gồm 100 event mới nhất
+    let events = events_view(&mut sim);
+    assert_eq!(events.len(), 100);
+    assert_eq!(sim.events_dropped() as usize, pushed - 100);
+    assert!(events.iter().all(|e| (e >> 24) == 34));
+    // Event cuối cùng là lần convert cuối (-> Water)
+    assert_eq!(events[99] & 0xFF, 3);
+}
+
+#[wasm_bindgen_test]
+fn preview_swap_leaves_board_untouched() {
+    let mut sim = Simulation::new(8, 8, 3);
+    let before = sim.get_checksum();
+
+    for y in 0..8 {
+        for x in 0..7 {
+            let preview = Uint32Array::from(&sim.preview_swap(x, y, x + 1, y)[..]).to_vec();
+            // Pairs of [index, type]
+            assert_eq!(preview.len() % 2, 0);
+            for pair in preview.chunks(2) {
+                assert!(pair[0] < 64);
+                assert!(pair[1] <= 2);
+            }
+        }
+    }
+
+    assert_eq!(sim.get_checksum(), before);
+}
+
+// Cùng kịch bản và golden với sim::grid_test::test_determinism_matrix (native).
+// Lệch ở đây mà native pass = có nguồn bất định phụ thuộc nền tảng (usize 32/64 bit...).
+#[wasm_bindgen_test]
+fn determinism_matrix_matches_native_golden() {
+    let matrix: &[((usize, usize, u64), (u32, u32))] = &[
+        ((6, 6, 1), (26100, 255262826)),
+        ((8, 8, 42), (35000, 862847183)),
+        ((8, 8, 1337), (47200, 765591736)),
+        ((10, 7, 9), (75100, 968884433)),
+    ];
+    for &((w, h, seed), golden) in matrix {
+        let mut sim = Simulation::new(w, h, seed);
+        let len = w * h;
+        for turn in 0..20 {
+            let start = (turn * 7) % len;
+            let swapped = (0..len)
+                .map(|k| (start + k) % len)
+                .any(|idx| !sim.assist_tap(idx % w, idx / w).is_empty());
+            if !swapped { break; }
+            for _ in 0..1000 {
+                sim.tick_grid();
+                if unsafe { (*sim.get_grid()).is_stable() } { break; }
+            }
+        }
+        assert_eq!((sim.get_score(), sim.get_checksum()), golden, "{}x{} seed {}", w, h, seed);
+    }
+}